@@ -0,0 +1,33 @@
+fn main() {
+    #[cfg(feature = "capi")]
+    generate_c_header();
+}
+
+// write the header of the C API to `OUT_DIR/rasciify.h`, from the `extern "C"` functions of src/capi.rs
+//
+// the source tree is never written to, unless `RASCIIFY_HEADER_DIR` ask for a copy of the header,
+// e.g. `RASCIIFY_HEADER_DIR=include` to update the header that is shipped in the repository
+#[cfg(feature = "capi")]
+fn generate_c_header() {
+    let crate_directory = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let out_directory = std::env::var("OUT_DIR").unwrap();
+    println!("cargo:rerun-if-changed=src/capi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-env-changed=RASCIIFY_HEADER_DIR");
+
+    let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_directory))
+        .expect("Failed to read cbindgen.toml");
+    let bindings = cbindgen::Builder::new()
+        .with_crate(&crate_directory)
+        .with_config(config)
+        .generate()
+        .expect("Failed to generate the C header");
+    bindings.write_to_file(format!("{}/rasciify.h", out_directory));
+    // a relative directory is relative to the root of the crate, the same as the other paths of the build
+    if let Ok(header_directory) = std::env::var("RASCIIFY_HEADER_DIR") {
+        let header_directory = std::path::Path::new(&crate_directory).join(header_directory);
+        std::fs::create_dir_all(&header_directory)
+            .expect("Failed to create the directory of RASCIIFY_HEADER_DIR");
+        bindings.write_to_file(header_directory.join("rasciify.h"));
+    }
+}
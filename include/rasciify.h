@@ -0,0 +1,118 @@
+/* Generated by cbindgen from src/capi.rs, do not edit. */
+
+#ifndef RASCIIFY_H
+#define RASCIIFY_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The result of every call, the message of an error is given by `rasciify_last_error_message()`.
+typedef enum RasciifyStatus {
+  RASCIIFY_STATUS_OK = 0,
+  // A required pointer was NULL.
+  RASCIIFY_STATUS_NULL_POINTER = 1,
+  // An option or a name was not valid.
+  RASCIIFY_STATUS_INVALID_ARGUMENT = 2,
+  // The input could not be decoded or converted.
+  RASCIIFY_STATUS_CONVERSION_FAILED = 3,
+  // A bug in rasciify, the message has the details.
+  RASCIIFY_STATUS_PANIC = 4,
+} RasciifyStatus;
+
+// The settings of a conversion, created with `rasciify_config_new()` and freed with `rasciify_config_free()`.
+typedef struct RasciifyConfig RasciifyConfig;
+
+// An RGBA image given out by rasciify, 4 bytes per pixel row by row.
+typedef struct RasciifyImage {
+  uint8_t *data;
+  size_t len;
+  uint32_t width;
+  uint32_t height;
+} RasciifyImage;
+
+// Bytes given out by rasciify, e.g. an encoded GIF.
+typedef struct RasciifyBuffer {
+  uint8_t *data;
+  size_t len;
+} RasciifyBuffer;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// The settings of a conversion with the defaults of `RenderConfig::builder()`.
+struct RasciifyConfig *rasciify_config_new(void);
+
+// Free a config from `rasciify_config_new()`, NULL is ignored.
+void rasciify_config_free(struct RasciifyConfig *config);
+
+// Set the character type by name, e.g. "simple", "bar" or "zh_simplified".
+enum RasciifyStatus rasciify_config_set_character_type(struct RasciifyConfig *config,
+                                                       const char *name);
+
+// Set the number of columns of the ASCII art.
+enum RasciifyStatus rasciify_config_set_num_cols(struct RasciifyConfig *config, uint32_t num_cols);
+
+// Convert in the colors of the image instead of grayscale.
+enum RasciifyStatus rasciify_config_set_color(struct RasciifyConfig *config, bool is_color);
+
+// Draw the ASCII art on a white background.
+enum RasciifyStatus rasciify_config_set_white_bg(struct RasciifyConfig *config, bool is_white_bg);
+
+// Leave the background of the ASCII art transparent.
+enum RasciifyStatus rasciify_config_set_transparent_bg(struct RasciifyConfig *config,
+                                                       bool is_transparent_bg);
+
+// Set a color theme by name, e.g. "amber" or "green_phosphor".
+enum RasciifyStatus rasciify_config_set_color_theme(struct RasciifyConfig *config,
+                                                    const char *name);
+
+// Restrict the colors to a palette preset by name, e.g. "gameboy" or "xterm256".
+enum RasciifyStatus rasciify_config_set_palette(struct RasciifyConfig *config, const char *name);
+
+// Set the number of GIF frames converted at a time.
+enum RasciifyStatus rasciify_config_set_frame_window(struct RasciifyConfig *config,
+                                                     size_t frame_window);
+
+// Convert an encoded image (PNG, JPEG, ...) to ASCII art text, only the "simple" and "complex" character types are supported.
+// The text is written to `out_text` and must be freed with `rasciify_string_free()`.
+enum RasciifyStatus rasciify_image_to_text(const struct RasciifyConfig *config,
+                                           const uint8_t *data,
+                                           size_t len,
+                                           char **out_text);
+
+// Convert an encoded image (PNG, JPEG, ...) to an RGBA ASCII art image.
+// The image is written to `out_image` and must be freed with `rasciify_image_free()`.
+enum RasciifyStatus rasciify_image_to_image(const struct RasciifyConfig *config,
+                                            const uint8_t *data,
+                                            size_t len,
+                                            struct RasciifyImage *out_image);
+
+// Convert an encoded GIF to an ASCII art GIF.
+// The GIF is written to `out_buffer` and must be freed with `rasciify_buffer_free()`.
+enum RasciifyStatus rasciify_gif_to_gif(const struct RasciifyConfig *config,
+                                        const uint8_t *data,
+                                        size_t len,
+                                        struct RasciifyBuffer *out_buffer);
+
+// Free a string given out by rasciify, NULL is ignored.
+void rasciify_string_free(char *text);
+
+// Free a buffer given out by rasciify.
+void rasciify_buffer_free(struct RasciifyBuffer buffer);
+
+// Free an image given out by rasciify.
+void rasciify_image_free(struct RasciifyImage image);
+
+// The message of the last failed call on this thread, NULL if no call failed.
+// The message belongs to rasciify and is valid until the next failed call on this thread.
+const char *rasciify_last_error_message(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* RASCIIFY_H */
@@ -0,0 +1,360 @@
+use std::sync::OnceLock;
+
+use crate::{Palette, PalettePreset};
+
+// a color set by an SGR escape code
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum AnsiColor {
+    Indexed(u8), // index into the 256 color palette, the first 16 depend on the AnsiColorScheme
+    Rgb([u8; 3]),
+}
+
+// the colors used for the first 16 ANSI colors
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct AnsiColorScheme {
+    pub basic_color_list: [[u8; 3]; 16],
+    // blink turns the background to its bright version instead, which most ANSI art rely on
+    pub is_ice_color: bool,
+}
+
+impl AnsiColorScheme {
+    // the colors of xterm, used for the text of a modern terminal
+    pub fn xterm() -> AnsiColorScheme {
+        let mut basic_color_list = [[0; 3]; 16];
+        basic_color_list
+            .copy_from_slice(&Palette::from_preset(PalettePreset::Xterm256).get_color_list()[..16]);
+        return AnsiColorScheme {
+            basic_color_list,
+            is_ice_color: false,
+        };
+    }
+
+    // the colors of the VGA text mode, used by ANSI art
+    pub fn vga() -> AnsiColorScheme {
+        let mut basic_color_list = [[0; 3]; 16];
+        basic_color_list.copy_from_slice(Palette::from_preset(PalettePreset::Ega).get_color_list());
+        // the EGA palette is in the order of the PC hardware, which has red and blue the other way round from ANSI
+        let ansi_order: [usize; 8] = [0, 4, 2, 6, 1, 5, 3, 7];
+        let ega_color_list = basic_color_list;
+        for (ansi_index, ega_index) in ansi_order.iter().enumerate() {
+            basic_color_list[ansi_index] = ega_color_list[*ega_index];
+            basic_color_list[ansi_index + 8] = ega_color_list[*ega_index + 8];
+        }
+        return AnsiColorScheme {
+            basic_color_list,
+            is_ice_color: true,
+        };
+    }
+
+    pub fn get_color(&self, ansi_color: AnsiColor) -> [u8; 3] {
+        match ansi_color {
+            AnsiColor::Indexed(index) if index < 16 => {
+                return self.basic_color_list[index as usize];
+            }
+            AnsiColor::Indexed(index) => return get_ansi_256_color(index),
+            AnsiColor::Rgb(rgb) => return rgb,
+        }
+    }
+}
+
+impl Default for AnsiColorScheme {
+    fn default() -> AnsiColorScheme {
+        return AnsiColorScheme::xterm();
+    }
+}
+
+// the color and attributes set by the SGR escape codes (`ESC[...m`), `None` means the default color
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct AnsiStyle {
+    pub foreground: Option<AnsiColor>,
+    pub background: Option<AnsiColor>,
+    pub is_bold: bool,
+    pub is_blink: bool,
+    pub is_inverse: bool,
+}
+
+impl AnsiStyle {
+    // apply the parameters of a single SGR escape code, e.g. [1, 31] of `ESC[1;31m`
+    pub fn apply_sgr(&mut self, param_list: &[u16]) {
+        if param_list.is_empty() {
+            *self = AnsiStyle::default();
+            return;
+        }
+
+        let mut i = 0;
+        while i < param_list.len() {
+            match param_list[i] {
+                0 => *self = AnsiStyle::default(),
+                1 => self.is_bold = true,
+                22 => self.is_bold = false,
+                5 | 6 => self.is_blink = true,
+                25 => self.is_blink = false,
+                7 => self.is_inverse = true,
+                27 => self.is_inverse = false,
+                code @ 30..=37 => self.foreground = Some(AnsiColor::Indexed((code - 30) as u8)),
+                code @ 90..=97 => self.foreground = Some(AnsiColor::Indexed((code - 90 + 8) as u8)),
+                code @ 40..=47 => self.background = Some(AnsiColor::Indexed((code - 40) as u8)),
+                code @ 100..=107 => {
+                    self.background = Some(AnsiColor::Indexed((code - 100 + 8) as u8))
+                }
+                39 => self.foreground = None,
+                49 => self.background = None,
+                // 38;5;n / 38;2;r;g;b and the same for the background with 48
+                code @ (38 | 48) => {
+                    let (color, num_params) = parse_extended_color(&param_list[i + 1..]);
+                    if let Some(color) = color {
+                        if code == 38 {
+                            self.foreground = Some(color);
+                        } else {
+                            self.background = Some(color);
+                        }
+                    }
+                    i += num_params;
+                }
+                // the other attributes (italic, underline, ...) can't be shown by a character cell
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+
+    // the foreground and background after bold, blink and inverse were applied,
+    // a `None` background means the default background is shown
+    //
+    // bold turns the 8 basic foreground colors to their bright version, as the old terminals did
+    pub fn get_color_pair(
+        &self,
+        color_scheme: &AnsiColorScheme,
+        default_foreground: [u8; 3],
+        default_background: [u8; 3],
+    ) -> ([u8; 3], Option<[u8; 3]>) {
+        let foreground = match self.foreground {
+            Some(AnsiColor::Indexed(index)) if self.is_bold && index < 8 => {
+                color_scheme.get_color(AnsiColor::Indexed(index + 8))
+            }
+            Some(foreground) => color_scheme.get_color(foreground),
+            None => default_foreground,
+        };
+        let background = match self.background {
+            Some(AnsiColor::Indexed(index))
+                if self.is_blink && color_scheme.is_ice_color && index < 8 =>
+            {
+                Some(color_scheme.get_color(AnsiColor::Indexed(index + 8)))
+            }
+            Some(background) => Some(color_scheme.get_color(background)),
+            None => None,
+        };
+
+        if self.is_inverse {
+            return (background.unwrap_or(default_background), Some(foreground));
+        }
+        return (foreground, background);
+    }
+}
+
+// a character of a text together with the style it was written in
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct AnsiChar {
+    pub character: char,
+    pub style: AnsiStyle,
+}
+
+// split a text with ANSI escape codes into lines of styled characters
+//
+// the SGR codes set the style of the characters after them, the other escape codes are left out
+// and a tab is expanded to the next multiple of 8 columns
+pub fn parse_ansi_text(text: &str) -> Vec<Vec<AnsiChar>> {
+    let mut line_list: Vec<Vec<AnsiChar>> = vec![];
+    let mut line: Vec<AnsiChar> = vec![];
+    let mut style = AnsiStyle::default();
+    let mut char_iter = text.chars().peekable();
+
+    while let Some(character) = char_iter.next() {
+        match character {
+            '\x1b' => {
+                if let Some((param_list, final_byte)) = read_escape_code(&mut char_iter) {
+                    if final_byte == 'm' {
+                        style.apply_sgr(&param_list);
+                    }
+                }
+            }
+            '\n' => line_list.push(std::mem::take(&mut line)),
+            '\t' => {
+                let num_spaces = 8 - line.len() % 8;
+                for _ in 0..num_spaces {
+                    line.push(AnsiChar {
+                        character: ' ',
+                        style,
+                    });
+                }
+            }
+            // the other control characters (e.g. `\r`) take no space
+            character if character.is_control() => {}
+            character => line.push(AnsiChar { character, style }),
+        }
+    }
+    if !line.is_empty() {
+        line_list.push(line);
+    }
+
+    return line_list;
+}
+
+// read the rest of an escape code after `ESC`, return the parameters and the final byte of a CSI code (`ESC[...X`)
+// `None` is returned for the other escape codes, which are skipped
+//
+// the string of an OSC (`ESC]`, e.g. the title of the window), DCS, SOS, PM or APC code is skipped
+// up to the BEL or ST (`ESC\\`) that end it
+pub fn read_escape_code<I: Iterator<Item = char>>(
+    char_iter: &mut std::iter::Peekable<I>,
+) -> Option<(Vec<u16>, char)> {
+    if matches!(char_iter.peek(), Some(']' | 'P' | 'X' | '^' | '_')) {
+        char_iter.next();
+        while let Some(character) = char_iter.next() {
+            if character == '\x07' {
+                break;
+            }
+            if character == '\x1b' {
+                if char_iter.peek() == Some(&'\\') {
+                    char_iter.next();
+                }
+                break;
+            }
+        }
+        return None;
+    }
+    if char_iter.peek() != Some(&'[') {
+        // a two character escape code, e.g. `ESC7`
+        char_iter.next();
+        return None;
+    }
+    char_iter.next();
+
+    // read the parameters until the final byte of the escape code
+    let mut param_text = String::new();
+    for character in char_iter.by_ref() {
+        if ('\x40'..='\x7e').contains(&character) {
+            // private parameters (e.g. `ESC[?7h`) are not used
+            let param_list = param_text
+                .trim_start_matches(['?', '<', '=', '>'])
+                .split(';')
+                .filter(|param| !param.is_empty())
+                // a value too large for a u16 is capped, so it is not read as a small value
+                .map(|param| {
+                    param
+                        .parse::<u64>()
+                        .map_or(0, |value| value.min(u16::MAX as u64) as u16)
+                })
+                .collect();
+            return Some((param_list, character));
+        }
+        param_text.push(character);
+    }
+    return None;
+}
+
+// the xterm 256 color palette, built on the first use
+static ANSI_256_PALETTE: OnceLock<Palette> = OnceLock::new();
+
+// the color of the xterm 256 color palette, the first 16 are the basic and bright colors
+pub fn get_ansi_256_color(index: u8) -> [u8; 3] {
+    let palette = ANSI_256_PALETTE.get_or_init(|| Palette::from_preset(PalettePreset::Xterm256));
+    return palette.get_color_list()[index as usize];
+}
+
+// parse the color after 38 or 48, return the color and the number of parameters it used
+//
+// a value above 255 is not a color, its parameters are still used so they are not read as other codes
+fn parse_extended_color(param_list: &[u16]) -> (Option<AnsiColor>, usize) {
+    let to_u8 = |value: &u16| u8::try_from(*value).ok();
+    match param_list {
+        [5, index, ..] => return (to_u8(index).map(AnsiColor::Indexed), 2),
+        [2, r, g, b, ..] => {
+            let rgb = to_u8(r).zip(to_u8(g)).zip(to_u8(b));
+            return (rgb.map(|((r, g), b)| AnsiColor::Rgb([r, g, b])), 4);
+        }
+        [5, ..] | [2, ..] => return (None, param_list.len()),
+        _ => return (None, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_style(text: &str) -> AnsiStyle {
+        return parse_ansi_text(text)[0][0].style;
+    }
+
+    #[test]
+    fn apply_basic_sgr() {
+        let style = parse_style("\x1b[1;5;7;32;104mx");
+        assert!(style.is_bold && style.is_blink && style.is_inverse);
+        assert_eq!(style.foreground, Some(AnsiColor::Indexed(2)));
+        assert_eq!(style.background, Some(AnsiColor::Indexed(12)));
+        assert_eq!(parse_style("\x1b[1;31m\x1b[mx"), AnsiStyle::default());
+        assert_eq!(parse_style("\x1b[31;39mx").foreground, None);
+    }
+
+    #[test]
+    fn apply_extended_color_sgr() {
+        let style = parse_style("\x1b[38;5;196;48;2;10;20;30mx");
+        assert_eq!(style.foreground, Some(AnsiColor::Indexed(196)));
+        assert_eq!(style.background, Some(AnsiColor::Rgb([10, 20, 30])));
+        assert_eq!(get_ansi_256_color(196), [255, 0, 0]);
+        assert_eq!(get_ansi_256_color(232), [8, 8, 8]);
+    }
+
+    #[test]
+    fn skip_extended_color_above_255() {
+        // the out of range color is ignored, and its parameters are not read as bold or blink
+        let style = parse_style("\x1b[32;38;5;300;1mx");
+        assert_eq!(style.foreground, Some(AnsiColor::Indexed(2)));
+        assert!(style.is_bold);
+        let style = parse_style("\x1b[38;2;1;5;256mx");
+        assert_eq!(style, AnsiStyle::default());
+        let style = parse_style("\x1b[38;2;1;99999999999mx");
+        assert_eq!(style, AnsiStyle::default());
+    }
+
+    #[test]
+    fn skip_other_escape_codes() {
+        let line_list = parse_ansi_text("\x1b]0;title\x07a\x1b[2Kb\x1b]8;;url\x1b\\c\x1b7d");
+        let text: String = line_list[0]
+            .iter()
+            .map(|ansi_char| ansi_char.character)
+            .collect();
+        assert_eq!(text, "abcd");
+    }
+
+    #[test]
+    fn expand_tab_and_split_lines() {
+        let line_list = parse_ansi_text("ab\tc\r\nd");
+        assert_eq!(line_list.len(), 2);
+        assert_eq!(line_list[0].len(), 9);
+        assert_eq!(line_list[0][8].character, 'c');
+        assert_eq!(line_list[1][0].character, 'd');
+    }
+
+    #[test]
+    fn get_color_pair_with_color_scheme() {
+        let xterm = AnsiColorScheme::xterm();
+        let style = parse_style("\x1b[7;31mx");
+        assert_eq!(
+            style.get_color_pair(&xterm, [255, 255, 255], [0, 0, 0]),
+            ([0, 0, 0], Some(get_ansi_256_color(1)))
+        );
+
+        // bold turns the foreground bright, and blink turns the background bright with ice colors
+        let style = parse_style("\x1b[1;5;31;44mx");
+        let vga = AnsiColorScheme::vga();
+        assert_eq!(
+            style.get_color_pair(&vga, [255, 255, 255], [0, 0, 0]),
+            (vga.basic_color_list[9], Some(vga.basic_color_list[12]))
+        );
+        assert_eq!(
+            style.get_color_pair(&xterm, [255, 255, 255], [0, 0, 0]),
+            (xterm.basic_color_list[9], Some(xterm.basic_color_list[4]))
+        );
+    }
+}
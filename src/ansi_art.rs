@@ -0,0 +1,505 @@
+use std::fs::{read, write};
+
+use crate::{
+    ansi::{read_escape_code, AnsiChar, AnsiColorScheme, AnsiStyle},
+    ascii_grid::AsciiGrid,
+    character::CharacterType,
+    text_to_img::ansi_line_list_to_ascii_grid,
+    utils::utils::{get_output_path, get_saved_message},
+    SettingOption,
+};
+
+// the width of the screen an ANSI art is drawn on, if its SAUCE record doesn't tell
+pub const DEFAULT_ANSI_ART_WIDTH: u32 = 80;
+// the largest screen an ANSI art can be drawn on in cells (columns x lines), e.g. 12500 lines of 80 columns,
+// a cursor movement is kept within it and an art that write past it is rejected,
+// so a broken or hostile file can't grow the screen (and the image it is drawn to) without end
+pub const MAX_ANSI_ART_CELLS: usize = 1_000_000;
+
+const SAUCE_RECORD_SIZE: usize = 128;
+const SAUCE_COMMENT_LINE_SIZE: usize = 64;
+// the character that ends the art, anything after it is the SAUCE record
+const SUB: char = '\x1a';
+
+// the characters of code page 437 for the bytes 0x00 to 0x1F, shown by DOS in place of the control characters
+const CP437_LOW_CHARACTER_LIST: [char; 32] = [
+    ' ', '☺', '☻', '♥', '♦', '♣', '♠', '•', '◘', '○', '◙', '♂', '♀', '♪', '♫', '☼', '►', '◄', '↕',
+    '‼', '¶', '§', '▬', '↨', '↑', '↓', '→', '←', '∟', '↔', '▲', '▼',
+];
+
+// the characters of code page 437 for the bytes 0x80 to 0xFF
+const CP437_HIGH_CHARACTER_LIST: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', 'É', 'æ', 'Æ',
+    'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ', 'á', 'í', 'ó', 'ú', 'ñ', 'Ñ',
+    'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»', '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕',
+    '╣', '║', '╗', '╝', '╜', '╛', '┐', '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦',
+    '╠', '═', '╬', '╧', '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐',
+    '▀', 'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩', '≡', '±',
+    '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', ' ',
+];
+
+// the metadata that most ANSI art files carry at their end, see https://www.acid.org/info/sauce/sauce.htm
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Sauce {
+    pub title: String,
+    pub author: String,
+    pub group: String,
+    pub date: String, // CCYYMMDD
+    pub file_size: u32,
+    pub data_type: u8,
+    pub file_type: u8,
+    pub tinfo1: u16,
+    pub tinfo2: u16,
+    pub tinfo3: u16,
+    pub tinfo4: u16,
+    pub flags: u8,
+    pub tinfos: String,
+    pub comment_list: Vec<String>,
+}
+
+impl Sauce {
+    // the number of columns of a character file (ASCII, ANSI or ANSiMation), if it is set
+    pub fn get_width(&self) -> Option<u32> {
+        if self.data_type == 1 && self.file_type <= 2 && self.tinfo1 > 0 {
+            return Some(self.tinfo1 as u32);
+        }
+        return None;
+    }
+
+    // the iCE color flag, blink shows a bright background instead
+    pub fn is_ice_color(&self) -> bool {
+        return self.data_type == 1 && self.flags & 1 == 1;
+    }
+}
+
+// the screen of an ANSI art after all of its escape codes were played
+#[derive(PartialEq, Debug, Clone)]
+pub struct AnsiArt {
+    pub width: u32,
+    // every line is `width` characters long, an unwritten cell is a space without a color
+    pub line_list: Vec<Vec<AnsiChar>>,
+    pub sauce: Option<Sauce>,
+}
+
+impl AnsiArt {
+    // the VGA colors the art was drawn with, with iCE colors if the SAUCE record ask for them
+    pub fn get_color_scheme(&self) -> AnsiColorScheme {
+        let mut color_scheme = AnsiColorScheme::vga();
+        color_scheme.is_ice_color = self
+            .sauce
+            .as_ref()
+            .map(|sauce| sauce.is_ice_color())
+            .unwrap_or(false);
+        return color_scheme;
+    }
+}
+
+// Converts an ANSI art file (.ans) to an image and save it as png.
+pub fn ansi_art_to_image(
+    path: &str,
+    character_type: CharacterType,
+    output_directory: Option<&str>,
+    filename: Option<&str>,
+    setting_option: SettingOption,
+) -> Result<String, String> {
+    let ansi_art = load_ansi_art(path)?;
+    let ascii_grid = ansi_art_to_ascii_grid(&ansi_art, character_type, setting_option);
+
+    let path = get_output_path(output_directory, filename, "png")?;
+    ascii_grid
+        .to_rgba_img()
+        .save(&path)
+        .map_err(|e| format!("Failed to save {}: {}", path, e))?;
+
+    return Ok(get_saved_message(
+        "Image",
+        output_directory,
+        filename,
+        "png",
+    ));
+}
+
+// Converts an ANSI art file (.ans) to a html page and save it as html.
+pub fn ansi_art_to_html(
+    path: &str,
+    character_type: CharacterType,
+    output_directory: Option<&str>,
+    filename: Option<&str>,
+    setting_option: SettingOption,
+) -> Result<String, String> {
+    let ansi_art = load_ansi_art(path)?;
+    let ascii_grid = ansi_art_to_ascii_grid(&ansi_art, character_type, setting_option);
+
+    let path = get_output_path(output_directory, filename, "html")?;
+    write(&path, ascii_grid.to_html()).map_err(|e| format!("Failed to save {}: {}", path, e))?;
+
+    return Ok(get_saved_message(
+        "Html",
+        output_directory,
+        filename,
+        "html",
+    ));
+}
+
+// put the characters of an ANSI art into an AsciiGrid
+//
+// the art is drawn in the VGA colors, light gray on black by default, or in the colors of the color theme,
+// the colors are left out if `is_color` is not set
+pub fn ansi_art_to_ascii_grid(
+    ansi_art: &AnsiArt,
+    character_type: CharacterType,
+    setting_option: SettingOption,
+) -> AsciiGrid {
+    let color_scheme = ansi_art.get_color_scheme();
+    let default_color_pair = match setting_option.color_theme {
+        Some(color_theme) => (color_theme.get_color(255), color_theme.get_color(0)),
+        None => (
+            color_scheme.basic_color_list[7],
+            color_scheme.basic_color_list[0],
+        ),
+    };
+    return ansi_line_list_to_ascii_grid(
+        &ansi_art.line_list,
+        ansi_art.width,
+        character_type,
+        setting_option,
+        &color_scheme,
+        default_color_pair,
+    );
+}
+
+pub fn load_ansi_art(path: &str) -> Result<AnsiArt, String> {
+    let byte_list = read(path).map_err(|e| format!("Failed to read ANSI art: {}", e))?;
+    return parse_ansi_art(&byte_list);
+}
+
+// play the escape codes of an ANSI art on a virtual screen
+//
+// the art is read as UTF-8 if it is valid UTF-8, otherwise as code page 437
+// the cursor movement (`ESC[A`/`B`/`C`/`D`, `ESC[H`, `ESC[s`/`u`), erase (`ESC[2J`, `ESC[K`) and SGR codes are played,
+// the other escape codes are left out
+//
+// an art with more cells than `MAX_ANSI_ART_CELLS` is an error
+pub fn parse_ansi_art(byte_list: &[u8]) -> Result<AnsiArt, String> {
+    let (content, sauce) = split_sauce(byte_list);
+    let width = sauce
+        .as_ref()
+        .and_then(|sauce| sauce.get_width())
+        .unwrap_or(DEFAULT_ANSI_ART_WIDTH);
+    let is_utf8 = std::str::from_utf8(content).is_ok();
+
+    let mut screen = AnsiScreen::new(width as usize);
+    let mut char_iter = decode_character_list(content, is_utf8)
+        .into_iter()
+        .peekable();
+    while let Some(character) = char_iter.next() {
+        match character {
+            SUB => break,
+            '\x1b' => {
+                if let Some((param_list, final_byte)) = read_escape_code(&mut char_iter) {
+                    screen.play_escape_code(&param_list, final_byte);
+                }
+            }
+            '\n' => {
+                screen.row += 1;
+                screen.col = 0;
+            }
+            '\r' => screen.col = 0,
+            '\t' => {
+                let col = screen.col.min(width as usize - 1);
+                screen.col = ((col / 8 + 1) * 8).min(width as usize - 1);
+            }
+            '\x00'..='\x1f' => screen.put(CP437_LOW_CHARACTER_LIST[character as usize]),
+            '\x7f' if !is_utf8 => screen.put('⌂'),
+            character => screen.put(character),
+        }
+        if screen.row >= screen.max_rows {
+            return Err(format!(
+                "ANSI art is larger than {} cells, {} lines of {} columns",
+                MAX_ANSI_ART_CELLS, screen.max_rows, width
+            ));
+        }
+    }
+
+    return Ok(AnsiArt {
+        width,
+        line_list: screen.line_list,
+        sauce,
+    });
+}
+
+// split the SAUCE record (and its comments) from the end of an ANSI art file
+pub fn split_sauce(byte_list: &[u8]) -> (&[u8], Option<Sauce>) {
+    if byte_list.len() < SAUCE_RECORD_SIZE {
+        return (byte_list, None);
+    }
+    let record_start = byte_list.len() - SAUCE_RECORD_SIZE;
+    let record = &byte_list[record_start..];
+    if &record[0..7] != b"SAUCE00" {
+        return (byte_list, None);
+    }
+
+    let read_u16 = |offset: usize| u16::from_le_bytes([record[offset], record[offset + 1]]);
+    let mut sauce = Sauce {
+        title: decode_sauce_field(&record[7..42]),
+        author: decode_sauce_field(&record[42..62]),
+        group: decode_sauce_field(&record[62..82]),
+        date: decode_sauce_field(&record[82..90]),
+        file_size: u32::from_le_bytes([record[90], record[91], record[92], record[93]]),
+        data_type: record[94],
+        file_type: record[95],
+        tinfo1: read_u16(96),
+        tinfo2: read_u16(98),
+        tinfo3: read_u16(100),
+        tinfo4: read_u16(102),
+        flags: record[105],
+        tinfos: decode_sauce_field(&record[106..128]),
+        comment_list: vec![],
+    };
+
+    // the comment block is `COMNT` followed by the 64 character comment lines
+    let num_comments = record[104] as usize;
+    let comment_size = 5 + num_comments * SAUCE_COMMENT_LINE_SIZE;
+    let mut content_end = record_start;
+    if num_comments > 0 && record_start >= comment_size {
+        let comment_start = record_start - comment_size;
+        if &byte_list[comment_start..comment_start + 5] == b"COMNT" {
+            sauce.comment_list = byte_list[comment_start + 5..record_start]
+                .chunks(SAUCE_COMMENT_LINE_SIZE)
+                .map(decode_sauce_field)
+                .collect();
+            content_end = comment_start;
+        }
+    }
+
+    return (&byte_list[..content_end], Some(sauce));
+}
+
+// the character of code page 437 for a byte
+pub fn get_cp437_character(byte: u8) -> char {
+    match byte {
+        0x00..=0x1f => return CP437_LOW_CHARACTER_LIST[byte as usize],
+        0x7f => return '⌂',
+        0x80..=0xff => return CP437_HIGH_CHARACTER_LIST[(byte - 0x80) as usize],
+        _ => return byte as char,
+    }
+}
+
+// the control characters are kept as they are, they are turned into the CP437 characters while playing the art
+fn decode_character_list(byte_list: &[u8], is_utf8: bool) -> Vec<char> {
+    if is_utf8 {
+        return String::from_utf8_lossy(byte_list).chars().collect();
+    }
+    return byte_list
+        .iter()
+        .map(|byte| {
+            if *byte >= 0x80 {
+                get_cp437_character(*byte)
+            } else {
+                *byte as char
+            }
+        })
+        .collect();
+}
+
+// the SAUCE fields are padded with spaces (or sometimes zeros)
+fn decode_sauce_field(byte_list: &[u8]) -> String {
+    return byte_list
+        .iter()
+        .take_while(|byte| **byte != 0)
+        .map(|byte| get_cp437_character(*byte))
+        .collect::<String>()
+        .trim_end()
+        .to_string();
+}
+
+// the screen the ANSI art is played on, it grow downwards as the cursor moves down
+struct AnsiScreen {
+    width: usize,
+    // the number of lines of `width` that fit in `MAX_ANSI_ART_CELLS`
+    max_rows: usize,
+    line_list: Vec<Vec<AnsiChar>>,
+    row: usize,
+    // the column can be `width` after the last column was written, the next character then wraps to the next line
+    col: usize,
+    saved_cursor: (usize, usize),
+    style: AnsiStyle,
+}
+
+impl AnsiScreen {
+    fn new(width: usize) -> AnsiScreen {
+        return AnsiScreen {
+            width,
+            max_rows: (MAX_ANSI_ART_CELLS / width).max(1),
+            line_list: vec![],
+            row: 0,
+            col: 0,
+            saved_cursor: (0, 0),
+            style: AnsiStyle::default(),
+        };
+    }
+
+    fn put(&mut self, character: char) {
+        if self.col >= self.width {
+            self.row += 1;
+            self.col = 0;
+        }
+        let row = self.row;
+        let col = self.col;
+        let style = self.style;
+        self.get_line(row)[col] = AnsiChar { character, style };
+        self.col += 1;
+    }
+
+    fn play_escape_code(&mut self, param_list: &[u16], final_byte: char) {
+        // the cursor movements take a count of at least 1
+        let count = param_list.first().copied().unwrap_or(1).max(1) as usize;
+        let last_col = self.width - 1;
+        let last_row = self.max_rows - 1;
+        match final_byte {
+            'A' => self.row = self.row.saturating_sub(count),
+            'B' => self.row = (self.row + count).min(last_row),
+            'C' => self.col = (self.col + count).min(last_col),
+            'D' => self.col = self.col.min(last_col).saturating_sub(count),
+            // 1-based row;col
+            'H' | 'f' => {
+                let row = param_list.first().copied().unwrap_or(1).max(1) as usize;
+                let col = param_list.get(1).copied().unwrap_or(1).max(1) as usize;
+                self.row = (row - 1).min(last_row);
+                self.col = (col - 1).min(last_col);
+            }
+            's' => self.saved_cursor = (self.row, self.col),
+            'u' => (self.row, self.col) = self.saved_cursor,
+            'J' if param_list.first() == Some(&2) => {
+                self.line_list.clear();
+                self.row = 0;
+                self.col = 0;
+            }
+            'K' => {
+                let col = self.col.min(last_col);
+                let row = self.row;
+                let line = self.get_line(row);
+                let erase_range = match param_list.first().copied().unwrap_or(0) {
+                    0 => col..line.len(),
+                    1 => 0..col + 1,
+                    _ => 0..line.len(),
+                };
+                line[erase_range].fill(get_blank_character());
+            }
+            'm' => self.style.apply_sgr(param_list),
+            _ => {}
+        }
+    }
+
+    fn get_line(&mut self, row: usize) -> &mut Vec<AnsiChar> {
+        while self.line_list.len() <= row {
+            self.line_list.push(vec![get_blank_character(); self.width]);
+        }
+        return &mut self.line_list[row];
+    }
+}
+
+fn get_blank_character() -> AnsiChar {
+    return AnsiChar {
+        character: ' ',
+        style: AnsiStyle::default(),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AnsiColor;
+
+    // a SAUCE record of an ANSI file, the text fields are padded with spaces
+    fn get_sauce_record(width: u16, num_comments: u8, flags: u8) -> Vec<u8> {
+        let mut record = b"SAUCE00".to_vec();
+        for (field, size) in [
+            ("Title", 35),
+            ("Author", 20),
+            ("Group", 20),
+            ("20240101", 8),
+        ] {
+            record.extend_from_slice(field.as_bytes());
+            record.resize(record.len() + size - field.len(), b' ');
+        }
+        record.extend_from_slice(&12u32.to_le_bytes());
+        record.extend_from_slice(&[1, 1]);
+        record.extend_from_slice(&width.to_le_bytes());
+        record.extend_from_slice(&[25, 0, 0, 0, 0, 0]);
+        record.extend_from_slice(&[num_comments, flags]);
+        record.extend_from_slice(b"IBM VGA");
+        record.resize(SAUCE_RECORD_SIZE, 0);
+        return record;
+    }
+
+    #[test]
+    fn split_sauce_record() {
+        let mut byte_list = b"hello\x1a".to_vec();
+        byte_list.extend_from_slice(b"COMNT");
+        let mut comment = b"a comment".to_vec();
+        comment.resize(SAUCE_COMMENT_LINE_SIZE, b' ');
+        byte_list.extend_from_slice(&comment);
+        byte_list.extend_from_slice(&get_sauce_record(40, 1, 1));
+
+        let (content, sauce) = split_sauce(&byte_list);
+        let sauce = sauce.unwrap();
+        assert_eq!(content, b"hello\x1a");
+        assert_eq!(sauce.title, "Title");
+        assert_eq!(sauce.author, "Author");
+        assert_eq!(sauce.group, "Group");
+        assert_eq!(sauce.date, "20240101");
+        assert_eq!(sauce.file_size, 12);
+        assert_eq!(sauce.tinfos, "IBM VGA");
+        assert_eq!(sauce.comment_list, vec!["a comment".to_string()]);
+        assert_eq!(sauce.get_width(), Some(40));
+        assert!(sauce.is_ice_color());
+
+        assert_eq!(split_sauce(b"no record"), (&b"no record"[..], None));
+    }
+
+    #[test]
+    fn parse_ansi_art_with_sauce_width() {
+        let mut byte_list = b"\x1b[31mabcdef\x1b[0m\r\n\xdb\x1a".to_vec();
+        byte_list.extend_from_slice(&get_sauce_record(4, 0, 0));
+
+        let ansi_art = parse_ansi_art(&byte_list).unwrap();
+        assert_eq!(ansi_art.width, 4);
+        assert!(!ansi_art.get_color_scheme().is_ice_color);
+        let text_list: Vec<String> = ansi_art
+            .line_list
+            .iter()
+            .map(|line| line.iter().map(|ansi_char| ansi_char.character).collect())
+            .collect();
+        // the line wraps at the width of the SAUCE record and the CP437 block is decoded
+        assert_eq!(text_list, vec!["abcd", "ef  ", "█   "]);
+        assert_eq!(
+            ansi_art.line_list[0][0].style.foreground,
+            Some(AnsiColor::Indexed(1))
+        );
+        assert_eq!(ansi_art.line_list[2][0].style.foreground, None);
+    }
+
+    #[test]
+    fn reject_ansi_art_out_of_bounds() {
+        // 12500 lines of the default 80 columns fit in the screen
+        let max_rows = MAX_ANSI_ART_CELLS / DEFAULT_ANSI_ART_WIDTH as usize;
+        let byte_list = format!("\x1b[{}Ha", max_rows + 100);
+        let ansi_art = parse_ansi_art(byte_list.as_bytes()).unwrap();
+        assert_eq!(ansi_art.line_list.len(), max_rows);
+        let byte_list = "\n".repeat(max_rows);
+        assert!(parse_ansi_art(byte_list.as_bytes()).is_err());
+
+        // a wider art has fewer lines
+        let mut byte_list = "\n"
+            .repeat(MAX_ANSI_ART_CELLS / u16::MAX as usize - 1)
+            .into_bytes();
+        byte_list.extend_from_slice(&get_sauce_record(u16::MAX, 0, 0));
+        assert!(parse_ansi_art(&byte_list).is_ok());
+        let mut byte_list = "\n"
+            .repeat(MAX_ANSI_ART_CELLS / u16::MAX as usize)
+            .into_bytes();
+        byte_list.extend_from_slice(&get_sauce_record(u16::MAX, 0, 0));
+        assert!(parse_ansi_art(&byte_list).is_err());
+    }
+}
@@ -0,0 +1,628 @@
+use std::{borrow::Cow, fmt::Write};
+
+use image::{DynamicImage, GenericImageView, GrayImage, ImageBuffer, Pixel, Rgba, RgbaImage};
+use imageproc::{
+    drawing::{draw_filled_rect_mut, draw_text_mut},
+    rect::Rect,
+};
+
+use crate::{
+    character::{CharacterType, FontData},
+    preprocess::apply_preprocess,
+    terminal::{AnsiColorEncoder, ColorSupport},
+    utils::{
+        font::get_character_dimensions,
+        utils::{apply_tone, get_alpha_aware_rgba, get_character_and_rgba_based_on_rgba},
+    },
+    PaletteMatcher, SettingOption,
+};
+
+// the width of a cell when more columns than the width of the image were asked for,
+// the image outputs use a 1/4 of the width to keep the characters readable, the text keep a column for every pixel
+pub const IMAGE_FALLBACK_CELL_WIDTH: u32 = 4;
+pub const TEXT_FALLBACK_CELL_WIDTH: u32 = 1;
+
+// the foreground and background color of a run of cells in the ANSI and HTML output
+type CellStyle = (Option<Rgba<u8>>, Option<Rgba<u8>>);
+
+// a single character of the ascii art
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct AsciiCell {
+    pub character: char,
+    // color of the character, the alpha is below 255 if the cell was faded
+    pub foreground: Rgba<u8>,
+    // color that fill the cell behind the character, `None` means the background of the grid is shown
+    pub background: Option<Rgba<u8>>,
+    // luminance of the pixel the cell was sampled from
+    pub luminance: u8,
+}
+
+// the characters picked for an image, before they are rendered to text, ANSI, HTML or an image
+//
+// the cells are stored row by row, a `None` cell is left empty (e.g. the transparent part of an image)
+#[derive(Clone, Debug)]
+pub struct AsciiGrid {
+    pub num_rows: u32,
+    pub num_cols: u32,
+    pub cell_list: Vec<Option<AsciiCell>>,
+    // color behind all the cells, an alpha of 0 means transparent
+    pub background: Rgba<u8>,
+    // the font of the character type is used to render the grid to an image
+    pub character_type: CharacterType,
+}
+
+impl AsciiGrid {
+    // a grid with every cell empty
+    pub fn new(
+        num_rows: u32,
+        num_cols: u32,
+        background: Rgba<u8>,
+        character_type: CharacterType,
+    ) -> AsciiGrid {
+        return AsciiGrid {
+            num_rows,
+            num_cols,
+            cell_list: vec![None; num_rows as usize * num_cols as usize],
+            background,
+            character_type,
+        };
+    }
+
+    pub fn get_cell(&self, row: u32, col: u32) -> Option<&AsciiCell> {
+        return self.cell_list[self.get_index(row, col)].as_ref();
+    }
+
+    pub fn set_cell(&mut self, row: u32, col: u32, cell: Option<AsciiCell>) {
+        let index = self.get_index(row, col);
+        self.cell_list[index] = cell;
+    }
+
+    pub fn get_row(&self, row: u32) -> &[Option<AsciiCell>] {
+        let start = self.get_index(row, 0);
+        return &self.cell_list[start..start + self.num_cols as usize];
+    }
+
+    // the index is computed in usize, as the number of cells of a large grid doesn't fit in u32
+    fn get_index(&self, row: u32, col: u32) -> usize {
+        return row as usize * self.num_cols as usize + col as usize;
+    }
+
+    // the characters only, a line for every row and an empty cell as a space
+    pub fn to_text(&self) -> String {
+        let mut text = String::with_capacity(((self.num_cols + 1) * self.num_rows) as usize);
+        for row in 0..self.num_rows {
+            for cell in self.get_row(row) {
+                text.push(cell.map_or(' ', |cell| cell.character));
+            }
+            text.push('\n');
+        }
+        return text;
+    }
+
+    // the characters with 24-bit ANSI colors for the terminal, every line end with a reset
+    pub fn to_ansi(&self) -> String {
+        return self.to_ansi_with_color_support(ColorSupport::TrueColor);
+    }
+
+    // the characters with the ANSI colors the terminal support, the colors are mapped to the nearest
+    // of the 256 or 16 colors, and left out for `ColorSupport::None`
+    pub fn to_ansi_with_color_support(&self, color_support: ColorSupport) -> String {
+        if color_support == ColorSupport::None {
+            return self.to_text();
+        }
+        let mut color_encoder = AnsiColorEncoder::new(color_support);
+        let grid_background = get_opaque_background(self.background);
+        let mut ansi = String::new();
+
+        for row in 0..self.num_rows {
+            // the escape codes are only written when the color change from the cell before
+            let mut current_style: Option<CellStyle> = None;
+            for cell in self.get_row(row) {
+                let (character, style) = match cell {
+                    Some(cell) => (
+                        cell.character,
+                        (Some(cell.foreground), cell.background.or(grid_background)),
+                    ),
+                    None => (' ', (None, grid_background)),
+                };
+
+                if current_style != Some(style) {
+                    ansi.push_str("\x1b[0m");
+                    if let Some(foreground) = style.0 {
+                        ansi.push_str(&color_encoder.get_escape_code(foreground.to_rgb().0, false));
+                    }
+                    if let Some(background) = style.1 {
+                        ansi.push_str(&color_encoder.get_escape_code(background.to_rgb().0, true));
+                    }
+                    current_style = Some(style);
+                }
+                ansi.push(character);
+            }
+            ansi.push_str("\x1b[0m\n");
+        }
+        return ansi;
+    }
+
+    // the characters as a `<pre>` block, the cells of the same color are grouped in a `<span>`
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        let _ = write!(
+            html,
+            "<pre style=\"font-family: monospace; line-height: 1; background-color: {};\">",
+            get_css_color(self.background)
+        );
+
+        for row in 0..self.num_rows {
+            let mut current_style: Option<CellStyle> = None;
+            for cell in self.get_row(row) {
+                let (character, style) = match cell {
+                    Some(cell) => (cell.character, (Some(cell.foreground), cell.background)),
+                    None => (' ', (None, None)),
+                };
+
+                if current_style != Some(style) {
+                    if current_style.is_some() {
+                        html.push_str("</span>");
+                    }
+                    html.push_str("<span style=\"");
+                    if let Some(foreground) = style.0 {
+                        let _ = write!(html, "color: {};", get_css_color(foreground));
+                    }
+                    if let Some(background) = style.1 {
+                        let _ = write!(html, "background-color: {};", get_css_color(background));
+                    }
+                    html.push_str("\">");
+                    current_style = Some(style);
+                }
+                match character {
+                    '&' => html.push_str("&amp;"),
+                    '<' => html.push_str("&lt;"),
+                    '>' => html.push_str("&gt;"),
+                    '"' => html.push_str("&quot;"),
+                    _ => html.push(character),
+                }
+            }
+            if current_style.is_some() {
+                html.push_str("</span>");
+            }
+            html.push('\n');
+        }
+
+        html.push_str("</pre>");
+        return html;
+    }
+
+    // draw the grid with the font of its character type
+    pub fn to_rgba_img(&self) -> RgbaImage {
+        return self.to_rgba_img_with_character_data(&self.character_type.get_character_data());
+    }
+
+    // draw the grid with the font of its character type, every color is turned to its luminance
+    pub fn to_luma_img(&self) -> GrayImage {
+        return self.to_luma_img_with_character_data(&self.character_type.get_character_data());
+    }
+
+    // the same as `to_rgba_img` with the font data that was already loaded to sample the grid
+    pub(crate) fn to_rgba_img_with_character_data(&self, character_data: &FontData) -> RgbaImage {
+        return self.draw(character_data, |rgba| rgba);
+    }
+
+    pub(crate) fn to_luma_img_with_character_data(&self, character_data: &FontData) -> GrayImage {
+        return self.draw(character_data, |rgba| rgba.to_luma());
+    }
+
+    // the image renderers share this, only the type of pixel differ
+    fn draw<P, F>(&self, character_data: &FontData, to_pixel: F) -> ImageBuffer<P, Vec<u8>>
+    where
+        P: Pixel<Subpixel = u8>,
+        F: Fn(Rgba<u8>) -> P,
+    {
+        let (char_width, char_height) = get_character_dimensions(
+            character_data.scale,
+            character_data.character,
+            character_data.font_data,
+        );
+
+        // create a blank image to draw the ASCII art on
+        let mut out_image: ImageBuffer<P, Vec<u8>> = ImageBuffer::from_pixel(
+            char_width * self.num_cols,
+            char_height * self.num_rows,
+            to_pixel(self.background),
+        );
+
+        for i in 0..self.num_rows {
+            for (j, cell) in self.get_row(i).iter().enumerate() {
+                let cell = match cell {
+                    Some(cell) => cell,
+                    None => continue,
+                };
+                let x = j as u32 * char_width;
+                let y = i * char_height;
+
+                if let Some(background) = cell.background {
+                    draw_filled_rect_mut(
+                        &mut out_image,
+                        Rect::at(x as i32, y as i32).of_size(char_width, char_height),
+                        to_pixel(background),
+                    );
+                }
+                draw_text_mut(
+                    &mut out_image,
+                    to_pixel(cell.foreground),
+                    x as i32,
+                    y as i32,
+                    character_data.scale,
+                    &character_data.font,
+                    &cell.character.to_string(),
+                );
+            }
+        }
+        return out_image;
+    }
+}
+
+// sample the image into a grid of characters, the shared first step of every ascii art output
+//
+// grayscale: the character is picked from the luma, and drawn in white (or black on a white background),
+//            or the foreground color of the color theme
+// rgb: the character is picked from the mean rgb and drawn in the color of the cell,
+//      the alpha, palette and cell background of the setting option are applied to the cell
+// the preprocess and tone of the setting option are applied to the image first
+pub fn image_to_ascii_grid(
+    img: &DynamicImage,
+    character_type: CharacterType,
+    setting_option: &SettingOption,
+) -> AsciiGrid {
+    return sample_ascii_grid(
+        img,
+        character_type,
+        &character_type.get_character_data(),
+        setting_option,
+        IMAGE_FALLBACK_CELL_WIDTH,
+    );
+}
+
+// the same as `image_to_ascii_grid` with the font data of the character type already loaded,
+// and the width of a cell when more columns than the width of the image were asked for
+pub(crate) fn sample_ascii_grid(
+    img: &DynamicImage,
+    character_type: CharacterType,
+    character_data: &FontData,
+    setting_option: &SettingOption,
+    fallback_cell_width: u32,
+) -> AsciiGrid {
+    let img = apply_preprocess(img, setting_option.preprocess);
+    let img = apply_tone(&img, setting_option.tone);
+    let img = if setting_option.is_color || img.width() == 0 || img.height() == 0 {
+        img
+    } else {
+        Cow::Owned(img.grayscale())
+    };
+    let img = img.as_ref();
+    let (width, height) = img.dimensions();
+    let (num_cols, num_rows, cell_width, cell_height) =
+        get_ascii_grid_size(width, height, setting_option.num_cols, fallback_cell_width);
+
+    // the background and the color of the characters
+    let (background, foreground) = if setting_option.is_color {
+        let background_code = if setting_option.is_white_bg { 255 } else { 0 };
+        let background_alpha = if setting_option.is_transparent_bg {
+            0
+        } else {
+            255
+        };
+        (
+            Rgba([
+                background_code,
+                background_code,
+                background_code,
+                background_alpha,
+            ]),
+            None,
+        )
+    } else {
+        let (background, foreground) = match setting_option.color_theme {
+            Some(color_theme) => (color_theme.get_color(0), color_theme.get_color(255)),
+            None if setting_option.is_white_bg => ([255, 255, 255], [0, 0, 0]),
+            None => ([0, 0, 0], [255, 255, 255]),
+        };
+        (
+            Rgba([background[0], background[1], background[2], 255]),
+            Some(Rgba([foreground[0], foreground[1], foreground[2], 255])),
+        )
+    };
+    let mut ascii_grid = AsciiGrid::new(num_rows, num_cols, background, character_type);
+
+    // map the color of every cell to the nearest color of the palette if there is one
+    let mut palette_matcher = if setting_option.is_color {
+        setting_option.palette.as_deref().map(PaletteMatcher::new)
+    } else {
+        None
+    };
+
+    for i in 0..num_rows {
+        for j in 0..num_cols {
+            let (character, rgba_value) = get_character_and_rgba_based_on_rgba(
+                &character_data.character_list,
+                img,
+                cell_width,
+                width,
+                cell_height,
+                height,
+                i,
+                j,
+            );
+            let luminance = rgba_value.to_luma()[0];
+
+            if let Some(foreground) = foreground {
+                ascii_grid.set_cell(
+                    i,
+                    j,
+                    Some(AsciiCell {
+                        character,
+                        foreground,
+                        background: None,
+                        luminance,
+                    }),
+                );
+                continue;
+            }
+
+            let rgba_value = match get_alpha_aware_rgba(
+                rgba_value,
+                background,
+                setting_option.alpha_threshold,
+                setting_option.alpha_mode,
+            ) {
+                Some(rgba_value) => rgba_value,
+                None => continue,
+            };
+
+            let rgba_value = match palette_matcher.as_mut() {
+                Some(palette_matcher) => {
+                    let color =
+                        palette_matcher.get_color([rgba_value[0], rgba_value[1], rgba_value[2]]);
+                    Rgba([color[0], color[1], color[2], rgba_value[3]])
+                }
+                None => rgba_value,
+            };
+
+            // fill the cell with its color and draw the character in a shade that stand out from it
+            let (foreground, cell_background) = match setting_option.cell_background {
+                Some(cell_background) => {
+                    let (mut background, mut glyph) = cell_background
+                        .get_background_and_glyph_color([
+                            rgba_value[0],
+                            rgba_value[1],
+                            rgba_value[2],
+                        ]);
+                    if let Some(palette_matcher) = palette_matcher.as_mut() {
+                        background = palette_matcher.get_color(background);
+                        glyph = palette_matcher.get_color(glyph);
+                    }
+                    (
+                        Rgba([glyph[0], glyph[1], glyph[2], rgba_value[3]]),
+                        Some(Rgba([
+                            background[0],
+                            background[1],
+                            background[2],
+                            rgba_value[3],
+                        ])),
+                    )
+                }
+                None => (rgba_value, None),
+            };
+
+            ascii_grid.set_cell(
+                i,
+                j,
+                Some(AsciiCell {
+                    character,
+                    foreground,
+                    background: cell_background,
+                    luminance,
+                }),
+            );
+        }
+    }
+
+    return ascii_grid;
+}
+
+// the number of columns and rows of the grid sampled from an image, and the width and height of a cell in pixels
+//
+// a cell is twice as high as it is wide, the same as a character of the terminal
+//
+// if the user provide a number of columns that is greater than the width of the image,
+// cells of `fallback_cell_width` pixels are used instead, e.g. `IMAGE_FALLBACK_CELL_WIDTH`
+pub fn get_ascii_grid_size(
+    width: u32,
+    height: u32,
+    num_cols: u32,
+    fallback_cell_width: u32,
+) -> (u32, u32, u32, u32) {
+    // an empty image has no cell, and a cell of width 0 would divide by zero below
+    if width == 0 || height == 0 {
+        return (0, 0, 0, 0);
+    }
+
+    let mut num_cols = if num_cols > width {
+        width / fallback_cell_width.max(1)
+    } else {
+        num_cols
+    }
+    .max(1);
+    // width per cell
+    let mut cell_width = width / num_cols;
+    // height per cell
+    let mut cell_height = 2 * cell_width;
+    let mut num_rows = height / cell_height;
+
+    if num_cols > width || num_rows > height {
+        // Too many columns or rows. Use default setting
+        cell_width = 6;
+        cell_height = 12;
+        num_cols = width / cell_width;
+        num_rows = height / cell_height;
+    }
+
+    return (num_cols, num_rows, cell_width, cell_height);
+}
+
+// a transparent background is left to the terminal
+fn get_opaque_background(background: Rgba<u8>) -> Option<Rgba<u8>> {
+    if background[3] == 0 {
+        return None;
+    }
+    return Some(background);
+}
+
+fn get_css_color(rgba: Rgba<u8>) -> String {
+    if rgba[3] == 255 {
+        return format!("#{:02x}{:02x}{:02x}", rgba[0], rgba[1], rgba[2]);
+    }
+    return format!(
+        "rgba({}, {}, {}, {:.3})",
+        rgba[0],
+        rgba[1],
+        rgba[2],
+        rgba[3] as f32 / 255.0
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgb;
+
+    use super::*;
+
+    fn cell(character: char, foreground: [u8; 3], background: Option<[u8; 3]>) -> AsciiCell {
+        return AsciiCell {
+            character,
+            foreground: Rgba([foreground[0], foreground[1], foreground[2], 255]),
+            background: background.map(|rgb| Rgba([rgb[0], rgb[1], rgb[2], 255])),
+            luminance: 0,
+        };
+    }
+
+    // a 2x3 grid, the last cell of the first row is empty
+    fn build_grid() -> AsciiGrid {
+        let mut grid = AsciiGrid::new(2, 3, Rgba([0, 0, 0, 255]), CharacterType::Simple);
+        grid.set_cell(0, 0, Some(cell('a', [255, 0, 0], None)));
+        grid.set_cell(0, 1, Some(cell('b', [255, 0, 0], None)));
+        grid.set_cell(1, 0, Some(cell('<', [0, 255, 0], Some([0, 0, 255]))));
+        grid.set_cell(1, 1, Some(cell('&', [0, 255, 0], Some([0, 0, 255]))));
+        grid.set_cell(1, 2, Some(cell('c', [0, 255, 0], None)));
+        return grid;
+    }
+
+    #[test]
+    fn get_and_set_cell() {
+        let grid = build_grid();
+        assert_eq!(grid.get_cell(0, 1).unwrap().character, 'b');
+        assert!(grid.get_cell(0, 2).is_none());
+        assert_eq!(grid.get_row(1).len(), 3);
+        assert_eq!(grid.get_row(1)[2].unwrap().character, 'c');
+    }
+
+    #[test]
+    fn render_text() {
+        assert_eq!(build_grid().to_text(), "ab \n<&c\n");
+    }
+
+    #[test]
+    fn render_ansi() {
+        let ansi = build_grid().to_ansi();
+        let line_list: Vec<&str> = ansi.lines().collect();
+        // the color is only written when it change, and the empty cell keep the background of the grid
+        assert_eq!(
+            line_list[0],
+            "\x1b[0m\x1b[38;2;255;0;0m\x1b[48;2;0;0;0mab\x1b[0m\x1b[48;2;0;0;0m \x1b[0m"
+        );
+        assert_eq!(
+            line_list[1],
+            "\x1b[0m\x1b[38;2;0;255;0m\x1b[48;2;0;0;255m<&\x1b[0m\x1b[38;2;0;255;0m\x1b[48;2;0;0;0mc\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn render_ansi_with_transparent_background() {
+        let mut grid = AsciiGrid::new(1, 1, Rgba([0, 0, 0, 0]), CharacterType::Simple);
+        grid.set_cell(0, 0, Some(cell('a', [1, 2, 3], None)));
+        assert_eq!(grid.to_ansi(), "\x1b[0m\x1b[38;2;1;2;3ma\x1b[0m\n");
+    }
+
+    #[test]
+    fn render_html() {
+        let html = build_grid().to_html();
+        assert!(html.starts_with("<pre style="));
+        assert!(html.ends_with("</pre>"));
+        assert!(html.contains("<span style=\"color: #ff0000;\">ab</span>"));
+        // the special characters are escaped
+        assert!(html.contains("&lt;&amp;</span>"));
+        assert!(!html.contains("<&"));
+    }
+
+    #[test]
+    fn render_image() {
+        let grid = build_grid();
+        let character_data = CharacterType::Simple.get_character_data();
+        let (char_width, char_height) = get_character_dimensions(
+            character_data.scale,
+            character_data.character,
+            character_data.font_data,
+        );
+
+        let rgba_img = grid.to_rgba_img();
+        assert_eq!(rgba_img.dimensions(), (3 * char_width, 2 * char_height));
+        // the background of a cell fill the whole cell
+        assert_eq!(*rgba_img.get_pixel(0, char_height), Rgba([0, 0, 255, 255]));
+        // the empty cell show the background of the grid
+        assert_eq!(
+            *rgba_img.get_pixel(2 * char_width + 1, 1),
+            Rgba([0, 0, 0, 255])
+        );
+
+        let luma_img = grid.to_luma_img();
+        assert_eq!(luma_img.dimensions(), rgba_img.dimensions());
+    }
+
+    #[test]
+    fn sample_image() {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(40, 40, Rgb([255, 0, 0])));
+        let grid = image_to_ascii_grid(&img, CharacterType::Simple, &SettingOption::rgb(10));
+        assert_eq!((grid.num_cols, grid.num_rows), (10, 5));
+        let cell = grid.get_cell(0, 0).unwrap();
+        assert_eq!(cell.foreground, Rgba([255, 0, 0, 255]));
+
+        // a grayscale grid is drawn in white on black
+        let grid = image_to_ascii_grid(&img, CharacterType::Simple, &SettingOption::grayscale(10));
+        assert_eq!(
+            grid.get_cell(0, 0).unwrap().foreground,
+            Rgba([255, 255, 255, 255])
+        );
+    }
+
+    #[test]
+    fn sample_empty_image() {
+        let img = DynamicImage::new_rgb8(0, 0);
+        let grid = image_to_ascii_grid(&img, CharacterType::Simple, &SettingOption::grayscale(10));
+        assert_eq!((grid.num_cols, grid.num_rows), (0, 0));
+        assert_eq!(grid.to_text(), "");
+    }
+
+    #[test]
+    fn ascii_grid_size() {
+        assert_eq!(get_ascii_grid_size(0, 10, 4, 4), (0, 0, 0, 0));
+        assert_eq!(get_ascii_grid_size(40, 40, 10, 4), (10, 5, 4, 8));
+        // more columns than the width of the image
+        assert_eq!(
+            get_ascii_grid_size(40, 40, 100, IMAGE_FALLBACK_CELL_WIDTH),
+            (10, 5, 4, 8)
+        );
+        assert_eq!(
+            get_ascii_grid_size(40, 40, 100, TEXT_FALLBACK_CELL_WIDTH),
+            (40, 20, 1, 2)
+        );
+    }
+}
@@ -0,0 +1,453 @@
+use ab_glyph::{point, Font, FontRef, Glyph, GlyphId, PxScale, ScaleFont};
+use image::{DynamicImage, GrayImage, Luma};
+
+use crate::{ascii_grid::image_to_ascii_grid, character::CharacterType, SettingOption};
+
+// the size of the banner text in pixels if it was not set
+pub const DEFAULT_BANNER_FONT_SIZE: f32 = 32.0;
+// the coverage from which a pixel is a dot in the braille and block modes
+pub const DEFAULT_BANNER_THRESHOLD: u8 = 128;
+// the largest font size a banner can be drawn with
+pub const MAX_BANNER_FONT_SIZE: f32 = 1024.0;
+// the largest number of pixels the rasterized text can have, a long text can't be drawn at a large font size
+pub const MAX_BANNER_PIXELS: u64 = 64_000_000;
+
+// the fonts bundled in `assets/fonts` that a banner can be drawn with
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum BannerFont {
+    Auto,           // the first bundled font that has every character of the text
+    DejaVuSansMono, // latin, greek, cyrillic
+    RobotoMono,     // latin, greek, cyrillic
+    SimSun,         // chinese
+    NotoSansJp,     // japanese kana
+    NotoSansKr,     // korean jamo
+    Monotty,        // devanagari
+    AzarMehr,       // arabic, the letters are drawn in their isolated form
+    Mitra,          // bengali
+}
+
+impl BannerFont {
+    // every font except `Auto`, in the order `Auto` try them
+    pub fn get_font_list() -> Vec<BannerFont> {
+        return vec![
+            BannerFont::DejaVuSansMono,
+            BannerFont::RobotoMono,
+            BannerFont::SimSun,
+            BannerFont::NotoSansJp,
+            BannerFont::NotoSansKr,
+            BannerFont::Monotty,
+            BannerFont::AzarMehr,
+            BannerFont::Mitra,
+        ];
+    }
+
+    pub fn from_name(name: &str) -> Option<BannerFont> {
+        match name.to_lowercase().as_str() {
+            "auto" => return Some(BannerFont::Auto),
+            "dejavu" | "dejavu_sans_mono" => return Some(BannerFont::DejaVuSansMono),
+            "roboto" | "roboto_mono" => return Some(BannerFont::RobotoMono),
+            "simsun" => return Some(BannerFont::SimSun),
+            "noto_jp" | "noto_sans_jp" => return Some(BannerFont::NotoSansJp),
+            "noto_kr" | "noto_sans_kr" => return Some(BannerFont::NotoSansKr),
+            "monotty" => return Some(BannerFont::Monotty),
+            "azarmehr" => return Some(BannerFont::AzarMehr),
+            "mitra" => return Some(BannerFont::Mitra),
+            _ => return None,
+        }
+    }
+
+    // the font file, `None` for `Auto` as it depends on the text
+    pub fn get_font_data(&self) -> Option<&'static [u8]> {
+        match self {
+            BannerFont::Auto => return None,
+            BannerFont::DejaVuSansMono => {
+                return Some(include_bytes!(
+                    "../assets/fonts/dejavu/DejaVuSansMono-Bold.ttf"
+                ))
+            }
+            BannerFont::RobotoMono => {
+                return Some(include_bytes!("../assets/fonts/roboto/RobotoMono-Bold.ttf"))
+            }
+            BannerFont::SimSun => {
+                return Some(include_bytes!(
+                    "../assets/fonts/simsun/SimSun-Bold-Modified.ttf"
+                ))
+            }
+            BannerFont::NotoSansJp => {
+                return Some(include_bytes!(
+                    "../assets/fonts/noto/jp/NotoSansJP-Bold-NoKanji.ttf"
+                ))
+            }
+            BannerFont::NotoSansKr => {
+                return Some(include_bytes!(
+                    "../assets/fonts/noto/kr/NotoSansKR-Bold-KoreanOnly.ttf"
+                ))
+            }
+            BannerFont::Monotty => {
+                return Some(include_bytes!("../assets/fonts/monotty/monotty-dev2.ttf"))
+            }
+            BannerFont::AzarMehr => {
+                return Some(include_bytes!(
+                    "../assets/fonts/azarmehr/AzarMehrMonospacedSansBold.ttf"
+                ))
+            }
+            BannerFont::Mitra => return Some(include_bytes!("../assets/fonts/mitra/mitra.ttf")),
+        }
+    }
+
+    // the characters of the text that the font doesn't have, the whitespaces are not checked
+    pub fn get_missing_character_list(&self, text: &str) -> Vec<char> {
+        let font = match self.get_font_data() {
+            Some(font_data) => FontRef::try_from_slice(font_data).unwrap(),
+            None => return vec![],
+        };
+        let mut missing_character_list: Vec<char> = vec![];
+        for character in text.chars() {
+            // glyph id 0 is the `.notdef` glyph, which the font use for a character it doesn't have
+            if !character.is_whitespace()
+                && font.glyph_id(character).0 == 0
+                && !missing_character_list.contains(&character)
+            {
+                missing_character_list.push(character);
+            }
+        }
+        return missing_character_list;
+    }
+
+    // the fonts the characters are looked up in, in order
+    fn get_fallback_font_list(&self) -> Vec<BannerFont> {
+        if *self == BannerFont::Auto {
+            return BannerFont::get_font_list();
+        }
+        return vec![*self];
+    }
+}
+
+// how the rasterized text is turned into characters
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum BannerMode {
+    Ramp(CharacterType), // a character of the brightness ramp for every 2x4 pixels, like `grayscale_to_ascii`
+    Braille,             // a braille pattern for every 2x4 pixels, a dot for every pixel
+    Block,               // a half block for every 1x2 pixels, `▀`, `▄` or `█`
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct BannerOption {
+    pub font: BannerFont,
+    pub font_size: f32, // height of a line of text in pixels, see BannerMode for the number of pixels in a character
+    pub mode: BannerMode,
+    pub threshold: u8, // only used by the braille and block modes
+}
+
+impl BannerOption {
+    pub fn new() -> BannerOption {
+        return BannerOption {
+            font: BannerFont::Auto,
+            font_size: DEFAULT_BANNER_FONT_SIZE,
+            mode: BannerMode::Ramp(CharacterType::Simple),
+            threshold: DEFAULT_BANNER_THRESHOLD,
+        };
+    }
+
+    pub fn with_font(mut self, font: BannerFont) -> BannerOption {
+        self.font = font;
+        return self;
+    }
+
+    pub fn with_font_size(mut self, font_size: f32) -> BannerOption {
+        self.font_size = font_size;
+        return self;
+    }
+
+    pub fn with_mode(mut self, mode: BannerMode) -> BannerOption {
+        self.mode = mode;
+        return self;
+    }
+
+    pub fn with_threshold(mut self, threshold: u8) -> BannerOption {
+        self.threshold = threshold;
+        return self;
+    }
+}
+
+impl Default for BannerOption {
+    fn default() -> BannerOption {
+        return BannerOption::new();
+    }
+}
+
+// draw a text as a banner of characters, e.g. `text_to_banner("Hello", &BannerOption::new())`
+//
+// every line of the text is a line of the banner, the trailing spaces of the banner lines are trimmed
+pub fn text_to_banner(text: &str, banner_option: &BannerOption) -> Result<String, String> {
+    let img = rasterize_text(text, banner_option.font, banner_option.font_size)?;
+
+    let line_list = match banner_option.mode {
+        BannerMode::Ramp(character_type) => {
+            let num_cols = (img.width() / 2).max(1);
+            let ascii_grid = image_to_ascii_grid(
+                &DynamicImage::ImageLuma8(img),
+                character_type,
+                &SettingOption::grayscale(num_cols),
+            );
+            ascii_grid
+                .to_text()
+                .lines()
+                .map(|line| line.to_string())
+                .collect()
+        }
+        BannerMode::Braille => gray_to_braille_line_list(&img, banner_option.threshold),
+        BannerMode::Block => gray_to_block_line_list(&img, banner_option.threshold),
+    };
+
+    let mut banner = String::new();
+    for line in line_list {
+        banner.push_str(line.trim_end());
+        banner.push('\n');
+    }
+    return Ok(banner);
+}
+
+// draw a text in white on black, every line is `font_size` pixels high
+//
+// with `BannerFont::Auto` every character is drawn with the first bundled font that has it,
+// so a text can mix several scripts
+pub fn rasterize_text(text: &str, font: BannerFont, font_size: f32) -> Result<GrayImage, String> {
+    if text.trim().is_empty() {
+        return Err("The text is empty".to_string());
+    }
+    if !font_size.is_finite() || !(1.0..=MAX_BANNER_FONT_SIZE).contains(&font_size) {
+        return Err(format!(
+            "font_size must be a number between 1 and {}",
+            MAX_BANNER_FONT_SIZE
+        ));
+    }
+    let scale = PxScale::from(font_size);
+    let font_list: Vec<FontRef> = font
+        .get_fallback_font_list()
+        .iter()
+        .map(|font| FontRef::try_from_slice(font.get_font_data().unwrap()).unwrap())
+        .collect();
+
+    // pick the font of every character, a whitespace use the first font
+    let mut missing_character_list: Vec<char> = vec![];
+    let mut line_list: Vec<Vec<(char, usize)>> = vec![];
+    for line in text.lines() {
+        let mut character_list: Vec<(char, usize)> = vec![];
+        for character in line.chars() {
+            // glyph id 0 is the `.notdef` glyph, which the font use for a character it doesn't have
+            let font_index = if character.is_whitespace() {
+                Some(0)
+            } else {
+                font_list
+                    .iter()
+                    .position(|font| font.glyph_id(character).0 != 0)
+            };
+            match font_index {
+                Some(font_index) => character_list.push((character, font_index)),
+                None if !missing_character_list.contains(&character) => {
+                    missing_character_list.push(character)
+                }
+                None => {}
+            }
+        }
+        line_list.push(character_list);
+    }
+    if !missing_character_list.is_empty() {
+        return Err(format!(
+            "{:?} doesn't have the characters: {}",
+            font,
+            missing_character_list.iter().collect::<String>()
+        ));
+    }
+
+    // every line share the same baseline position, from the fonts that are used
+    let mut ascent: f32 = 0.0;
+    let mut descent: f32 = 0.0;
+    for (_, font_index) in line_list.iter().flatten() {
+        let scaled_font = font_list[*font_index].as_scaled(scale);
+        ascent = ascent.max(scaled_font.ascent());
+        descent = descent.min(scaled_font.descent());
+    }
+    let line_height = (ascent - descent).ceil().max(1.0);
+
+    // lay out the glyphs, the kerning is only applied between characters of the same font
+    let mut glyph_list: Vec<(Glyph, usize)> = vec![];
+    let mut width: f32 = 1.0;
+    for (i, character_list) in line_list.iter().enumerate() {
+        let baseline = i as f32 * line_height + ascent;
+        let mut x: f32 = 0.0;
+        let mut previous_glyph: Option<(GlyphId, usize)> = None;
+        for (character, font_index) in character_list {
+            let scaled_font = font_list[*font_index].as_scaled(scale);
+            let glyph_id = scaled_font.glyph_id(*character);
+            if let Some((previous_glyph_id, previous_font_index)) = previous_glyph {
+                if previous_font_index == *font_index {
+                    x += scaled_font.kern(previous_glyph_id, glyph_id);
+                }
+            }
+            glyph_list.push((
+                glyph_id.with_scale_and_position(scale, point(x, baseline)),
+                *font_index,
+            ));
+            x += scaled_font.h_advance(glyph_id);
+            previous_glyph = Some((glyph_id, *font_index));
+        }
+        width = width.max(x);
+    }
+
+    let width = width.ceil() as u64;
+    let height = line_height as u64 * line_list.len() as u64;
+    if width * height > MAX_BANNER_PIXELS {
+        return Err(format!(
+            "The banner is too large to draw, {}x{} pixels is more than {} pixels",
+            width, height, MAX_BANNER_PIXELS
+        ));
+    }
+    let mut img = GrayImage::from_pixel(width as u32, height as u32, Luma([0]));
+    for (glyph, font_index) in glyph_list {
+        let outlined_glyph = match font_list[font_index].outline_glyph(glyph) {
+            Some(outlined_glyph) => outlined_glyph,
+            None => continue,
+        };
+        let bounds = outlined_glyph.px_bounds();
+        outlined_glyph.draw(|gx, gy, coverage| {
+            let x = bounds.min.x as i32 + gx as i32;
+            let y = bounds.min.y as i32 + gy as i32;
+            if x < 0 || y < 0 || x >= img.width() as i32 || y >= img.height() as i32 {
+                return;
+            }
+            let pixel = img.get_pixel_mut(x as u32, y as u32);
+            pixel[0] = pixel[0].max((coverage.min(1.0) * 255.0) as u8);
+        });
+    }
+    return Ok(img);
+}
+
+// a braille character for every 2x4 pixels, the dots are numbered
+// 1 4
+// 2 5
+// 3 6
+// 7 8
+fn gray_to_braille_line_list(img: &GrayImage, threshold: u8) -> Vec<String> {
+    const DOT_BIT_LIST: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+    let mut line_list: Vec<String> = vec![];
+    for row in 0..img.height().div_ceil(4) {
+        let mut line = String::new();
+        for col in 0..img.width().div_ceil(2) {
+            let mut pattern = 0;
+            for (dy, dot_bit_row) in DOT_BIT_LIST.iter().enumerate() {
+                for (dx, dot_bit) in dot_bit_row.iter().enumerate() {
+                    if is_pixel_set(img, col * 2 + dx as u32, row * 4 + dy as u32, threshold) {
+                        pattern |= dot_bit;
+                    }
+                }
+            }
+            // an empty cell is a space instead of the blank braille pattern, so it can be trimmed
+            if pattern == 0 {
+                line.push(' ');
+            } else {
+                line.push(char::from_u32(0x2800 + pattern).unwrap());
+            }
+        }
+        line_list.push(line);
+    }
+    return line_list;
+}
+
+// a half block for every 1x2 pixels
+fn gray_to_block_line_list(img: &GrayImage, threshold: u8) -> Vec<String> {
+    let mut line_list: Vec<String> = vec![];
+    for row in 0..img.height().div_ceil(2) {
+        let mut line = String::new();
+        for col in 0..img.width() {
+            let is_top_set = is_pixel_set(img, col, row * 2, threshold);
+            let is_bottom_set = is_pixel_set(img, col, row * 2 + 1, threshold);
+            line.push(match (is_top_set, is_bottom_set) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            });
+        }
+        line_list.push(line);
+    }
+    return line_list;
+}
+
+// the pixels outside of the image are not set
+fn is_pixel_set(img: &GrayImage, x: u32, y: u32, threshold: u8) -> bool {
+    if x >= img.width() || y >= img.height() {
+        return false;
+    }
+    return img.get_pixel(x, y)[0] >= threshold;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn banner_font_from_name() {
+        assert_eq!(
+            BannerFont::from_name("DejaVu_Sans_Mono"),
+            Some(BannerFont::DejaVuSansMono)
+        );
+        assert_eq!(BannerFont::from_name("unknown"), None);
+        for font in BannerFont::get_font_list() {
+            assert!(font.get_font_data().is_some());
+        }
+    }
+
+    #[test]
+    fn rasterize_text_size() {
+        let img = rasterize_text("A\nA", BannerFont::DejaVuSansMono, 16.0).unwrap();
+        let single_line_img = rasterize_text("A", BannerFont::DejaVuSansMono, 16.0).unwrap();
+        assert_eq!(img.width(), single_line_img.width());
+        assert_eq!(img.height(), single_line_img.height() * 2);
+        assert!(img.pixels().any(|pixel| pixel[0] > 0));
+    }
+
+    #[test]
+    fn rasterize_text_invalid_option() {
+        let font = BannerFont::Auto;
+        assert!(rasterize_text(" ", font, 16.0).is_err());
+        assert!(rasterize_text("A", font, 0.5).is_err());
+        assert!(rasterize_text("A", font, f32::NAN).is_err());
+        assert!(rasterize_text("A", font, f32::INFINITY).is_err());
+        assert!(rasterize_text("A", font, MAX_BANNER_FONT_SIZE + 1.0).is_err());
+        // a long text at the largest font size is too many pixels
+        let text = "A".repeat(1000);
+        assert!(rasterize_text(&text, font, MAX_BANNER_FONT_SIZE).is_err());
+    }
+
+    #[test]
+    fn rasterize_text_missing_character() {
+        let error = rasterize_text("A\u{10FFFD}", BannerFont::DejaVuSansMono, 16.0).unwrap_err();
+        assert!(error.contains('\u{10FFFD}'));
+    }
+
+    #[test]
+    fn text_to_banner_mode() {
+        for mode in [
+            BannerMode::Ramp(CharacterType::Simple),
+            BannerMode::Braille,
+            BannerMode::Block,
+        ] {
+            let banner_option = BannerOption::new().with_font_size(16.0).with_mode(mode);
+            let banner = text_to_banner("Hi", &banner_option).unwrap();
+            assert!(banner.ends_with('\n'));
+            assert!(banner.lines().all(|line| line == line.trim_end()));
+            assert!(banner.chars().any(|character| !character.is_whitespace()));
+        }
+    }
+
+    #[test]
+    fn gray_to_block_line() {
+        let mut img = GrayImage::new(2, 2);
+        img.put_pixel(0, 0, Luma([255]));
+        img.put_pixel(1, 1, Luma([255]));
+        assert_eq!(gray_to_block_line_list(&img, 128), vec!["▀▄"]);
+        assert_eq!(gray_to_braille_line_list(&img, 128), vec!["⠑"]);
+    }
+}
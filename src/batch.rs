@@ -0,0 +1,612 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::read_dir,
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use image::ImageFormat;
+
+use crate::{
+    config::RenderConfig,
+    utils::{parallel::*, utils::get_panic_message},
+    CharacterType, SettingOption,
+};
+
+// what the still images of a batch are converted to, GIFs are always converted to GIF
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum BatchOutput {
+    Image, // ASCII art image, same as `image_to_image`
+    Text,  // ASCII art text, same as `image_to_text`, only Simple and Complex are supported
+}
+
+// decide which files of a directory are converted and how
+#[derive(PartialEq, Debug, Clone)]
+pub struct BatchOption {
+    // glob patterns of the files to convert, relative to the input directory, e.g. "**/*.png"
+    // an empty list means every file
+    pub include_list: Vec<String>,
+    // glob patterns of the files to leave out, checked after `include_list`
+    pub exclude_list: Vec<String>,
+    pub output: BatchOutput,
+    // walk the sub directories too, their structure is mirrored in the output directory
+    pub is_recursive: bool,
+    // number of threads of the rayon pool, `None` means the rayon default (number of CPUs)
+    pub num_threads: Option<usize>,
+}
+
+impl BatchOption {
+    pub fn new() -> BatchOption {
+        return BatchOption {
+            include_list: vec![],
+            exclude_list: vec![],
+            output: BatchOutput::Image,
+            is_recursive: true,
+            num_threads: None,
+        };
+    }
+
+    pub fn with_include(mut self, pattern: &str) -> BatchOption {
+        self.include_list.push(pattern.to_string());
+        return self;
+    }
+
+    pub fn with_exclude(mut self, pattern: &str) -> BatchOption {
+        self.exclude_list.push(pattern.to_string());
+        return self;
+    }
+
+    pub fn with_output(mut self, output: BatchOutput) -> BatchOption {
+        self.output = output;
+        return self;
+    }
+
+    pub fn with_recursive(mut self, is_recursive: bool) -> BatchOption {
+        self.is_recursive = is_recursive;
+        return self;
+    }
+
+    pub fn with_num_threads(mut self, num_threads: usize) -> BatchOption {
+        self.num_threads = Some(num_threads);
+        return self;
+    }
+}
+
+impl Default for BatchOption {
+    fn default() -> BatchOption {
+        return BatchOption::new();
+    }
+}
+
+// the result of converting a single file of a batch
+#[derive(Debug, Clone)]
+pub struct BatchFileResult {
+    pub input_path: PathBuf,
+    pub output_path: PathBuf,
+    // the message of the conversion, or why it failed
+    pub result: Result<String, String>,
+}
+
+// the result of every file of a batch, in the order the files were found
+#[derive(Debug, Clone)]
+pub struct BatchReport {
+    pub file_result_list: Vec<BatchFileResult>,
+}
+
+impl BatchReport {
+    pub fn get_success_count(&self) -> usize {
+        return self
+            .file_result_list
+            .iter()
+            .filter(|file_result| file_result.result.is_ok())
+            .count();
+    }
+
+    pub fn get_failure_list(&self) -> Vec<&BatchFileResult> {
+        return self
+            .file_result_list
+            .iter()
+            .filter(|file_result| file_result.result.is_err())
+            .collect();
+    }
+}
+
+// the kind of a file found in the input directory
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum BatchFileKind {
+    Image,
+    Gif,
+}
+
+// Converts every image and GIF of a directory to ASCII art, see `batch_convert_with_progress`.
+pub fn batch_convert(
+    input_directory: &str,
+    output_directory: &str,
+    character_type: CharacterType,
+    setting_option: SettingOption,
+    batch_option: &BatchOption,
+) -> Result<BatchReport, String> {
+    return batch_convert_with_progress(
+        input_directory,
+        output_directory,
+        character_type,
+        setting_option,
+        batch_option,
+        |_, _, _| {},
+    );
+}
+
+// Converts every image and GIF of a directory to ASCII art in a rayon pool.
+//
+// images go to `image_to_image` or `image_to_text` based on `BatchOption::output` and GIFs go to `gif_to_gif`,
+// the output keep the name and the sub directory of the input, e.g. `input/a/b.jpg` -> `output/a/b.png`
+//
+// `progress` is called from the worker threads after every file with the result, the number of files done and the total,
+// a file that failed, or panicked, doesn't stop the batch, it is reported in the returned `BatchReport`
+//
+// two inputs with the same output, e.g. `a.png` and `a.jpg`, would overwrite each other,
+// so only the first one is converted and the others are reported as failed
+pub fn batch_convert_with_progress<F>(
+    input_directory: &str,
+    output_directory: &str,
+    character_type: CharacterType,
+    setting_option: SettingOption,
+    batch_option: &BatchOption,
+    progress: F,
+) -> Result<BatchReport, String>
+where
+    F: Fn(&BatchFileResult, usize, usize) + Sync,
+{
+    let render_config = RenderConfig {
+        character_type,
+        setting_option,
+        output_directory: Some(output_directory.to_string()),
+        filename: None,
+    };
+    render_config.validate()?;
+    if batch_option.output == BatchOutput::Text
+        && !matches!(
+            character_type,
+            CharacterType::Simple | CharacterType::Complex
+        )
+    {
+        return Err(format!(
+            "{:?} is not supported for text, use Simple or Complex",
+            character_type
+        ));
+    }
+
+    let input_directory = Path::new(input_directory);
+    if !input_directory.is_dir() {
+        return Err(format!(
+            "Input directory does not exist: {}",
+            input_directory.display()
+        ));
+    }
+    let include_set = build_glob_set(&batch_option.include_list)?;
+    let exclude_set = build_glob_set(&batch_option.exclude_list)?;
+
+    let mut relative_path_list: Vec<PathBuf> = vec![];
+    collect_file_list(
+        input_directory,
+        Path::new(""),
+        batch_option.is_recursive,
+        &mut relative_path_list,
+    )?;
+    let file_list: Vec<(PathBuf, BatchFileKind)> = relative_path_list
+        .into_iter()
+        .filter(|relative_path| {
+            (batch_option.include_list.is_empty() || include_set.is_match(relative_path))
+                && !exclude_set.is_match(relative_path)
+        })
+        .filter_map(|relative_path| {
+            get_batch_file_kind(&relative_path).map(|file_kind| (relative_path, file_kind))
+        })
+        .collect();
+
+    // the first input of every output path, the later inputs with the same output are not converted
+    let mut output_owner_map: HashMap<PathBuf, &PathBuf> = HashMap::new();
+    let collision_list: Vec<Option<BatchFileResult>> = file_list
+        .iter()
+        .map(|(relative_path, file_kind)| {
+            let output_path = Path::new(output_directory).join(get_batch_output_path(
+                relative_path,
+                *file_kind,
+                render_config.setting_option.is_color,
+                batch_option.output,
+            ));
+            let owner = *output_owner_map
+                .entry(output_path.clone())
+                .or_insert(relative_path);
+            if owner == relative_path {
+                return None;
+            }
+            return Some(BatchFileResult {
+                input_path: input_directory.join(relative_path),
+                result: Err(format!(
+                    "{} is already the output of {}",
+                    output_path.display(),
+                    input_directory.join(owner).display()
+                )),
+                output_path,
+            });
+        })
+        .collect();
+
+    let total = file_list.len();
+    let done = AtomicUsize::new(0);
+    let convert_file_list = || -> Vec<BatchFileResult> {
+        return file_list
+            .par_iter()
+            .zip(collision_list.par_iter())
+            .map(|((relative_path, file_kind), collision)| {
+                let file_result = match collision {
+                    Some(file_result) => file_result.clone(),
+                    None => convert_batch_file(
+                        input_directory,
+                        Path::new(output_directory),
+                        relative_path,
+                        *file_kind,
+                        &render_config,
+                        batch_option.output,
+                    ),
+                };
+                let done = done.fetch_add(1, Ordering::SeqCst) + 1;
+                progress(&file_result, done, total);
+                file_result
+            })
+            .collect();
+    };
+
+    #[cfg(feature = "parallel")]
+    let file_result_list: Vec<BatchFileResult> = rayon::ThreadPoolBuilder::new()
+        .num_threads(batch_option.num_threads.unwrap_or(0))
+        .build()
+        .map_err(|e| format!("Failed to create thread pool: {}", e))?
+        .install(convert_file_list);
+    // without the `parallel` feature the files are converted one by one and `num_threads` is not used
+    #[cfg(not(feature = "parallel"))]
+    let file_result_list: Vec<BatchFileResult> = convert_file_list();
+
+    return Ok(BatchReport { file_result_list });
+}
+
+// convert a single file of the batch into the same sub directory of the output directory
+pub fn convert_batch_file(
+    input_directory: &Path,
+    output_directory: &Path,
+    relative_path: &Path,
+    file_kind: BatchFileKind,
+    render_config: &RenderConfig,
+    output: BatchOutput,
+) -> BatchFileResult {
+    let input_path = input_directory.join(relative_path);
+    let output_sub_directory = match relative_path.parent() {
+        Some(parent) => output_directory.join(parent),
+        None => output_directory.to_path_buf(),
+    };
+    let filename = relative_path
+        .file_stem()
+        .map(|file_stem| file_stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let output_path = output_directory.join(get_batch_output_path(
+        relative_path,
+        file_kind,
+        render_config.setting_option.is_color,
+        output,
+    ));
+
+    let file_config = RenderConfig {
+        output_directory: Some(output_sub_directory.to_string_lossy().to_string()),
+        filename: Some(filename),
+        ..render_config.clone()
+    };
+    // the output sub directory is created by the conversion,
+    // a panic is reported as the result of the file so the other files of the batch are still converted
+    let path = input_path.to_string_lossy();
+    let result = catch_unwind(AssertUnwindSafe(|| match (file_kind, output) {
+        (BatchFileKind::Gif, _) => file_config.gif_to_gif(&path),
+        (BatchFileKind::Image, BatchOutput::Text) => file_config.image_to_text(&path),
+        (BatchFileKind::Image, BatchOutput::Image) => file_config.image_to_image(&path),
+    }))
+    .unwrap_or_else(|panic| {
+        return Err(format!(
+            "The conversion failed unexpectedly: {}",
+            get_panic_message(panic.as_ref())
+        ));
+    });
+
+    return BatchFileResult {
+        input_path,
+        output_path,
+        result,
+    };
+}
+
+// the path of the output of a file relative to the output directory,
+// the extension is decided by the function that save the file
+pub fn get_batch_output_path(
+    relative_path: &Path,
+    file_kind: BatchFileKind,
+    is_color: bool,
+    output: BatchOutput,
+) -> PathBuf {
+    let extension = match (file_kind, output) {
+        (BatchFileKind::Gif, _) => "gif",
+        (BatchFileKind::Image, BatchOutput::Text) => "txt",
+        (BatchFileKind::Image, BatchOutput::Image) => {
+            if is_color {
+                "png"
+            } else {
+                "jpg"
+            }
+        }
+    };
+    return relative_path.with_extension(extension);
+}
+
+// find every file of a directory, the paths are relative to the input directory and sorted
+//
+// a directory that is reached again through a symlink is only walked once
+pub fn collect_file_list(
+    input_directory: &Path,
+    relative_directory: &Path,
+    is_recursive: bool,
+    relative_path_list: &mut Vec<PathBuf>,
+) -> Result<(), String> {
+    return walk_directory(
+        input_directory,
+        relative_directory,
+        is_recursive,
+        &mut HashSet::new(),
+        relative_path_list,
+    );
+}
+
+fn walk_directory(
+    input_directory: &Path,
+    relative_directory: &Path,
+    is_recursive: bool,
+    visited_directory_set: &mut HashSet<PathBuf>,
+    relative_path_list: &mut Vec<PathBuf>,
+) -> Result<(), String> {
+    let directory = input_directory.join(relative_directory);
+    // a symlink back to a parent directory would be walked forever
+    let canonical_directory = directory
+        .canonicalize()
+        .map_err(|e| format!("Failed to read directory {}: {}", directory.display(), e))?;
+    if !visited_directory_set.insert(canonical_directory) {
+        return Ok(());
+    }
+    let mut entry_list = read_dir(&directory)
+        .map_err(|e| format!("Failed to read directory {}: {}", directory.display(), e))?
+        .filter_map(|entry| entry.ok())
+        .collect::<Vec<_>>();
+    entry_list.sort_by_key(|entry| entry.file_name());
+
+    for entry in entry_list {
+        let relative_path = relative_directory.join(entry.file_name());
+        let path = entry.path();
+        if path.is_dir() {
+            if is_recursive {
+                walk_directory(
+                    input_directory,
+                    &relative_path,
+                    is_recursive,
+                    visited_directory_set,
+                    relative_path_list,
+                )?;
+            }
+        } else if path.is_file() {
+            relative_path_list.push(relative_path);
+        }
+    }
+
+    return Ok(());
+}
+
+fn build_glob_set(pattern_list: &[String]) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in pattern_list {
+        let glob =
+            Glob::new(pattern).map_err(|e| format!("Invalid glob pattern {}: {}", pattern, e))?;
+        builder.add(glob);
+    }
+    return builder
+        .build()
+        .map_err(|e| format!("Invalid glob pattern: {}", e));
+}
+
+// only the files that the image crate can read are converted, the rest are left out of the batch
+pub fn get_batch_file_kind(path: &Path) -> Option<BatchFileKind> {
+    match ImageFormat::from_path(path) {
+        Ok(ImageFormat::Gif) => return Some(BatchFileKind::Gif),
+        Ok(image_format) if image_format.reading_enabled() => return Some(BatchFileKind::Image),
+        _ => return None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, remove_dir_all, write};
+
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+
+    // a new empty directory for every test, so the tests can run together
+    fn create_test_directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("rasciify_batch_{}_{}", name, std::process::id()));
+        let _ = remove_dir_all(&directory);
+        create_dir_all(&directory).unwrap();
+        return directory;
+    }
+
+    fn save_test_image(path: &Path) {
+        create_dir_all(path.parent().unwrap()).unwrap();
+        RgbImage::from_pixel(32, 32, Rgb([200, 100, 50]))
+            .save(path)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_get_batch_output_path() {
+        let relative_path = Path::new("a/cat.jpeg");
+        assert_eq!(
+            get_batch_output_path(
+                relative_path,
+                BatchFileKind::Image,
+                true,
+                BatchOutput::Image
+            ),
+            Path::new("a/cat.png")
+        );
+        assert_eq!(
+            get_batch_output_path(
+                relative_path,
+                BatchFileKind::Image,
+                false,
+                BatchOutput::Image
+            ),
+            Path::new("a/cat.jpg")
+        );
+        assert_eq!(
+            get_batch_output_path(relative_path, BatchFileKind::Image, true, BatchOutput::Text),
+            Path::new("a/cat.txt")
+        );
+        assert_eq!(
+            get_batch_output_path(
+                Path::new("cat.gif"),
+                BatchFileKind::Gif,
+                true,
+                BatchOutput::Text
+            ),
+            Path::new("cat.gif")
+        );
+    }
+
+    #[test]
+    fn test_batch_convert_directory() {
+        let directory = create_test_directory("directory");
+        let input_directory = directory.join("input");
+        let output_directory = directory.join("output");
+        save_test_image(&input_directory.join("cat.png"));
+        save_test_image(&input_directory.join("sub/dog.png"));
+        save_test_image(&input_directory.join("skip/bird.png"));
+        write(input_directory.join("note.txt"), "not an image").unwrap();
+
+        let batch_option = BatchOption::new()
+            .with_recursive(true)
+            .with_exclude("skip/**")
+            .with_num_threads(2);
+        let report = batch_convert(
+            input_directory.to_str().unwrap(),
+            output_directory.to_str().unwrap(),
+            CharacterType::Simple,
+            SettingOption::rgb(8),
+            &batch_option,
+        )
+        .unwrap();
+
+        // the files are reported in sorted order, the text file and the excluded directory are left out
+        let input_path_list: Vec<PathBuf> = report
+            .file_result_list
+            .iter()
+            .map(|file_result| file_result.input_path.clone())
+            .collect();
+        assert_eq!(
+            input_path_list,
+            vec![
+                input_directory.join("cat.png"),
+                input_directory.join("sub/dog.png")
+            ]
+        );
+        assert_eq!(report.get_success_count(), 2);
+        assert!(output_directory.join("cat.png").is_file());
+        assert!(output_directory.join("sub/dog.png").is_file());
+
+        remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_batch_output_collision() {
+        let directory = create_test_directory("collision");
+        let input_directory = directory.join("input");
+        let output_directory = directory.join("output");
+        save_test_image(&input_directory.join("cat.jpg"));
+        save_test_image(&input_directory.join("cat.png"));
+
+        let report = batch_convert(
+            input_directory.to_str().unwrap(),
+            output_directory.to_str().unwrap(),
+            CharacterType::Simple,
+            SettingOption::rgb(8),
+            &BatchOption::new(),
+        )
+        .unwrap();
+
+        // both would be saved to cat.png, only the first one in sorted order is converted
+        assert_eq!(report.get_success_count(), 1);
+        let failure_list = report.get_failure_list();
+        assert_eq!(failure_list.len(), 1);
+        assert_eq!(failure_list[0].input_path, input_directory.join("cat.png"));
+        assert!(failure_list[0]
+            .result
+            .as_ref()
+            .unwrap_err()
+            .contains("is already the output of"));
+
+        remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_collect_file_list_symlink_cycle() {
+        let directory = create_test_directory("symlink");
+        save_test_image(&directory.join("sub/cat.png"));
+        std::os::unix::fs::symlink(&directory, directory.join("sub/parent")).unwrap();
+
+        let mut relative_path_list = vec![];
+        collect_file_list(&directory, Path::new(""), true, &mut relative_path_list).unwrap();
+        assert_eq!(relative_path_list, vec![PathBuf::from("sub/cat.png")]);
+
+        remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_batch_invalid_option() {
+        let directory = create_test_directory("invalid");
+
+        // the input directory doesn't exist
+        assert!(batch_convert(
+            directory.join("missing").to_str().unwrap(),
+            directory.join("output").to_str().unwrap(),
+            CharacterType::Simple,
+            SettingOption::rgb(8),
+            &BatchOption::new(),
+        )
+        .is_err());
+        // only Simple and Complex can be converted to text
+        assert!(batch_convert(
+            directory.to_str().unwrap(),
+            directory.join("output").to_str().unwrap(),
+            CharacterType::Bar,
+            SettingOption::rgb(8),
+            &BatchOption::new().with_output(BatchOutput::Text),
+        )
+        .is_err());
+        assert!(batch_convert(
+            directory.to_str().unwrap(),
+            directory.join("output").to_str().unwrap(),
+            CharacterType::Simple,
+            SettingOption::rgb(8),
+            &BatchOption::new().with_include("[invalid"),
+        )
+        .is_err());
+
+        remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,113 @@
+// the HTTP conversion service of the `server` feature
+//
+// cargo run --release --features server --bin rasciify-server -- --address 0.0.0.0:8080 --workers 4
+use std::{process::exit, time::Duration};
+
+use rasciify::server::{run_server_with_callback, RequestLog, ServerOption};
+
+const USAGE: &str = "Usage: rasciify-server [OPTIONS]
+
+Options:
+  --address <ADDRESS>              address to listen on [default: 127.0.0.1:8080]
+  --workers <NUM>                  number of conversions run at the same time [default: number of CPUs]
+  --queue-size <NUM>               number of requests waiting for a worker [default: 4 x workers]
+  --max-body-size <BYTES>          largest upload [default: 20971520]
+  --max-image-dimension <PIXELS>   largest width and height of an upload [default: 8192]
+  --max-num-cols <NUM>             largest num_cols a request can ask for [default: 500]
+  --timeout <SECONDS>              time limit of a GIF conversion [default: 30]
+  --body-timeout <SECONDS>         time limit of sending an upload [default: 10]
+  --max-frame-window <NUM>         largest frame_window a request can ask for [default: 64]
+  --max-gif-frames <NUM>           most frames of an uploaded GIF [default: 1000]
+  --max-gif-pixels <PIXELS>        most pixels of all the frames of an uploaded GIF [default: 1000000000]
+  -h, --help                       print this help";
+
+fn main() {
+    let server_option = match parse_args(std::env::args().skip(1).collect()) {
+        Ok(server_option) => server_option,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            exit(2);
+        }
+    };
+
+    eprintln!(
+        "rasciify-server listening on http://{}",
+        server_option.address
+    );
+    if let Err(e) = run_server_with_callback(server_option, log_request) {
+        eprintln!("{}", e);
+        exit(1);
+    }
+}
+
+fn log_request(request_log: &RequestLog) {
+    eprintln!(
+        "{} {} {} {:.2?}",
+        request_log.method, request_log.path, request_log.status_code, request_log.duration
+    );
+}
+
+fn parse_args(arg_list: Vec<String>) -> Result<ServerOption, String> {
+    let mut server_option = ServerOption::new();
+    let mut is_queue_size_set = false;
+    let mut arg_iter = arg_list.iter();
+    while let Some(arg) = arg_iter.next() {
+        if arg == "-h" || arg == "--help" {
+            println!("{}", USAGE);
+            exit(0);
+        }
+        let value = arg_iter
+            .next()
+            .ok_or(format!("Missing the value of {}", arg))?;
+        match arg.as_str() {
+            "--address" => server_option = server_option.with_address(value),
+            "--workers" => {
+                server_option = server_option.with_num_workers(parse_number(arg, value)?)
+            }
+            "--queue-size" => {
+                server_option = server_option.with_queue_size(parse_number(arg, value)?);
+                is_queue_size_set = true;
+            }
+            "--max-body-size" => {
+                server_option = server_option.with_max_body_size(parse_number(arg, value)?)
+            }
+            "--max-image-dimension" => {
+                server_option = server_option.with_max_image_dimension(parse_number(arg, value)?)
+            }
+            "--max-num-cols" => {
+                server_option = server_option.with_max_num_cols(parse_number(arg, value)?)
+            }
+            "--timeout" => {
+                server_option =
+                    server_option.with_timeout(Duration::from_secs(parse_number(arg, value)?))
+            }
+            "--body-timeout" => {
+                server_option =
+                    server_option.with_body_timeout(Duration::from_secs(parse_number(arg, value)?))
+            }
+            "--max-frame-window" => {
+                server_option = server_option.with_max_frame_window(parse_number(arg, value)?)
+            }
+            "--max-gif-frames" => {
+                server_option = server_option.with_max_gif_frames(parse_number(arg, value)?)
+            }
+            "--max-gif-pixels" => {
+                server_option = server_option.with_max_gif_pixels(parse_number(arg, value)?)
+            }
+            _ => return Err(format!("Unknown option: {}", arg)),
+        }
+    }
+
+    // the default queue follow the number of workers
+    if !is_queue_size_set {
+        let queue_size = server_option.num_workers * 4;
+        server_option = server_option.with_queue_size(queue_size);
+    }
+    return Ok(server_option);
+}
+
+fn parse_number<T: std::str::FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    return value
+        .parse()
+        .map_err(|_| format!("Invalid value of {}: {}", arg, value));
+}
@@ -0,0 +1,158 @@
+// convert images and GIFs again every time they change, the binary of the `watch` feature
+//
+// cargo run --release --features watch --bin rasciify-watch -- art/cat.png art/frames --output ascii --terminal
+use std::{process::exit, time::Duration};
+
+use rasciify::{
+    batch::BatchOutput,
+    config::DEFAULT_NUM_COLS,
+    watch::{watch_with_callback, WatchOption},
+    CancellationToken, CharacterType, ColorTheme, Palette, PalettePreset, SettingOption,
+};
+
+const USAGE: &str = "Usage: rasciify-watch [OPTIONS] <PATH>...
+
+Every PATH is an image, a GIF or a directory of them, they are converted once and again every time they change.
+
+Options:
+  -o, --output <DIR>          directory the ASCII art is saved to [default: ascii]
+  --text                      convert the still images to text instead of images
+  --terminal                  redraw the ASCII art of the last changed file in the terminal
+  --no-recursive              leave out the sub directories of a watched directory
+  --debounce <MS>             how long a file must stay unchanged before it is converted [default: 300]
+  --character-type <NAME>     e.g. simple, complex, bar or zh_simplified [default: simple]
+  --num-cols <NUM>            number of columns of the ASCII art [default: 100]
+  --color                     in the colors of the image instead of grayscale
+  --color-theme <NAME>        e.g. amber or green_phosphor
+  --palette <NAME>            e.g. gameboy or xterm256
+  -h, --help                  print this help";
+
+// the parsed command line
+struct WatchArgs {
+    path_list: Vec<String>,
+    output_directory: String,
+    character_type: CharacterType,
+    setting_option: SettingOption,
+    watch_option: WatchOption,
+}
+
+fn main() {
+    let watch_args = match parse_args(std::env::args().skip(1).collect()) {
+        Ok(watch_args) => watch_args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            exit(2);
+        }
+    };
+
+    // Ctrl-C stop the watch so the terminal preview can show the cursor again
+    let cancellation_token = CancellationToken::new();
+    let handler_token = cancellation_token.clone();
+    if let Err(e) = ctrlc::set_handler(move || handler_token.cancel()) {
+        eprintln!("Failed to set the Ctrl-C handler: {}", e);
+        exit(1);
+    }
+    let watch_option = watch_args
+        .watch_option
+        .with_cancellation_token(cancellation_token);
+
+    let is_terminal_preview = watch_option.is_terminal_preview;
+    let result = watch_with_callback(
+        &watch_args.path_list,
+        &watch_args.output_directory,
+        watch_args.character_type,
+        watch_args.setting_option,
+        &watch_option,
+        |file_result| {
+            // the message is drawn under the preview instead
+            if is_terminal_preview {
+                return;
+            }
+            match &file_result.result {
+                Ok(message) => eprintln!("{}", message),
+                Err(e) => eprintln!("{}: {}", file_result.input_path.display(), e),
+            }
+        },
+    );
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        exit(1);
+    }
+}
+
+fn parse_args(arg_list: Vec<String>) -> Result<WatchArgs, String> {
+    let mut watch_args = WatchArgs {
+        path_list: vec![],
+        output_directory: "ascii".to_string(),
+        character_type: CharacterType::Simple,
+        setting_option: SettingOption::grayscale(DEFAULT_NUM_COLS),
+        watch_option: WatchOption::new(),
+    };
+
+    let mut arg_iter = arg_list.iter();
+    while let Some(arg) = arg_iter.next() {
+        let mut get_value = || {
+            return arg_iter
+                .next()
+                .ok_or(format!("Missing the value of {}", arg));
+        };
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                exit(0);
+            }
+            "-o" | "--output" => watch_args.output_directory = get_value()?.to_string(),
+            "--text" => {
+                watch_args.watch_option = watch_args.watch_option.with_output(BatchOutput::Text)
+            }
+            "--terminal" => {
+                watch_args.watch_option = watch_args.watch_option.with_terminal_preview(true)
+            }
+            "--no-recursive" => {
+                watch_args.watch_option = watch_args.watch_option.with_recursive(false)
+            }
+            "--debounce" => {
+                let value = get_value()?;
+                let debounce = value
+                    .parse()
+                    .map_err(|_| format!("Invalid value of {}: {}", arg, value))?;
+                watch_args.watch_option = watch_args
+                    .watch_option
+                    .with_debounce(Duration::from_millis(debounce));
+            }
+            "--character-type" => {
+                let value = get_value()?;
+                watch_args.character_type = CharacterType::from_name(value)
+                    .ok_or(format!("Unknown character type: {}", value))?;
+            }
+            "--num-cols" => {
+                let value = get_value()?;
+                watch_args.setting_option.num_cols = value
+                    .parse()
+                    .map_err(|_| format!("Invalid value of {}: {}", arg, value))?;
+            }
+            "--color" => watch_args.setting_option.is_color = true,
+            "--color-theme" => {
+                let value = get_value()?;
+                let color_theme = ColorTheme::from_name(value)
+                    .ok_or(format!("Unknown color theme: {}", value))?;
+                watch_args.setting_option = watch_args.setting_option.with_color_theme(color_theme);
+            }
+            "--palette" => {
+                let value = get_value()?;
+                let palette_preset =
+                    PalettePreset::from_name(value).ok_or(format!("Unknown palette: {}", value))?;
+                watch_args.setting_option = watch_args
+                    .setting_option
+                    .with_palette(Palette::from_preset(palette_preset));
+            }
+            _ if arg.starts_with('-') => return Err(format!("Unknown option: {}", arg)),
+            _ => watch_args.path_list.push(arg.to_string()),
+        }
+    }
+
+    if watch_args.path_list.is_empty() {
+        return Err("No path to watch".to_string());
+    }
+    return Ok(watch_args);
+}
@@ -0,0 +1,530 @@
+// the C API of the cdylib, the header is generated to OUT_DIR/rasciify.h by the build script
+//
+// every function return a `RasciifyStatus`, the message of the last error of the calling thread is
+// kept until the next failed call and can be read with `rasciify_last_error_message()`
+// the strings, buffers and images given out must be freed with their own free function
+#![allow(clippy::missing_safety_doc)]
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    io::Cursor,
+    panic::{catch_unwind, AssertUnwindSafe},
+    ptr::{null, slice_from_raw_parts_mut},
+    slice,
+};
+
+use gif::DecodeOptions;
+use image::DynamicImage;
+
+use crate::{
+    config::{RenderConfig, RenderConfigBuilder},
+    decode_gif, grayscale_to_ascii, grayscale_to_color_theme_ascii_img,
+    preprocess::{apply_preprocess, load_image_from_memory, Preprocess},
+    rgb_to_rgb_ascii_img, stream_frames_to_ascii_gray_gif_with_progress,
+    stream_frames_to_ascii_rgb_gif_with_progress,
+    utils::utils::get_panic_message,
+    CharacterType, ColorTheme, Palette, PalettePreset, ProgressOption,
+};
+
+/// The result of every call, the message of an error is given by `rasciify_last_error_message()`.
+#[repr(C)]
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum RasciifyStatus {
+    Ok = 0,
+    /// A required pointer was NULL.
+    NullPointer = 1,
+    /// An option or a name was not valid.
+    InvalidArgument = 2,
+    /// The input could not be decoded or converted.
+    ConversionFailed = 3,
+    /// A bug in rasciify, the message has the details.
+    Panic = 4,
+}
+
+/// The settings of a conversion, created with `rasciify_config_new()` and freed with `rasciify_config_free()`.
+pub struct RasciifyConfig {
+    builder: RenderConfigBuilder,
+}
+
+/// Bytes given out by rasciify, e.g. an encoded GIF.
+#[repr(C)]
+pub struct RasciifyBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+/// An RGBA image given out by rasciify, 4 bytes per pixel row by row.
+#[repr(C)]
+pub struct RasciifyImage {
+    pub data: *mut u8,
+    pub len: usize,
+    pub width: u32,
+    pub height: u32,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// The settings of a conversion with the defaults of `RenderConfig::builder()`.
+#[no_mangle]
+pub extern "C" fn rasciify_config_new() -> *mut RasciifyConfig {
+    return Box::into_raw(Box::new(RasciifyConfig {
+        builder: RenderConfig::builder(),
+    }));
+}
+
+/// Free a config from `rasciify_config_new()`, NULL is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn rasciify_config_free(config: *mut RasciifyConfig) {
+    if !config.is_null() {
+        drop(Box::from_raw(config));
+    }
+}
+
+/// Set the character type by name, e.g. "simple", "bar" or "zh_simplified".
+#[no_mangle]
+pub unsafe extern "C" fn rasciify_config_set_character_type(
+    config: *mut RasciifyConfig,
+    name: *const c_char,
+) -> RasciifyStatus {
+    return run_ffi(|| {
+        let name = get_str(name)?;
+        let character_type = CharacterType::from_name(name).ok_or((
+            RasciifyStatus::InvalidArgument,
+            format!("Unknown character type: {}", name),
+        ))?;
+        update_config(config, |builder| builder.character_type(character_type))
+    });
+}
+
+/// Set the number of columns of the ASCII art.
+#[no_mangle]
+pub unsafe extern "C" fn rasciify_config_set_num_cols(
+    config: *mut RasciifyConfig,
+    num_cols: u32,
+) -> RasciifyStatus {
+    return run_ffi(|| update_config(config, |builder| builder.num_cols(num_cols)));
+}
+
+/// Convert in the colors of the image instead of grayscale.
+#[no_mangle]
+pub unsafe extern "C" fn rasciify_config_set_color(
+    config: *mut RasciifyConfig,
+    is_color: bool,
+) -> RasciifyStatus {
+    return run_ffi(|| update_config(config, |builder| builder.color(is_color)));
+}
+
+/// Draw the ASCII art on a white background.
+#[no_mangle]
+pub unsafe extern "C" fn rasciify_config_set_white_bg(
+    config: *mut RasciifyConfig,
+    is_white_bg: bool,
+) -> RasciifyStatus {
+    return run_ffi(|| update_config(config, |builder| builder.white_bg(is_white_bg)));
+}
+
+/// Leave the background of the ASCII art transparent.
+#[no_mangle]
+pub unsafe extern "C" fn rasciify_config_set_transparent_bg(
+    config: *mut RasciifyConfig,
+    is_transparent_bg: bool,
+) -> RasciifyStatus {
+    return run_ffi(|| update_config(config, |builder| builder.transparent_bg(is_transparent_bg)));
+}
+
+/// Set a color theme by name, e.g. "amber" or "green_phosphor".
+#[no_mangle]
+pub unsafe extern "C" fn rasciify_config_set_color_theme(
+    config: *mut RasciifyConfig,
+    name: *const c_char,
+) -> RasciifyStatus {
+    return run_ffi(|| {
+        let name = get_str(name)?;
+        let color_theme = ColorTheme::from_name(name).ok_or((
+            RasciifyStatus::InvalidArgument,
+            format!("Unknown color theme: {}", name),
+        ))?;
+        update_config(config, |builder| builder.color_theme(color_theme))
+    });
+}
+
+/// Restrict the colors to a palette preset by name, e.g. "gameboy" or "xterm256".
+#[no_mangle]
+pub unsafe extern "C" fn rasciify_config_set_palette(
+    config: *mut RasciifyConfig,
+    name: *const c_char,
+) -> RasciifyStatus {
+    return run_ffi(|| {
+        let name = get_str(name)?;
+        let palette_preset = PalettePreset::from_name(name).ok_or((
+            RasciifyStatus::InvalidArgument,
+            format!("Unknown palette: {}", name),
+        ))?;
+        update_config(config, |builder| {
+            builder.palette(Palette::from_preset(palette_preset))
+        })
+    });
+}
+
+/// Set the number of GIF frames converted at a time.
+#[no_mangle]
+pub unsafe extern "C" fn rasciify_config_set_frame_window(
+    config: *mut RasciifyConfig,
+    frame_window: usize,
+) -> RasciifyStatus {
+    return run_ffi(|| update_config(config, |builder| builder.frame_window(frame_window)));
+}
+
+/// Convert an encoded image (PNG, JPEG, ...) to ASCII art text, only the "simple" and "complex" character types are supported.
+/// The text is written to `out_text` and must be freed with `rasciify_string_free()`.
+#[no_mangle]
+pub unsafe extern "C" fn rasciify_image_to_text(
+    config: *const RasciifyConfig,
+    data: *const u8,
+    len: usize,
+    out_text: *mut *mut c_char,
+) -> RasciifyStatus {
+    return run_ffi(|| {
+        let render_config = build_config(config)?;
+        let preprocess = render_config.setting_option.preprocess;
+        let img = load_image(data, len, preprocess)?;
+        let img = apply_preprocess(&img, preprocess);
+        if out_text.is_null() {
+            return Err(null_pointer_error("out_text"));
+        }
+        let complex = match render_config.character_type {
+            CharacterType::Simple => false,
+            CharacterType::Complex => true,
+            _ => {
+                return Err((
+                    RasciifyStatus::InvalidArgument,
+                    "Only the simple and complex character types can be converted to text"
+                        .to_string(),
+                ))
+            }
+        };
+
+        let ascii = grayscale_to_ascii(&img, render_config.setting_option.num_cols, complex);
+        let ascii = CString::new(ascii).map_err(|e| {
+            (
+                RasciifyStatus::ConversionFailed,
+                format!("The text contains a NUL byte: {}", e),
+            )
+        })?;
+        *out_text = ascii.into_raw();
+        return Ok(());
+    });
+}
+
+/// Convert an encoded image (PNG, JPEG, ...) to an RGBA ASCII art image.
+/// The image is written to `out_image` and must be freed with `rasciify_image_free()`.
+#[no_mangle]
+pub unsafe extern "C" fn rasciify_image_to_image(
+    config: *const RasciifyConfig,
+    data: *const u8,
+    len: usize,
+    out_image: *mut RasciifyImage,
+) -> RasciifyStatus {
+    return run_ffi(|| {
+        let render_config = build_config(config)?;
+        let img = load_image(data, len, render_config.setting_option.preprocess)?;
+        if out_image.is_null() {
+            return Err(null_pointer_error("out_image"));
+        }
+
+        let setting_option = render_config.setting_option;
+        let rgba_ascii_img = if setting_option.is_color {
+            rgb_to_rgb_ascii_img(&img, render_config.character_type, setting_option)
+        } else {
+            // the gray levels are black and white, or in the colors of the color theme
+            let rgb_ascii_img = grayscale_to_color_theme_ascii_img(
+                &img,
+                render_config.character_type,
+                setting_option,
+            );
+            DynamicImage::ImageRgb8(rgb_ascii_img).into_rgba8()
+        };
+
+        let (width, height) = rgba_ascii_img.dimensions();
+        let (data, len) = into_raw_bytes(rgba_ascii_img.into_raw());
+        *out_image = RasciifyImage {
+            data,
+            len,
+            width,
+            height,
+        };
+        return Ok(());
+    });
+}
+
+/// Convert an encoded GIF to an ASCII art GIF.
+/// The GIF is written to `out_buffer` and must be freed with `rasciify_buffer_free()`.
+#[no_mangle]
+pub unsafe extern "C" fn rasciify_gif_to_gif(
+    config: *const RasciifyConfig,
+    data: *const u8,
+    len: usize,
+    out_buffer: *mut RasciifyBuffer,
+) -> RasciifyStatus {
+    return run_ffi(|| {
+        let render_config = build_config(config)?;
+        let gif_data = get_bytes(data, len)?;
+        if out_buffer.is_null() {
+            return Err(null_pointer_error("out_buffer"));
+        }
+        let setting_option = render_config.setting_option;
+        let decoder =
+            decode_gif(Cursor::new(gif_data), DecodeOptions::new()).map_err(conversion_error)?;
+        let result = if setting_option.is_color {
+            stream_frames_to_ascii_rgb_gif_with_progress(
+                decoder,
+                Vec::new(),
+                render_config.character_type,
+                setting_option,
+                ProgressOption::new(),
+            )
+        } else {
+            stream_frames_to_ascii_gray_gif_with_progress(
+                decoder,
+                Vec::new(),
+                render_config.character_type,
+                setting_option,
+                ProgressOption::new(),
+            )
+        };
+        let gif_buffer = result.map_err(conversion_error)?;
+
+        let (data, len) = into_raw_bytes(gif_buffer);
+        *out_buffer = RasciifyBuffer { data, len };
+        return Ok(());
+    });
+}
+
+/// Free a string given out by rasciify, NULL is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn rasciify_string_free(text: *mut c_char) {
+    if !text.is_null() {
+        drop(CString::from_raw(text));
+    }
+}
+
+/// Free a buffer given out by rasciify.
+#[no_mangle]
+pub unsafe extern "C" fn rasciify_buffer_free(buffer: RasciifyBuffer) {
+    free_raw_bytes(buffer.data, buffer.len);
+}
+
+/// Free an image given out by rasciify.
+#[no_mangle]
+pub unsafe extern "C" fn rasciify_image_free(image: RasciifyImage) {
+    free_raw_bytes(image.data, image.len);
+}
+
+/// The message of the last failed call on this thread, NULL if no call failed.
+/// The message belongs to rasciify and is valid until the next failed call on this thread.
+#[no_mangle]
+pub extern "C" fn rasciify_last_error_message() -> *const c_char {
+    return LAST_ERROR.with(|last_error| {
+        return last_error
+            .borrow()
+            .as_ref()
+            .map_or(null(), |message| message.as_ptr());
+    });
+}
+
+type FfiError = (RasciifyStatus, String);
+
+// run the body of a C function, the error is kept as the last error and a panic doesn't cross into C
+fn run_ffi<F: FnOnce() -> Result<(), FfiError>>(body: F) -> RasciifyStatus {
+    let (status, message) = match catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(())) => return RasciifyStatus::Ok,
+        Ok(Err(error)) => error,
+        Err(panic) => (RasciifyStatus::Panic, get_panic_message(panic.as_ref())),
+    };
+
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+    return status;
+}
+
+unsafe fn update_config<F: FnOnce(RenderConfigBuilder) -> RenderConfigBuilder>(
+    config: *mut RasciifyConfig,
+    update: F,
+) -> Result<(), FfiError> {
+    let config = config.as_mut().ok_or(null_pointer_error("config"))?;
+    config.builder = update(config.builder.clone());
+    return Ok(());
+}
+
+// the options are validated when they are used, so the setters never fail on a bad combination
+unsafe fn build_config(config: *const RasciifyConfig) -> Result<RenderConfig, FfiError> {
+    let config = config.as_ref().ok_or(null_pointer_error("config"))?;
+    return config
+        .builder
+        .clone()
+        .build()
+        .map_err(|e| (RasciifyStatus::InvalidArgument, e));
+}
+
+unsafe fn get_str<'a>(text: *const c_char) -> Result<&'a str, FfiError> {
+    if text.is_null() {
+        return Err(null_pointer_error("name"));
+    }
+    return CStr::from_ptr(text).to_str().map_err(|e| {
+        (
+            RasciifyStatus::InvalidArgument,
+            format!("The name is not valid UTF-8: {}", e),
+        )
+    });
+}
+
+unsafe fn get_bytes<'a>(data: *const u8, len: usize) -> Result<&'a [u8], FfiError> {
+    if data.is_null() {
+        return Err(null_pointer_error("data"));
+    }
+    return Ok(slice::from_raw_parts(data, len));
+}
+
+// decode the image and check that the preprocess doesn't make it too large
+unsafe fn load_image(
+    data: *const u8,
+    len: usize,
+    preprocess: Preprocess,
+) -> Result<DynamicImage, FfiError> {
+    let image_data = get_bytes(data, len)?;
+    let img = load_image_from_memory(image_data, preprocess.is_auto_orient)
+        .map_err(|e| conversion_error(format!("Failed to decode image: {}", e)))?;
+    preprocess
+        .get_output_size(img.width(), img.height())
+        .map_err(|e| (RasciifyStatus::InvalidArgument, e))?;
+    return Ok(img);
+}
+
+fn into_raw_bytes(bytes: Vec<u8>) -> (*mut u8, usize) {
+    let len = bytes.len();
+    let data = Box::into_raw(bytes.into_boxed_slice()) as *mut u8;
+    return (data, len);
+}
+
+unsafe fn free_raw_bytes(data: *mut u8, len: usize) {
+    if data.is_null() {
+        return;
+    }
+    drop(Box::from_raw(slice_from_raw_parts_mut(data, len)));
+}
+
+fn null_pointer_error(name: &str) -> FfiError {
+    return (
+        RasciifyStatus::NullPointer,
+        format!("{} must not be NULL", name),
+    );
+}
+
+fn conversion_error(message: String) -> FfiError {
+    return (RasciifyStatus::ConversionFailed, message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr::null_mut;
+
+    fn get_png_data() -> Vec<u8> {
+        let mut png_data = Cursor::new(vec![]);
+        DynamicImage::new_rgb8(40, 40)
+            .write_to(&mut png_data, image::ImageFormat::Png)
+            .unwrap();
+        return png_data.into_inner();
+    }
+
+    unsafe fn get_last_error_message() -> String {
+        return CStr::from_ptr(rasciify_last_error_message())
+            .to_string_lossy()
+            .to_string();
+    }
+
+    #[test]
+    fn set_config_by_name() {
+        unsafe {
+            let config = rasciify_config_new();
+            let status = rasciify_config_set_character_type(config, c"bar".as_ptr());
+            assert_eq!(status, RasciifyStatus::Ok);
+            let status = rasciify_config_set_palette(config, c"unknown".as_ptr());
+            assert_eq!(status, RasciifyStatus::InvalidArgument);
+            assert_eq!(get_last_error_message(), "Unknown palette: unknown");
+            let status = rasciify_config_set_palette(config, null());
+            assert_eq!(status, RasciifyStatus::NullPointer);
+            let status = rasciify_config_set_num_cols(null_mut(), 10);
+            assert_eq!(status, RasciifyStatus::NullPointer);
+            rasciify_config_free(config);
+        }
+    }
+
+    #[test]
+    fn convert_image() {
+        let png_data = get_png_data();
+        unsafe {
+            let config = rasciify_config_new();
+            rasciify_config_set_num_cols(config, 10);
+
+            let mut text: *mut c_char = null_mut();
+            let status =
+                rasciify_image_to_text(config, png_data.as_ptr(), png_data.len(), &mut text);
+            assert_eq!(status, RasciifyStatus::Ok);
+            assert_eq!(CStr::from_ptr(text).to_str().unwrap().lines().count(), 5);
+            rasciify_string_free(text);
+
+            let mut out_image = RasciifyImage {
+                data: null_mut(),
+                len: 0,
+                width: 0,
+                height: 0,
+            };
+            let status =
+                rasciify_image_to_image(config, png_data.as_ptr(), png_data.len(), &mut out_image);
+            assert_eq!(status, RasciifyStatus::Ok);
+            assert_eq!(
+                out_image.len,
+                out_image.width as usize * out_image.height as usize * 4
+            );
+            rasciify_image_free(out_image);
+
+            // the text output only has the simple and complex character types
+            rasciify_config_set_character_type(config, c"bar".as_ptr());
+            let status =
+                rasciify_image_to_text(config, png_data.as_ptr(), png_data.len(), &mut text);
+            assert_eq!(status, RasciifyStatus::InvalidArgument);
+            let status =
+                rasciify_image_to_text(config, png_data.as_ptr(), png_data.len(), null_mut());
+            assert_eq!(status, RasciifyStatus::NullPointer);
+            rasciify_config_free(config);
+        }
+    }
+
+    #[test]
+    fn convert_invalid_gif() {
+        let gif_data = b"GIF89a";
+        unsafe {
+            let config = rasciify_config_new();
+            let mut out_buffer = RasciifyBuffer {
+                data: null_mut(),
+                len: 0,
+            };
+            let status =
+                rasciify_gif_to_gif(config, gif_data.as_ptr(), gif_data.len(), &mut out_buffer);
+            assert_eq!(status, RasciifyStatus::ConversionFailed);
+            assert!(out_buffer.data.is_null());
+            rasciify_config_free(config);
+        }
+    }
+
+    #[test]
+    fn catch_panic() {
+        let status = run_ffi(|| panic!("bug"));
+        assert_eq!(status, RasciifyStatus::Panic);
+        assert_eq!(unsafe { get_last_error_message() }, "bug");
+    }
+}
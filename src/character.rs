@@ -2,6 +2,9 @@ use ab_glyph::{FontRef, PxScale};
 
 use crate::utils::utils::sort_character_brightness;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 pub struct FontData<'a> {
     pub character_list: Vec<char>,
     pub font: FontRef<'a>,
@@ -11,6 +14,12 @@ pub struct FontData<'a> {
 }
 
 #[derive(PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[cfg_attr(feature = "python", pyo3::pyclass(eq, eq_int))]
 pub enum CharacterType {
     Simple,        // simple symbol chracters
     Complex,       // complex symbol characters
@@ -36,6 +45,34 @@ pub enum CharacterType {
 }
 
 impl CharacterType {
+    // get a character type by name, e.g. "zh_simplified"
+    pub fn from_name(name: &str) -> Option<CharacterType> {
+        match name.to_lowercase().replace(['-', ' '], "_").as_str() {
+            "simple" => return Some(CharacterType::Simple),
+            "complex" => return Some(CharacterType::Complex),
+            "bar" => return Some(CharacterType::Bar),
+            "en" => return Some(CharacterType::En),
+            "ru" => return Some(CharacterType::Ru),
+            "de" => return Some(CharacterType::De),
+            "fr" => return Some(CharacterType::Fr),
+            "es" => return Some(CharacterType::Es),
+            "it" => return Some(CharacterType::It),
+            "pt" => return Some(CharacterType::Pt),
+            "pl" => return Some(CharacterType::Pl),
+            "hi" => return Some(CharacterType::Hi),
+            "ar" => return Some(CharacterType::Ar),
+            "bn" => return Some(CharacterType::Bn),
+            "zh_zhuyin" => return Some(CharacterType::ZhZhuyin),
+            "zh_simplified" => return Some(CharacterType::ZhSimplified),
+            "zh_traditional" => return Some(CharacterType::ZhTraditional),
+            "jp_hiragana" => return Some(CharacterType::JpHiragana),
+            "jp_katakana" => return Some(CharacterType::JpKatakana),
+            "kr" => return Some(CharacterType::Kr),
+            "vi" => return Some(CharacterType::Vi),
+            _ => return None,
+        }
+    }
+
     fn get_character_array(&self) -> Vec<char> {
         match self {
             CharacterType::Simple => vec![' ', '.', ':', '-', '=', '+', '*', '#', '%', '@'],
@@ -0,0 +1,502 @@
+use std::sync::Arc;
+
+use crate::{
+    ansi_art_to_html, ansi_art_to_image, batch_convert, dynamic_image_to_text, gif_to_gif,
+    gif_to_gif_with_progress, image_sequence_to_gif, image_sequence_to_images, image_to_image,
+    preprocess::{apply_preprocess, open_image},
+    text_to_image,
+    utils::utils::apply_tone,
+    y4m_to_gif, AlphaMode, BatchOption, BatchReport, CellBackground, CharacterType, ColorTheme,
+    CropRect, FrameSelection, Padding, Palette, PreScale, Preprocess, ProgressOption, ResizeFilter,
+    Rotation, SettingOption, Tone,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// the number of columns used if it was not set
+pub const DEFAULT_NUM_COLS: u32 = 100;
+
+// everything needed to convert an image or a gif, built and validated through RenderConfigBuilder
+//
+// let config = RenderConfig::builder()
+//     .character_type(CharacterType::Simple)
+//     .num_cols(200)
+//     .color(true)
+//     .filename("test_ascii")
+//     .build()?;
+// config.image_to_image("test.jpg")?;
+//
+// with the `serde` feature it can also be loaded from a preset file, see `load_preset`
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct RenderConfig {
+    pub character_type: CharacterType,
+    pub setting_option: SettingOption,
+    pub output_directory: Option<String>,
+    pub filename: Option<String>,
+}
+
+impl RenderConfig {
+    pub fn builder() -> RenderConfigBuilder {
+        return RenderConfigBuilder::new();
+    }
+
+    // check every option, this was done by `RenderConfigBuilder::build` and when loading a preset
+    pub fn validate(&self) -> Result<(), String> {
+        self.setting_option.validate()?;
+        if let Some(filename) = &self.filename {
+            if filename.is_empty() {
+                return Err("filename must not be empty".to_string());
+            }
+        }
+        if self.setting_option.is_transparent_bg && !self.setting_option.is_color {
+            return Err("transparent background is only supported for rgb output".to_string());
+        }
+        return Ok(());
+    }
+
+    // Converts an image to ASCII art image.
+    pub fn image_to_image(&self, path: &str) -> Result<String, String> {
+        return image_to_image(
+            path,
+            self.character_type,
+            self.output_directory.as_deref(),
+            self.filename.as_deref(),
+            self.setting_option.clone(),
+        );
+    }
+
+    // Converts an image to ASCII art text, only the simple and complex character type are supported for text
+    pub fn image_to_text(&self, path: &str) -> Result<String, String> {
+        let complex = match self.character_type {
+            CharacterType::Simple => false,
+            CharacterType::Complex => true,
+            _ => {
+                return Err(format!(
+                    "{:?} is not supported for text, use Simple or Complex",
+                    self.character_type
+                ))
+            }
+        };
+        let preprocess = self.setting_option.preprocess;
+        let img = open_image(path, preprocess.is_auto_orient)
+            .map_err(|e| format!("Failed to open image: {}", e))?;
+        preprocess.get_output_size(img.width(), img.height())?;
+        let img = apply_preprocess(&img, preprocess);
+        let img = apply_tone(&img, self.setting_option.tone);
+
+        return dynamic_image_to_text(
+            &img,
+            self.setting_option.num_cols,
+            complex,
+            self.output_directory.as_deref(),
+            self.filename.as_deref(),
+        );
+    }
+
+    // Converts a text file (optionally with ANSI colors) back to an image.
+    pub fn text_to_image(&self, path: &str) -> Result<String, String> {
+        return text_to_image(
+            path,
+            self.character_type,
+            self.output_directory.as_deref(),
+            self.filename.as_deref(),
+            self.setting_option.clone(),
+        );
+    }
+
+    // Converts an ANSI art file (.ans) to an image.
+    pub fn ansi_art_to_image(&self, path: &str) -> Result<String, String> {
+        return ansi_art_to_image(
+            path,
+            self.character_type,
+            self.output_directory.as_deref(),
+            self.filename.as_deref(),
+            self.setting_option.clone(),
+        );
+    }
+
+    // Converts an ANSI art file (.ans) to a html page.
+    pub fn ansi_art_to_html(&self, path: &str) -> Result<String, String> {
+        return ansi_art_to_html(
+            path,
+            self.character_type,
+            self.output_directory.as_deref(),
+            self.filename.as_deref(),
+            self.setting_option.clone(),
+        );
+    }
+
+    // Converts a gif to ASCII art gif.
+    pub fn gif_to_gif(&self, path: &str) -> Result<String, String> {
+        return gif_to_gif(
+            path,
+            self.character_type,
+            self.output_directory.as_deref(),
+            self.filename.as_deref(),
+            self.setting_option.clone(),
+        );
+    }
+
+    // Converts a gif to ASCII art gif, with the progress reported and a cancellation token that can stop it.
+    pub fn gif_to_gif_with_progress(
+        &self,
+        path: &str,
+        progress_option: ProgressOption,
+    ) -> Result<String, String> {
+        return gif_to_gif_with_progress(
+            path,
+            self.character_type,
+            self.output_directory.as_deref(),
+            self.filename.as_deref(),
+            self.setting_option.clone(),
+            progress_option,
+        );
+    }
+
+    // Converts a Y4M video file to ASCII art gif.
+    pub fn y4m_to_gif(&self, path: &str) -> Result<String, String> {
+        return y4m_to_gif(
+            path,
+            self.character_type,
+            self.output_directory.as_deref(),
+            self.filename.as_deref(),
+            self.setting_option.clone(),
+        );
+    }
+
+    // Converts an ordered list of images to ASCII art gif, playing `frame_rate` images per second.
+    pub fn image_sequence_to_gif(
+        &self,
+        path_list: &[String],
+        frame_rate: f32,
+    ) -> Result<String, String> {
+        return image_sequence_to_gif(
+            path_list,
+            self.character_type,
+            self.output_directory.as_deref(),
+            self.filename.as_deref(),
+            self.setting_option.clone(),
+            frame_rate,
+        );
+    }
+
+    // Converts an ordered list of images to a sequence of ASCII art images.
+    pub fn image_sequence_to_images(&self, path_list: &[String]) -> Result<String, String> {
+        return image_sequence_to_images(
+            path_list,
+            self.character_type,
+            self.output_directory.as_deref(),
+            self.filename.as_deref(),
+            self.setting_option.clone(),
+        );
+    }
+
+    // Converts every image and GIF of a directory into the output directory, the filename is not used.
+    pub fn batch_convert(
+        &self,
+        input_directory: &str,
+        batch_option: &BatchOption,
+    ) -> Result<BatchReport, String> {
+        let output_directory = match &self.output_directory {
+            Some(output_directory) => output_directory,
+            None => return Err("No output directory provided".to_string()),
+        };
+        return batch_convert(
+            input_directory,
+            output_directory,
+            self.character_type,
+            self.setting_option.clone(),
+            batch_option,
+        );
+    }
+}
+
+// build a RenderConfig step by step, every option not set keep the default of SettingOption::grayscale
+#[derive(Clone, Debug)]
+pub struct RenderConfigBuilder {
+    character_type: CharacterType,
+    setting_option: SettingOption,
+    output_directory: Option<String>,
+    filename: Option<String>,
+}
+
+impl RenderConfigBuilder {
+    pub fn new() -> RenderConfigBuilder {
+        return RenderConfigBuilder {
+            character_type: CharacterType::Simple,
+            setting_option: SettingOption::grayscale(DEFAULT_NUM_COLS),
+            output_directory: None,
+            filename: None,
+        };
+    }
+
+    // start from an existing setting instead of the default
+    pub fn from_setting_option(
+        character_type: CharacterType,
+        setting_option: SettingOption,
+    ) -> RenderConfigBuilder {
+        return RenderConfigBuilder {
+            character_type,
+            setting_option,
+            ..RenderConfigBuilder::new()
+        };
+    }
+
+    // character set
+    pub fn character_type(mut self, character_type: CharacterType) -> RenderConfigBuilder {
+        self.character_type = character_type;
+        return self;
+    }
+
+    // sizing
+    pub fn num_cols(mut self, num_cols: u32) -> RenderConfigBuilder {
+        self.setting_option.num_cols = num_cols;
+        return self;
+    }
+
+    // sampling
+    pub fn alpha_threshold(
+        mut self,
+        alpha_threshold: u8,
+        alpha_mode: AlphaMode,
+    ) -> RenderConfigBuilder {
+        self.setting_option.alpha_threshold = alpha_threshold;
+        self.setting_option.alpha_mode = alpha_mode;
+        return self;
+    }
+
+    pub fn frame_selection(mut self, frame_selection: FrameSelection) -> RenderConfigBuilder {
+        self.setting_option.frame_selection = frame_selection;
+        return self;
+    }
+
+    pub fn frame_window(mut self, frame_window: usize) -> RenderConfigBuilder {
+        self.setting_option.frame_window = frame_window;
+        return self;
+    }
+
+    // color
+    pub fn color(mut self, is_color: bool) -> RenderConfigBuilder {
+        self.setting_option.is_color = is_color;
+        return self;
+    }
+
+    pub fn color_theme(mut self, color_theme: ColorTheme) -> RenderConfigBuilder {
+        self.setting_option.color_theme = Some(color_theme);
+        return self;
+    }
+
+    pub fn palette(mut self, palette: Palette) -> RenderConfigBuilder {
+        self.setting_option.palette = Some(Arc::new(palette));
+        return self;
+    }
+
+    pub fn cell_background(mut self, cell_background: CellBackground) -> RenderConfigBuilder {
+        self.setting_option.cell_background = Some(cell_background);
+        return self;
+    }
+
+    // background
+    pub fn white_bg(mut self, is_white_bg: bool) -> RenderConfigBuilder {
+        self.setting_option.is_white_bg = is_white_bg;
+        return self;
+    }
+
+    pub fn transparent_bg(mut self, is_transparent_bg: bool) -> RenderConfigBuilder {
+        self.setting_option.is_transparent_bg = is_transparent_bg;
+        return self;
+    }
+
+    // tone
+    pub fn tone(mut self, tone: Tone) -> RenderConfigBuilder {
+        self.setting_option.tone = tone;
+        return self;
+    }
+
+    pub fn brightness(mut self, brightness: f32) -> RenderConfigBuilder {
+        self.setting_option.tone.brightness = brightness;
+        return self;
+    }
+
+    pub fn contrast(mut self, contrast: f32) -> RenderConfigBuilder {
+        self.setting_option.tone.contrast = contrast;
+        return self;
+    }
+
+    pub fn gamma(mut self, gamma: f32) -> RenderConfigBuilder {
+        self.setting_option.tone.gamma = gamma;
+        return self;
+    }
+
+    pub fn invert(mut self, invert: bool) -> RenderConfigBuilder {
+        self.setting_option.tone.invert = invert;
+        return self;
+    }
+
+    // preprocess
+    pub fn preprocess(mut self, preprocess: Preprocess) -> RenderConfigBuilder {
+        self.setting_option.preprocess = preprocess;
+        return self;
+    }
+
+    pub fn auto_orient(mut self, is_auto_orient: bool) -> RenderConfigBuilder {
+        self.setting_option.preprocess.is_auto_orient = is_auto_orient;
+        return self;
+    }
+
+    pub fn crop(mut self, crop: CropRect) -> RenderConfigBuilder {
+        self.setting_option.preprocess.crop = Some(crop);
+        return self;
+    }
+
+    pub fn rotation(mut self, rotation: Rotation) -> RenderConfigBuilder {
+        self.setting_option.preprocess.rotation = rotation;
+        return self;
+    }
+
+    pub fn flip_horizontal(mut self, is_flip_horizontal: bool) -> RenderConfigBuilder {
+        self.setting_option.preprocess.is_flip_horizontal = is_flip_horizontal;
+        return self;
+    }
+
+    pub fn flip_vertical(mut self, is_flip_vertical: bool) -> RenderConfigBuilder {
+        self.setting_option.preprocess.is_flip_vertical = is_flip_vertical;
+        return self;
+    }
+
+    pub fn scale(mut self, scale: PreScale) -> RenderConfigBuilder {
+        self.setting_option.preprocess.scale = Some(scale);
+        return self;
+    }
+
+    pub fn resize_filter(mut self, resize_filter: ResizeFilter) -> RenderConfigBuilder {
+        self.setting_option.preprocess.resize_filter = resize_filter;
+        return self;
+    }
+
+    pub fn padding(mut self, padding: Padding) -> RenderConfigBuilder {
+        self.setting_option.preprocess.padding = Some(padding);
+        return self;
+    }
+
+    // output
+    pub fn output_directory(mut self, output_directory: &str) -> RenderConfigBuilder {
+        self.output_directory = Some(output_directory.to_string());
+        return self;
+    }
+
+    // name of the output file, the extension was not required
+    pub fn filename(mut self, filename: &str) -> RenderConfigBuilder {
+        self.filename = Some(filename.to_string());
+        return self;
+    }
+
+    // validate every option and build the RenderConfig
+    pub fn build(self) -> Result<RenderConfig, String> {
+        let render_config = RenderConfig {
+            character_type: self.character_type,
+            setting_option: self.setting_option,
+            output_directory: self.output_directory,
+            filename: self.filename,
+        };
+        render_config.validate()?;
+
+        return Ok(render_config);
+    }
+}
+
+impl Default for RenderConfigBuilder {
+    fn default() -> RenderConfigBuilder {
+        return RenderConfigBuilder::new();
+    }
+}
+
+impl Default for RenderConfig {
+    fn default() -> RenderConfig {
+        let builder = RenderConfigBuilder::new();
+        return RenderConfig {
+            character_type: builder.character_type,
+            setting_option: builder.setting_option,
+            output_directory: builder.output_directory,
+            filename: builder.filename,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_with_default() {
+        let config = RenderConfig::builder().build().unwrap();
+        assert_eq!(config.character_type, CharacterType::Simple);
+        assert_eq!(config.setting_option.num_cols, DEFAULT_NUM_COLS);
+        assert!(!config.setting_option.is_color);
+        assert_eq!(config.filename, None);
+    }
+
+    #[test]
+    fn build_with_option() {
+        let config = RenderConfig::builder()
+            .character_type(CharacterType::Complex)
+            .num_cols(200)
+            .color(true)
+            .transparent_bg(true)
+            .brightness(0.2)
+            .output_directory("ascii")
+            .filename("test_ascii")
+            .build()
+            .unwrap();
+        assert_eq!(config.character_type, CharacterType::Complex);
+        assert_eq!(config.setting_option.num_cols, 200);
+        assert!(config.setting_option.is_transparent_bg);
+        assert_eq!(config.setting_option.tone.brightness, 0.2);
+        assert_eq!(config.output_directory.as_deref(), Some("ascii"));
+        assert_eq!(config.filename.as_deref(), Some("test_ascii"));
+    }
+
+    #[test]
+    fn build_with_invalid_option() {
+        let error = RenderConfig::builder().num_cols(0).build().unwrap_err();
+        assert_eq!(error, "num_cols must be greater than 0");
+
+        assert!(RenderConfig::builder().frame_window(0).build().is_err());
+        assert!(RenderConfig::builder().gamma(0.0).build().is_err());
+        assert!(RenderConfig::builder().brightness(1.5).build().is_err());
+        assert!(RenderConfig::builder().filename("").build().is_err());
+        assert!(RenderConfig::builder()
+            .frame_selection(FrameSelection::range(4, 2))
+            .build()
+            .is_err());
+        // a transparent background need rgb output
+        assert!(RenderConfig::builder()
+            .transparent_bg(true)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn build_from_setting_option() {
+        let config = RenderConfigBuilder::from_setting_option(
+            CharacterType::Bar,
+            SettingOption::rgb(50).with_frame_window(4),
+        )
+        .build()
+        .unwrap();
+        assert_eq!(config.character_type, CharacterType::Bar);
+        assert_eq!(config.setting_option.num_cols, 50);
+        assert_eq!(config.setting_option.frame_window, 4);
+    }
+
+    #[test]
+    fn image_to_text_only_support_simple_and_complex() {
+        let config = RenderConfig::builder()
+            .character_type(CharacterType::Bar)
+            .build()
+            .unwrap();
+        assert!(config.image_to_text("test.jpg").is_err());
+    }
+}
@@ -0,0 +1,527 @@
+use std::{
+    collections::HashMap,
+    fs::{read, write},
+};
+
+use crate::{
+    character::CharacterType,
+    text_to_img::text_to_ascii_img,
+    utils::utils::{get_output_path, get_saved_message},
+    SettingOption,
+};
+
+// the characters every FIGlet font has, after the printable ASCII characters: Ä Ö Ü ä ö ü ß
+const DEUTSCH_CHARACTER_LIST: [char; 7] = ['Ä', 'Ö', 'Ü', 'ä', 'ö', 'ü', 'ß'];
+// the tallest FIGlet font that can be loaded, the usual fonts are less than 20 lines high
+pub const MAX_FIGLET_FONT_HEIGHT: usize = 256;
+
+// the smushing rules of the FIGlet layout, see http://www.jave.de/figlet/figfont.html
+pub const SMUSH_EQUAL: u8 = 1; // two equal characters become one
+pub const SMUSH_UNDERSCORE: u8 = 2; // `_` is replaced by one of `|/\[]{}()<>`
+pub const SMUSH_HIERARCHY: u8 = 4; // the later class of `|`, `/\`, `[]`, `{}`, `()`, `<>` wins
+pub const SMUSH_OPPOSITE_PAIR: u8 = 8; // `[]`, `{}` and `()` in either order become `|`
+pub const SMUSH_BIG_X: u8 = 16; // `/\` become `|`, `\/` become `Y` and `><` become `X`
+pub const SMUSH_HARDBLANK: u8 = 32; // two hardblanks become one
+
+// how the characters of a FIGlet font are put next to each other
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum FigletLayout {
+    FullWidth,    // every character keep its full width
+    Kerning,      // the characters are moved together until they touch
+    Smushing(u8), // the characters overlap by one column where the rules allow it, no rule is the universal smushing
+}
+
+impl FigletLayout {
+    // the layout from the old_layout and full_layout parameters of the header
+    fn from_header(old_layout: i32, full_layout: Option<i32>) -> FigletLayout {
+        let full_layout = match full_layout {
+            Some(full_layout) => full_layout,
+            None if old_layout < 0 => 0,
+            None if old_layout == 0 => 64,
+            None => 128 | (old_layout & 63),
+        };
+        if full_layout & 128 != 0 {
+            return FigletLayout::Smushing((full_layout & 63) as u8);
+        }
+        if full_layout & 64 != 0 {
+            return FigletLayout::Kerning;
+        }
+        return FigletLayout::FullWidth;
+    }
+}
+
+// a FIGlet font (.flf), the characters are drawn with the lines of sub-characters of the font
+#[derive(PartialEq, Debug, Clone)]
+pub struct FigletFont {
+    // the sub-character drawn as a space that is never smushed
+    pub hardblank: char,
+    pub height: usize,
+    pub baseline: usize,
+    pub max_length: usize,
+    pub layout: FigletLayout,
+    pub is_right_to_left: bool,
+    pub comment: String,
+    character_map: HashMap<char, Vec<Vec<char>>>,
+}
+
+impl FigletFont {
+    pub fn with_layout(mut self, layout: FigletLayout) -> FigletFont {
+        self.layout = layout;
+        return self;
+    }
+
+    // the lines of a character, `None` if the font doesn't have it
+    pub fn get_character(&self, character: char) -> Option<&Vec<Vec<char>>> {
+        return self.character_map.get(&character);
+    }
+
+    // draw a text with the font, every line of the text is drawn below the previous one
+    //
+    // a character that the font doesn't have is drawn as the character 0 of the font if it has one,
+    // otherwise it is left out
+    pub fn render(&self, text: &str) -> String {
+        let mut figlet_text = String::new();
+        for line in text.lines() {
+            for row in self.render_line(line) {
+                let row: String = row
+                    .iter()
+                    .map(|sub_character| {
+                        if *sub_character == self.hardblank {
+                            ' '
+                        } else {
+                            *sub_character
+                        }
+                    })
+                    .collect();
+                figlet_text.push_str(row.trim_end());
+                figlet_text.push('\n');
+            }
+        }
+        return figlet_text;
+    }
+
+    fn render_line(&self, line: &str) -> Vec<Vec<char>> {
+        let mut character_list: Vec<&Vec<Vec<char>>> = line
+            .chars()
+            .filter_map(|character| {
+                self.get_character(character)
+                    .or_else(|| self.get_character('\0'))
+            })
+            .collect();
+        // a right to left font is laid out from the last character
+        if self.is_right_to_left {
+            character_list.reverse();
+        }
+
+        let mut row_list: Vec<Vec<char>> = vec![vec![]; self.height];
+        let mut previous_width = 0;
+        for character in character_list {
+            let width = character.iter().map(|row| row.len()).max().unwrap_or(0);
+            let smush_amount = self.get_smush_amount(&row_list, character, previous_width, width);
+            // the rows are always the same length, the first columns of the character can fall before the rows
+            // if they are blank
+            let row_length = row_list[0].len();
+            for (row, character_row) in row_list.iter_mut().zip(character.iter()) {
+                for (k, sub_character) in character_row.iter().enumerate() {
+                    let column = match (row_length + k).checked_sub(smush_amount) {
+                        Some(column) => column,
+                        None => continue,
+                    };
+                    if column < row.len() {
+                        row[column] = self
+                            .smush(row[column], *sub_character, previous_width, width)
+                            .unwrap_or(*sub_character);
+                    } else {
+                        row.push(*sub_character);
+                    }
+                }
+                row.resize(row_length + width - smush_amount, ' ');
+            }
+            previous_width = width;
+        }
+        return row_list;
+    }
+
+    // the number of columns the character can overlap the end of the rows
+    fn get_smush_amount(
+        &self,
+        row_list: &[Vec<char>],
+        character: &[Vec<char>],
+        previous_width: usize,
+        width: usize,
+    ) -> usize {
+        if self.layout == FigletLayout::FullWidth {
+            return 0;
+        }
+
+        let mut smush_amount = width;
+        for (row, character_row) in row_list.iter().zip(character.iter()) {
+            // the last sub-character of the row and the first of the character that are not a space
+            let row_end = row.iter().rposition(|sub_character| *sub_character != ' ');
+            let character_start = character_row
+                .iter()
+                .position(|sub_character| *sub_character != ' ')
+                .unwrap_or(character_row.len());
+
+            let mut amount = match row_end {
+                Some(row_end) => {
+                    let amount = character_start + row.len() - 1 - row_end;
+                    if character_start < character_row.len()
+                        && self
+                            .smush(
+                                row[row_end],
+                                character_row[character_start],
+                                previous_width,
+                                width,
+                            )
+                            .is_some()
+                    {
+                        amount + 1
+                    } else {
+                        amount
+                    }
+                }
+                None => character_start + row.len(),
+            };
+            amount = amount.min(width);
+            smush_amount = smush_amount.min(amount);
+        }
+        return smush_amount;
+    }
+
+    // the sub-character two overlapping sub-characters become, `None` if they can't be smushed
+    fn smush(&self, left: char, right: char, previous_width: usize, width: usize) -> Option<char> {
+        if left == ' ' {
+            return Some(right);
+        }
+        if right == ' ' {
+            return Some(left);
+        }
+        // a character that is 1 column wide is never smushed
+        if previous_width < 2 || width < 2 {
+            return None;
+        }
+        let rule_list = match self.layout {
+            FigletLayout::Smushing(rule_list) => rule_list,
+            _ => return None,
+        };
+
+        if rule_list == 0 {
+            // universal smushing, the later character wins over the earlier one
+            if left == self.hardblank {
+                return Some(right);
+            }
+            if right == self.hardblank {
+                return Some(left);
+            }
+            if self.is_right_to_left {
+                return Some(left);
+            }
+            return Some(right);
+        }
+
+        if rule_list & SMUSH_HARDBLANK != 0 && left == self.hardblank && right == self.hardblank {
+            return Some(left);
+        }
+        if left == self.hardblank || right == self.hardblank {
+            return None;
+        }
+        if rule_list & SMUSH_EQUAL != 0 && left == right {
+            return Some(left);
+        }
+        if rule_list & SMUSH_UNDERSCORE != 0 {
+            let border_list = "|/\\[]{}()<>";
+            if left == '_' && border_list.contains(right) {
+                return Some(right);
+            }
+            if right == '_' && border_list.contains(left) {
+                return Some(left);
+            }
+        }
+        if rule_list & SMUSH_HIERARCHY != 0 {
+            let class_list = ["|", "/\\", "[]", "{}", "()", "<>"];
+            let left_class = class_list.iter().position(|class| class.contains(left));
+            let right_class = class_list.iter().position(|class| class.contains(right));
+            if let (Some(left_class), Some(right_class)) = (left_class, right_class) {
+                if left_class < right_class {
+                    return Some(right);
+                }
+                if right_class < left_class {
+                    return Some(left);
+                }
+            }
+        }
+        if rule_list & SMUSH_OPPOSITE_PAIR != 0 {
+            match (left, right) {
+                ('[', ']') | (']', '[') | ('{', '}') | ('}', '{') | ('(', ')') | (')', '(') => {
+                    return Some('|')
+                }
+                _ => {}
+            }
+        }
+        if rule_list & SMUSH_BIG_X != 0 {
+            match (left, right) {
+                ('/', '\\') => return Some('|'),
+                ('\\', '/') => return Some('Y'),
+                ('>', '<') => return Some('X'),
+                _ => {}
+            }
+        }
+        return None;
+    }
+}
+
+pub fn load_figlet_font(path: &str) -> Result<FigletFont, String> {
+    let byte_list = read(path).map_err(|e| format!("Failed to read FIGlet font: {}", e))?;
+    // the fonts are usually ASCII, the older ones can have latin-1 characters
+    let content = match String::from_utf8(byte_list) {
+        Ok(content) => content,
+        Err(e) => e.into_bytes().iter().map(|byte| *byte as char).collect(),
+    };
+    return parse_figlet_font(&content);
+}
+
+// parse the content of a FIGlet font file (.flf)
+pub fn parse_figlet_font(content: &str) -> Result<FigletFont, String> {
+    let mut line_iter = content.lines();
+    let header = line_iter.next().ok_or("The FIGlet font is empty")?;
+    let signature = header
+        .strip_prefix("flf2a")
+        .ok_or("Not a FIGlet font, the header doesn't start with flf2a")?;
+
+    let mut signature_char_iter = signature.chars();
+    let hardblank = signature_char_iter
+        .next()
+        .ok_or("The FIGlet header has no hardblank")?;
+    let param_list: Vec<i32> = signature_char_iter
+        .as_str()
+        .split_whitespace()
+        .map(|param| {
+            param
+                .parse()
+                .map_err(|_| format!("Invalid FIGlet header parameter: {}", param))
+        })
+        .collect::<Result<Vec<i32>, String>>()?;
+    if param_list.len() < 5 {
+        return Err("The FIGlet header need at least 5 parameters".to_string());
+    }
+    if param_list[0] < 1 || param_list[0] as usize > MAX_FIGLET_FONT_HEIGHT {
+        return Err(format!(
+            "The FIGlet font height must be between 1 and {}",
+            MAX_FIGLET_FONT_HEIGHT
+        ));
+    }
+    let height = param_list[0] as usize;
+    let num_comment_lines = param_list[4].max(0) as usize;
+
+    let comment = line_iter
+        .by_ref()
+        .take(num_comment_lines)
+        .collect::<Vec<&str>>()
+        .join("\n");
+
+    let mut character_map: HashMap<char, Vec<Vec<char>>> = HashMap::new();
+    let required_character_list = (' '..='~').chain(DEUTSCH_CHARACTER_LIST);
+    for character in required_character_list {
+        // a few fonts leave out the last required characters
+        match read_figlet_character(&mut line_iter, height)? {
+            Some(lines) => character_map.insert(character, lines),
+            None => break,
+        };
+    }
+
+    // the code tagged characters, a line with the character code followed by the lines of the character
+    while let Some(code_line) = line_iter.next() {
+        let code_text = match code_line.split_whitespace().next() {
+            Some(code_text) => code_text,
+            None => continue,
+        };
+        let code = parse_character_code(code_text)
+            .ok_or(format!("Invalid FIGlet character code: {}", code_text))?;
+        let lines = read_figlet_character(&mut line_iter, height)?
+            .ok_or(format!("The FIGlet character {} has no lines", code_text))?;
+        // the negative codes are not characters, they are only used by the font
+        if let Some(character) = u32::try_from(code).ok().and_then(char::from_u32) {
+            character_map.insert(character, lines);
+        }
+    }
+    if character_map.is_empty() {
+        return Err("The FIGlet font has no characters".to_string());
+    }
+
+    return Ok(FigletFont {
+        hardblank,
+        height,
+        baseline: param_list[1].max(0) as usize,
+        max_length: param_list[2].max(0) as usize,
+        layout: FigletLayout::from_header(param_list[3], param_list.get(6).copied()),
+        is_right_to_left: param_list.get(5) == Some(&1),
+        comment,
+        character_map,
+    });
+}
+
+// Draw a text with a FIGlet font and save it as txt if a filename is given, the FIGlet text is returned.
+pub fn figlet_to_text(
+    text: &str,
+    figlet_font: &FigletFont,
+    output_directory: Option<&str>,
+    filename: Option<&str>,
+) -> Result<String, String> {
+    let figlet_text = figlet_font.render(text);
+    if filename.is_some() {
+        let path = get_output_path(output_directory, filename, "txt")?;
+        write(&path, &figlet_text).map_err(|e| format!("Failed to save {}: {}", path, e))?;
+    }
+    return Ok(figlet_text);
+}
+
+// Draw a text with a FIGlet font and save it as png, drawn the same way as `text_to_image`.
+pub fn figlet_to_image(
+    text: &str,
+    figlet_font: &FigletFont,
+    character_type: CharacterType,
+    output_directory: Option<&str>,
+    filename: Option<&str>,
+    setting_option: SettingOption,
+) -> Result<String, String> {
+    let ascii_img = text_to_ascii_img(&figlet_font.render(text), character_type, setting_option);
+
+    let path = get_output_path(output_directory, filename, "png")?;
+    ascii_img
+        .save(&path)
+        .map_err(|e| format!("Failed to save {}: {}", path, e))?;
+
+    return Ok(get_saved_message(
+        "Image",
+        output_directory,
+        filename,
+        "png",
+    ));
+}
+
+// read the `height` lines of a character, `None` if the font ended before the character
+//
+// every line ends with an endmark (usually `@`), the last line of the character with two of them
+fn read_figlet_character<'a, I: Iterator<Item = &'a str>>(
+    line_iter: &mut I,
+    height: usize,
+) -> Result<Option<Vec<Vec<char>>>, String> {
+    let mut lines: Vec<Vec<char>> = vec![];
+    for i in 0..height {
+        let line = match line_iter.next() {
+            Some(line) => line.trim_end(),
+            None if i == 0 => return Ok(None),
+            None => return Err("The FIGlet font ended in the middle of a character".to_string()),
+        };
+        let line = match line.chars().last() {
+            Some(endmark) => line.trim_end_matches(endmark),
+            None => line,
+        };
+        lines.push(line.chars().collect());
+    }
+
+    // the lines of a character should be the same width, pad the shorter ones
+    let width = lines.iter().map(|line| line.len()).max().unwrap_or(0);
+    for line in lines.iter_mut() {
+        line.resize(width, ' ');
+    }
+    return Ok(Some(lines));
+}
+
+// a character code can be decimal, hexadecimal (`0x`) or octal (leading `0`), and negative
+fn parse_character_code(code_text: &str) -> Option<i64> {
+    let (sign, code_text) = match code_text.strip_prefix('-') {
+        Some(code_text) => (-1, code_text),
+        None => (1, code_text),
+    };
+    let code = if let Some(hex) = code_text
+        .strip_prefix("0x")
+        .or_else(|| code_text.strip_prefix("0X"))
+    {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if code_text.len() > 1 && code_text.starts_with('0') {
+        i64::from_str_radix(&code_text[1..], 8).ok()?
+    } else {
+        code_text.parse().ok()?
+    };
+    return Some(sign * code);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a font 1 line high where every required character is empty, and `A` and `B` are drawn with `left` and `right`
+    fn get_test_font(full_layout: i32, left: &str, right: &str) -> FigletFont {
+        let mut content = format!("flf2a$ 1 1 10 0 1 0 {}\ntest font\n", full_layout);
+        for _ in (' '..='~').chain(DEUTSCH_CHARACTER_LIST) {
+            content.push_str("@@\n");
+        }
+        content.push_str(&format!("65 LATIN CAPITAL LETTER A\n{}@@\n", left));
+        content.push_str(&format!("0x42 LATIN CAPITAL LETTER B\n{}@@\n", right));
+        return parse_figlet_font(&content).unwrap();
+    }
+
+    #[test]
+    fn parse_header() {
+        let font = get_test_font(128 | SMUSH_EQUAL as i32, "a", "b");
+        assert_eq!(font.hardblank, '$');
+        assert_eq!(font.height, 1);
+        assert_eq!(font.comment, "test font");
+        assert_eq!(font.layout, FigletLayout::Smushing(SMUSH_EQUAL));
+        assert_eq!(font.get_character('A'), Some(&vec![vec!['a']]));
+        assert_eq!(font.get_character('B'), Some(&vec![vec!['b']]));
+    }
+
+    #[test]
+    fn render_full_width_and_kerning() {
+        assert_eq!(get_test_font(0, "a ", " b").render("AB"), "a  b\n");
+        assert_eq!(get_test_font(64, "a ", " b").render("AB"), "ab\n");
+    }
+
+    #[test]
+    fn render_smushing_rule() {
+        let case_list = [
+            (SMUSH_EQUAL, "_|", "|_", "_|_\n"),
+            (SMUSH_UNDERSCORE, "a_", "/b", "a/b\n"),
+            (SMUSH_HIERARCHY, "a|", "/b", "a/b\n"),
+            (SMUSH_OPPOSITE_PAIR, "a[", "]b", "a|b\n"),
+            (SMUSH_BIG_X, "a/", "\\b", "a|b\n"),
+            (SMUSH_BIG_X, "a\\", "/b", "aYb\n"),
+            (SMUSH_BIG_X, "a>", "<b", "aXb\n"),
+            (SMUSH_HARDBLANK, "a$", "$b", "a b\n"),
+        ];
+        for (rule, left, right, expected) in case_list {
+            let font = get_test_font(128 | rule as i32, left, right);
+            assert_eq!(font.render("AB"), expected, "rule {}", rule);
+        }
+    }
+
+    #[test]
+    fn render_without_matching_rule() {
+        // the characters only touch when no rule smush them
+        assert_eq!(
+            get_test_font(128 | SMUSH_EQUAL as i32, "ab", "cd").render("AB"),
+            "abcd\n"
+        );
+        // the universal smushing let the later character win
+        assert_eq!(get_test_font(128, "ab", "cd").render("AB"), "acd\n");
+    }
+
+    #[test]
+    fn parse_invalid_font() {
+        assert!(parse_figlet_font("").is_err());
+        assert!(parse_figlet_font("flf2a$ 1 1 10 0\n").is_err());
+        assert!(parse_figlet_font("flf2a$ 0 0 10 0 0\n@@\n").is_err());
+        // a huge height is rejected before any line of a character is read
+        let content = format!("flf2a$ {} 1 10 0 0\n@@\n", MAX_FIGLET_FONT_HEIGHT + 1);
+        assert!(parse_figlet_font(&content).is_err());
+        // a header without any character
+        assert!(parse_figlet_font("flf2a$ 1 1 10 0 0\n").is_err());
+        assert!(parse_figlet_font("flf2a$ 1 1 10 0 1\ncomment\n").is_err());
+        // the font ended in the middle of a character
+        assert!(parse_figlet_font("flf2a$ 2 1 10 0 0\na@\n").is_err());
+        assert!(parse_figlet_font("flf2a$ 1 1 10 0 0\na@@\n").is_ok());
+    }
+}
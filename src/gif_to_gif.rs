@@ -1,22 +1,33 @@
 use std::{
-    fs::File,
-    io::{Cursor, Write},
+    borrow::Cow,
+    fs::{remove_file, File},
+    io::{BufReader, BufWriter, Cursor, Read, Write},
 };
 
-use gif::{ColorOutput, DecodeOptions, Decoder, Encoder, Frame, Repeat};
-use image::{DynamicImage, GrayImage, ImageBuffer, Luma, Rgba, RgbaImage};
-
-use rayon::prelude::*;
+use gif::{ColorOutput, DecodeOptions, Decoder, DisposalMethod, Encoder, Frame, Repeat};
+use image::{
+    codecs::gif::GifDecoder, AnimationDecoder, DynamicImage, GrayImage, ImageBuffer, Luma, Rgba,
+    RgbaImage,
+};
 
 use crate::{
-    grayscale_to_ascii_img, rgb_to_rgb_ascii_img,
-    utils::utils::{
-        check_and_create_directory, get_img_flatten_gray_and_color_map,
-        get_img_flatten_rgb_and_color_map,
+    dynamic_image_to_image, grayscale_to_ascii_img, rgb_to_rgb_ascii_img,
+    utils::{
+        frame_sampler::{FrameSampler, FrameSelector, GifDelayTimer, SampledFrame},
+        parallel::*,
+        progress_tracker::ProgressTracker,
+        utils::{
+            check_and_create_directory, get_gray_color_map,
+            get_img_flatten_gray_and_color_map_with_color_theme,
+        },
     },
-    CharacterType, SettingOption,
+    CharacterType, ColorTheme, FrameSelection, Palette, PaletteMatcher, ProgressOption,
+    SettingOption, DEFAULT_FRAME_WINDOW, MAX_PALETTE_SIZE,
 };
 
+// pixels with an alpha below this value become the transparent color of a gif frame
+const GIF_TRANSPARENT_ALPHA_THRESHOLD: u8 = 128;
+
 pub fn gif_to_gif(
     path: &str,
     character_type: CharacterType,
@@ -24,191 +35,720 @@ pub fn gif_to_gif(
     filename: Option<&str>,
     setting_option: SettingOption,
 ) -> Result<String, String> {
-    let file = File::open(path).expect("Failed to open GIF");
+    return gif_to_gif_with_progress(
+        path,
+        character_type,
+        output_directory,
+        filename,
+        setting_option,
+        ProgressOption::new(),
+    );
+}
+
+// same as `gif_to_gif`, with the progress reported to the observer and a cancellation token that can stop it
+// the partly written output file is removed if the conversion was cancelled or failed
+pub fn gif_to_gif_with_progress(
+    path: &str,
+    character_type: CharacterType,
+    output_directory: Option<&str>,
+    filename: Option<&str>,
+    setting_option: SettingOption,
+    mut progress_option: ProgressOption,
+) -> Result<String, String> {
+    setting_option.validate()?;
+    let file = File::open(path).map_err(|e| format!("Failed to open GIF: {}", e))?;
+    // the number of frames is only counted if someone is listening, as it takes an extra pass over the file
+    if progress_option.observer.is_some() && progress_option.total_frames.is_none() {
+        progress_option.total_frames = Some(count_selected_gif_frames(
+            path,
+            setting_option.frame_selection,
+        )?);
+    }
 
-    // init the decode option for gif
+    // init the decode option for gif, the header is read before the output file is created
+    // so an invalid GIF doesn't leave an empty file behind
     let options = DecodeOptions::new();
-    if setting_option.is_color {
-        let gif_buffer: Cursor<Vec<u8>> =
-            rgb_gif_to_ascii_rgb_gif(file, options, character_type, setting_option);
-        match check_and_create_directory(output_directory) {
-            Ok(_) => {
-                if let Some(filename) = filename {
-                    let path = if let Some(output_dir) = output_directory {
-                        format!("{}/{}.gif", output_dir, filename)
-                    } else {
-                        format!("{}.gif", filename)
-                    };
-                    let mut output_file = File::create(path).unwrap();
-                    let _ = output_file.write_all(&gif_buffer.into_inner()).unwrap();
-                    if output_directory.is_some() {
-                        return Ok(format!(
-                            "Gif saved to path: {} as {}.gif",
-                            output_directory.unwrap(),
-                            filename
-                        ));
-                    } else {
-                        return Ok(format!(
-                            "Gif saved to current directory as {}.gif",
-                            filename
-                        ));
-                    }
-                }
-                return Err("No filename provided".to_string());
-            }
-            Err(e) => {
-                return Err(e);
-            }
-        }
+    let decoder = decode_gif(file, options)?;
+    check_and_create_directory(output_directory)?;
+    let filename = match filename {
+        Some(filename) => filename,
+        None => return Err("No filename provided".to_string()),
+    };
+    let path = if let Some(output_dir) = output_directory {
+        format!("{}/{}.gif", output_dir, filename)
     } else {
-        let gif_buffer: Cursor<Vec<u8>> =
-            rgb_gif_to_ascii_grayscale_gif(file, options, character_type, setting_option);
-        match check_and_create_directory(output_directory) {
-            Ok(_) => {
-                if let Some(filename) = filename {
-                    let path = if let Some(output_dir) = output_directory {
-                        format!("{}/{}.gif", output_dir, filename)
-                    } else {
-                        format!("{}.gif", filename)
-                    };
-                    let mut output_file = File::create(path).unwrap();
-                    let _ = output_file.write_all(&gif_buffer.into_inner()).unwrap();
-                    if output_directory.is_some() {
-                        return Ok(format!(
-                            "Gif saved to path: {} as {}.gif",
-                            output_directory.unwrap(),
-                            filename
-                        ));
-                    } else {
-                        return Ok(format!(
-                            "Gif saved to current directory as {}.gif",
-                            filename
-                        ));
-                    }
-                }
-                return Err("No filename provided".to_string());
-            }
-            Err(e) => {
-                return Err(e);
-            }
+        format!("{}.gif", filename)
+    };
+    let output_file =
+        File::create(&path).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+
+    // the frames are encoded straight into the output file as they were converted,
+    // so the whole animation never needs to be held in memory
+    let result = if setting_option.is_color {
+        stream_frames_to_ascii_rgb_gif_with_progress(
+            decoder,
+            BufWriter::new(output_file),
+            character_type,
+            setting_option,
+            progress_option,
+        )
+    } else {
+        stream_frames_to_ascii_gray_gif_with_progress(
+            decoder,
+            BufWriter::new(output_file),
+            character_type,
+            setting_option,
+            progress_option,
+        )
+    };
+    let result = result.and_then(|writer| {
+        writer
+            .into_inner()
+            .map_err(|e| format!("Failed to write {}: {}", path, e))
+    });
+    if let Err(e) = result {
+        let _ = remove_file(&path);
+        return Err(e);
+    }
+
+    if let Some(output_dir) = output_directory {
+        return Ok(format!(
+            "Gif saved to path: {} as {}.gif",
+            output_dir, filename
+        ));
+    } else {
+        return Ok(format!(
+            "Gif saved to current directory as {}.gif",
+            filename
+        ));
+    }
+}
+
+// Converts a single frame of a gif to a still ASCII art image.
+pub fn gif_frame_to_image(
+    path: &str,
+    frame_index: usize,
+    character_type: CharacterType,
+    output_directory: Option<&str>,
+    filename: Option<&str>,
+    setting_option: SettingOption,
+) -> Result<String, String> {
+    let img = get_gif_frame_img(path, frame_index)?;
+
+    return dynamic_image_to_image(
+        &img,
+        character_type,
+        output_directory,
+        filename,
+        setting_option,
+    );
+}
+
+// get a single frame of the gif as a full image,
+// a frame that only update part of the canvas is drawn over the frames before it
+pub fn get_gif_frame_img(path: &str, frame_index: usize) -> Result<DynamicImage, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open GIF: {}", e))?;
+    let decoder = GifDecoder::new(BufReader::new(file))
+        .map_err(|e| format!("Failed to decode GIF: {}", e))?;
+
+    let frame = decoder
+        .into_frames()
+        .nth(frame_index)
+        .ok_or_else(|| format!("GIF does not have a frame at index {}", frame_index))?
+        .map_err(|e| format!("Failed to decode GIF frame: {}", e))?;
+
+    return Ok(DynamicImage::ImageRgba8(frame.into_buffer()));
+}
+
+// count the frames of a gif without decoding their pixels
+pub fn count_gif_frames(path: &str) -> Result<usize, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open GIF: {}", e))?;
+    let mut options = DecodeOptions::new();
+    options.skip_frame_decoding(true);
+    let mut decoder = options
+        .read_info(BufReader::new(file))
+        .map_err(|e| format!("Failed to decode GIF: {}", e))?;
+
+    let mut num_frames = 0;
+    while decoder
+        .read_next_frame()
+        .map_err(|e| format!("Failed to decode GIF frame: {}", e))?
+        .is_some()
+    {
+        num_frames += 1;
+    }
+
+    return Ok(num_frames);
+}
+
+// count the frames of the output of a gif with the frame selection, without decoding their pixels
+//
+// a frame is counted once for every delay it is kept for, the same as `GifProgress::converted_frames`
+pub fn count_selected_gif_frames(
+    path: &str,
+    frame_selection: FrameSelection,
+) -> Result<usize, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open GIF: {}", e))?;
+    let mut options = DecodeOptions::new();
+    options.skip_frame_decoding(true);
+    let mut decoder = options
+        .read_info(BufReader::new(file))
+        .map_err(|e| format!("Failed to decode GIF: {}", e))?;
+
+    let mut frame_selector = FrameSelector::new(frame_selection);
+    let mut num_frames = 0;
+    while !frame_selector.is_finished() {
+        let delay = match decoder
+            .read_next_frame()
+            .map_err(|e| format!("Failed to decode GIF frame: {}", e))?
+        {
+            Some(frame) => frame.delay,
+            None => break,
+        };
+        if let Some(((), delay_list)) = frame_selector.push(delay, || ()) {
+            num_frames += delay_list.len();
         }
     }
+    if let Some(((), delay_list)) = frame_selector.finish() {
+        num_frames += delay_list.len();
+    }
+
+    return Ok(num_frames);
 }
 
 // the function that bundle all the process that need to:
 // 1. decode gif to rgba frames
 // 2. read the frames and turn frames -> ImageRgba -> DynamicImage -> process image to turn to ascii art in DynamicImage
-// 3. i ) based on the list of DynamicImage get color map and flatten the rgba image to turn into paletted image
-//    ii) initialize an encoder, write the frames to a buffer and return the buffer
-pub fn rgb_gif_to_ascii_rgb_gif(
-    gif_file: File,
+// 3. quantize every ascii art frame and write it to the buffer through the encoder
+//
+// the frames are streamed through in windows of `setting_option.frame_window` frames
+//
+// panics if the gif can't be decoded or converted, or the setting option is invalid (see `SettingOption::validate()`),
+// use `decode_gif` with `stream_frames_to_ascii_rgb_gif` to get the error instead
+pub fn rgb_gif_to_ascii_rgb_gif<R: Read>(
+    gif_file: R,
     options: DecodeOptions,
     character_type: CharacterType,
     setting_option: SettingOption,
 ) -> Cursor<Vec<u8>> {
-    let decoder = decode_gif(gif_file, options, true);
+    let decoder = decode_gif(gif_file, options).expect("Failed to decode GIF");
 
-    let rgba_image_buffer_list =
-        process_frames_to_ascii_rgba_img(decoder, character_type, setting_option);
+    let mut gif_buffer = stream_frames_to_ascii_rgb_gif(
+        decoder,
+        Cursor::new(Vec::<u8>::new()),
+        character_type,
+        setting_option,
+    )
+    .expect("Failed to convert GIF");
+    gif_buffer.set_position(0);
 
-    return encode_images_to_ascii_rgb_gif(&rgba_image_buffer_list);
+    return gif_buffer;
 }
 
 // the function that bundle all the process that need to:
 // 1. decode gif to grayscale frames
 // 2. read the frames and turn frames -> GrayImage -> DynamicImage -> process image to turn to ascii art in DynamicImage
-// 3. i ) based on the list of DynamicImage get color map and flatten the grayscale image to turn into paletted image
-//    ii) initialize an encoder, write the frames to a buffer and return the buffer
-pub fn rgb_gif_to_ascii_grayscale_gif(
-    gif_file: File,
+// 3. write every ascii art frame as an indexed frame of the grayscale (or color theme) palette to the buffer through the encoder
+//
+// the frames are streamed through in windows of `setting_option.frame_window` frames
+//
+// panics if the gif can't be decoded or converted, or the setting option is invalid (see `SettingOption::validate()`),
+// use `decode_gif` with `stream_frames_to_ascii_gray_gif` to get the error instead
+pub fn rgb_gif_to_ascii_grayscale_gif<R: Read>(
+    gif_file: R,
     options: DecodeOptions,
     character_type: CharacterType,
     setting_option: SettingOption,
 ) -> Cursor<Vec<u8>> {
-    let decoder = decode_gif(gif_file, options, false);
+    let decoder = decode_gif(gif_file, options).expect("Failed to decode GIF");
 
-    let luma_image_buffer_list =
-        process_frames_to_ascii_grayscale_img(decoder, character_type, setting_option);
+    let mut gif_buffer = stream_frames_to_ascii_gray_gif(
+        decoder,
+        Cursor::new(Vec::<u8>::new()),
+        character_type,
+        setting_option,
+    )
+    .expect("Failed to convert GIF");
+    gif_buffer.set_position(0);
 
-    return encode_images_to_ascii_gray_gif(&luma_image_buffer_list);
+    return gif_buffer;
 }
 
-// decode the gif to frames and be process later
-pub fn decode_gif(gif_file: File, mut options: DecodeOptions, is_color: bool) -> Decoder<File> {
-    // set the decode option to be decoding as RGBA or Indexed
-    if is_color {
-        options.set_color_output(ColorOutput::RGBA);
-    } else {
-        options.set_color_output(ColorOutput::Indexed);
-    }
+// decode the gif to frames and be process later, an error if the header of the gif is invalid
+//
+// the frames are decoded as RGBA for both rgb and grayscale ascii art, as they are drawn on the logical screen
+pub fn decode_gif<R: Read>(gif_file: R, mut options: DecodeOptions) -> Result<Decoder<R>, String> {
+    options.set_color_output(ColorOutput::RGBA);
     //  decode the file with the set options
-    let decoder = options.read_info(gif_file).unwrap();
-
-    return decoder;
+    return options
+        .read_info(gif_file)
+        .map_err(|e| format!("Failed to decode GIF: {}", e));
 }
 
 // encode the rgba ascii art images back to rgb frames and return the buffer
 pub fn encode_images_to_ascii_rgb_gif(
-    rgba_image_buffer_list: &Vec<ImageBuffer<Rgba<u8>, Vec<u8>>>,
+    rgba_image_buffer_list: &[ImageBuffer<Rgba<u8>, Vec<u8>>],
 ) -> Cursor<Vec<u8>> {
+    return encode_images_to_ascii_rgb_gif_with_palette(rgba_image_buffer_list, None);
+}
+
+// same as `encode_images_to_ascii_rgb_gif`, the colors of the frames are restricted to the palette if there is one
+//
+// panics if an image is larger than a GIF frame (see `check_gif_frame_size()`),
+// use `encode_images_to_ascii_rgb_gif_with_progress` to get the error instead
+pub fn encode_images_to_ascii_rgb_gif_with_palette(
+    rgba_image_buffer_list: &[ImageBuffer<Rgba<u8>, Vec<u8>>],
+    palette: Option<&Palette>,
+) -> Cursor<Vec<u8>> {
+    return encode_images_to_ascii_rgb_gif_with_progress(
+        rgba_image_buffer_list,
+        palette,
+        ProgressOption::new(),
+    )
+    .expect("Failed to encode GIF");
+}
+
+// same as `encode_images_to_ascii_rgb_gif_with_palette`, with the encoded frames reported and a cancellation token checked for every frame
+pub fn encode_images_to_ascii_rgb_gif_with_progress(
+    rgba_image_buffer_list: &[ImageBuffer<Rgba<u8>, Vec<u8>>],
+    palette: Option<&Palette>,
+    progress_option: ProgressOption,
+) -> Result<Cursor<Vec<u8>>, String> {
+    let progress_tracker = ProgressTracker::new(progress_option);
+    return encode_rgb_gif(rgba_image_buffer_list, palette, None, &progress_tracker);
+}
+
+// same as `encode_images_to_ascii_rgb_gif_with_progress`, with the delay of every frame set from the frames per second
+pub fn encode_images_to_ascii_rgb_gif_with_frame_rate(
+    rgba_image_buffer_list: &[ImageBuffer<Rgba<u8>, Vec<u8>>],
+    palette: Option<&Palette>,
+    frame_rate: f32,
+    progress_option: ProgressOption,
+) -> Result<Cursor<Vec<u8>>, String> {
+    let progress_tracker = ProgressTracker::new(progress_option);
+    return encode_rgb_gif(
+        rgba_image_buffer_list,
+        palette,
+        Some(GifDelayTimer::new(frame_rate)),
+        &progress_tracker,
+    );
+}
+
+// encode the rgba ascii art images with the frames counted by an existing progress tracker
+// the frames have no delay if there is no delay timer
+pub fn encode_rgb_gif(
+    rgba_image_buffer_list: &[ImageBuffer<Rgba<u8>, Vec<u8>>],
+    palette: Option<&Palette>,
+    mut delay_timer: Option<GifDelayTimer>,
+    progress_tracker: &ProgressTracker,
+) -> Result<Cursor<Vec<u8>>, String> {
     let mut gif_buffer = Cursor::new(Vec::<u8>::new());
     let mut encoder_width: u16 = 0;
     let mut encoder_height: u16 = 0;
 
-    let (flatten_rgb, color_map) = get_img_flatten_rgb_and_color_map(rgba_image_buffer_list);
-    rgba_image_buffer_list.iter().for_each(|frame| {
+    for frame in rgba_image_buffer_list {
+        check_gif_frame_size(frame.width(), frame.height())?;
         encoder_height = encoder_height.max(frame.height() as u16);
         encoder_width = encoder_width.max(frame.width() as u16);
-    });
+    }
 
-    // start the encoding process
-    let mut encoder =
-        Encoder::new(&mut gif_buffer, encoder_width, encoder_height, &color_map).unwrap();
+    // start the encoding process, the rgb frames carry their own palette so no global palette is needed
+    let mut encoder = Encoder::new(&mut gif_buffer, encoder_width, encoder_height, &[])
+        .map_err(|e| format!("Failed to create GIF encoder: {}", e))?;
     let _ = encoder.set_repeat(Repeat::Infinite);
 
-    // get the rgb gif frame from the flatten rgb, dynamic image is neede to get the width and height info as the flattern rgb is just a 1d array
-    let rgb_gif_frame = get_rgb_gif_frame(rgba_image_buffer_list, &flatten_rgb);
+    // get the rgb gif frame from the images, transparent pixels are kept through the transparency index of the frame
+    let rgb_gif_frame = get_rgb_gif_frame(rgba_image_buffer_list, palette)?;
 
     // Convert images and write frames to GIF
-    rgb_gif_frame.iter().for_each(|frame| {
-        encoder.write_frame(frame).unwrap();
-    });
+    for mut frame in rgb_gif_frame {
+        progress_tracker.check_cancelled()?;
+        if let Some(delay_timer) = delay_timer.as_mut() {
+            frame.delay = delay_timer.next_delay();
+        }
+        encoder
+            .write_frame(&frame)
+            .map_err(|e| format!("Failed to encode GIF frame: {}", e))?;
+        progress_tracker.add_encoded_frames(1);
+    }
 
     drop(encoder);
 
-    return gif_buffer.clone();
+    return Ok(gif_buffer.clone());
 }
 
 // encode the gray ascii art images back to gray frames and return the buffer
 pub fn encode_images_to_ascii_gray_gif(
-    luma_image_buffer_list: &Vec<ImageBuffer<Luma<u8>, Vec<u8>>>,
+    luma_image_buffer_list: &[ImageBuffer<Luma<u8>, Vec<u8>>],
 ) -> Cursor<Vec<u8>> {
+    return encode_images_to_ascii_gray_gif_with_color_theme(luma_image_buffer_list, None);
+}
+
+// same as `encode_images_to_ascii_gray_gif`, the gray frames are colored with the color theme if there is one
+//
+// panics if an image is larger than a GIF frame (see `check_gif_frame_size()`),
+// use `encode_images_to_ascii_gray_gif_with_progress` to get the error instead
+pub fn encode_images_to_ascii_gray_gif_with_color_theme(
+    luma_image_buffer_list: &[ImageBuffer<Luma<u8>, Vec<u8>>],
+    color_theme: Option<ColorTheme>,
+) -> Cursor<Vec<u8>> {
+    return encode_images_to_ascii_gray_gif_with_progress(
+        luma_image_buffer_list,
+        color_theme,
+        ProgressOption::new(),
+    )
+    .expect("Failed to encode GIF");
+}
+
+// same as `encode_images_to_ascii_gray_gif_with_color_theme`, with the encoded frames reported and a cancellation token checked for every frame
+pub fn encode_images_to_ascii_gray_gif_with_progress(
+    luma_image_buffer_list: &[ImageBuffer<Luma<u8>, Vec<u8>>],
+    color_theme: Option<ColorTheme>,
+    progress_option: ProgressOption,
+) -> Result<Cursor<Vec<u8>>, String> {
+    let progress_tracker = ProgressTracker::new(progress_option);
+    return encode_gray_gif(luma_image_buffer_list, color_theme, None, &progress_tracker);
+}
+
+// same as `encode_images_to_ascii_gray_gif_with_progress`, with the delay of every frame set from the frames per second
+pub fn encode_images_to_ascii_gray_gif_with_frame_rate(
+    luma_image_buffer_list: &[ImageBuffer<Luma<u8>, Vec<u8>>],
+    color_theme: Option<ColorTheme>,
+    frame_rate: f32,
+    progress_option: ProgressOption,
+) -> Result<Cursor<Vec<u8>>, String> {
+    let progress_tracker = ProgressTracker::new(progress_option);
+    return encode_gray_gif(
+        luma_image_buffer_list,
+        color_theme,
+        Some(GifDelayTimer::new(frame_rate)),
+        &progress_tracker,
+    );
+}
+
+// encode the gray ascii art images with the frames counted by an existing progress tracker
+// the frames have no delay if there is no delay timer
+pub fn encode_gray_gif(
+    luma_image_buffer_list: &[ImageBuffer<Luma<u8>, Vec<u8>>],
+    color_theme: Option<ColorTheme>,
+    mut delay_timer: Option<GifDelayTimer>,
+    progress_tracker: &ProgressTracker,
+) -> Result<Cursor<Vec<u8>>, String> {
     let mut gif_buffer = Cursor::new(Vec::<u8>::new());
     let mut encoder_width: u16 = 0;
     let mut encoder_height: u16 = 0;
 
-    let (flatten_gray, color_map) = get_img_flatten_gray_and_color_map(luma_image_buffer_list);
-    luma_image_buffer_list.iter().for_each(|frame| {
+    for frame in luma_image_buffer_list {
+        check_gif_frame_size(frame.width(), frame.height())?;
         encoder_height = encoder_height.max(frame.height() as u16);
         encoder_width = encoder_width.max(frame.width() as u16);
-    });
+    }
+    let (flatten_gray, color_map) =
+        get_img_flatten_gray_and_color_map_with_color_theme(luma_image_buffer_list, color_theme);
 
     // start the encoding process
-    let mut encoder =
-        Encoder::new(&mut gif_buffer, encoder_width, encoder_height, &color_map).unwrap();
+    let mut encoder = Encoder::new(&mut gif_buffer, encoder_width, encoder_height, &color_map)
+        .map_err(|e| format!("Failed to create GIF encoder: {}", e))?;
     let _ = encoder.set_repeat(Repeat::Infinite);
 
     // get the grayScale gif frame from the flatten gray, dynamic image is neede to get the width and height info as the flattern rgb is just a 1d array
     let grayscale_gif_frame = get_grayscale_gif_frame(luma_image_buffer_list, &flatten_gray);
 
     // Convert images and write frames to GIF
-    grayscale_gif_frame.iter().for_each(|frame| {
-        encoder.write_frame(&frame).unwrap();
-    });
+    for mut frame in grayscale_gif_frame {
+        progress_tracker.check_cancelled()?;
+        if let Some(delay_timer) = delay_timer.as_mut() {
+            frame.delay = delay_timer.next_delay();
+        }
+        encoder
+            .write_frame(&frame)
+            .map_err(|e| format!("Failed to encode GIF frame: {}", e))?;
+        progress_tracker.add_encoded_frames(1);
+    }
 
     drop(encoder);
 
-    return gif_buffer.clone();
+    return Ok(gif_buffer.clone());
+}
+
+// ***************************************************************************************
+//
+//    The following functions stream the gif through a bounded window of frames
+//
+//    Every window goes through:
+//      - decode `frame_window` frames from the decoder
+//      - process the frames to ascii art and quantize them to gif frames in parallel
+//      - write the gif frames to the encoder in their original order
+//
+//    so only `frame_window` frames are held in memory at any time
+//
+// ***************************************************************************************
+
+// stream the rgba frames of the decoder to an rgb ascii art gif written to `writer`
+pub fn stream_frames_to_ascii_rgb_gif<R: Read, W: Write>(
+    decoder: Decoder<R>,
+    writer: W,
+    character_type: CharacterType,
+    setting_option: SettingOption,
+) -> Result<W, String> {
+    return stream_frames_to_ascii_rgb_gif_with_progress(
+        decoder,
+        writer,
+        character_type,
+        setting_option,
+        ProgressOption::new(),
+    );
+}
+
+// same as `stream_frames_to_ascii_rgb_gif`, with the progress reported and a cancellation token checked for every frame
+pub fn stream_frames_to_ascii_rgb_gif_with_progress<R: Read, W: Write>(
+    mut decoder: Decoder<R>,
+    writer: W,
+    character_type: CharacterType,
+    setting_option: SettingOption,
+    progress_option: ProgressOption,
+) -> Result<W, String> {
+    setting_option.validate()?;
+    let progress_tracker = ProgressTracker::new(progress_option);
+    // the rgb frames carry their own palette, so no global palette is needed
+    let mut encoder = GifStreamEncoder::new(writer, &[]);
+    let mut frame_sampler = FrameSampler::new(
+        setting_option.frame_selection,
+        decoder.width(),
+        decoder.height(),
+    );
+
+    loop {
+        let frame_window = read_frame_window(
+            &mut decoder,
+            &mut frame_sampler,
+            setting_option.frame_window,
+            &progress_tracker,
+        )?;
+        if frame_window.is_empty() {
+            break;
+        }
+
+        let gif_frame_list: Vec<Frame<'static>> = frame_window
+            .par_iter()
+            .map(|sampled_frame| {
+                progress_tracker.check_cancelled()?;
+                setting_option
+                    .preprocess
+                    .get_output_size(sampled_frame.img.width(), sampled_frame.img.height())?;
+                let rgb_ascii_img = rgb_to_rgb_ascii_img(
+                    &sampled_frame.img,
+                    character_type,
+                    setting_option.clone(),
+                );
+
+                let gif_frame =
+                    rgba_img_to_gif_frame(&rgb_ascii_img, setting_option.palette.as_deref())?;
+                progress_tracker.add_converted_frames(sampled_frame.delay_list.len());
+                Ok(repeat_gif_frame(gif_frame, &sampled_frame.delay_list))
+            })
+            .collect::<Result<Vec<_>, String>>()?
+            .concat();
+        drop(frame_window);
+
+        encoder.write_frame_window(&gif_frame_list, &progress_tracker)?;
+    }
+
+    return encoder.finish();
+}
+
+// stream the indexed frames of the decoder to a grayscale ascii art gif written to `writer`
+pub fn stream_frames_to_ascii_gray_gif<R: Read, W: Write>(
+    decoder: Decoder<R>,
+    writer: W,
+    character_type: CharacterType,
+    setting_option: SettingOption,
+) -> Result<W, String> {
+    return stream_frames_to_ascii_gray_gif_with_progress(
+        decoder,
+        writer,
+        character_type,
+        setting_option,
+        ProgressOption::new(),
+    );
+}
+
+// same as `stream_frames_to_ascii_gray_gif`, with the progress reported and a cancellation token checked for every frame
+pub fn stream_frames_to_ascii_gray_gif_with_progress<R: Read, W: Write>(
+    mut decoder: Decoder<R>,
+    writer: W,
+    character_type: CharacterType,
+    setting_option: SettingOption,
+    progress_option: ProgressOption,
+) -> Result<W, String> {
+    setting_option.validate()?;
+    let progress_tracker = ProgressTracker::new(progress_option);
+    let mut encoder =
+        GifStreamEncoder::new(writer, &get_gray_color_map(setting_option.color_theme));
+    let mut frame_sampler = FrameSampler::new(
+        setting_option.frame_selection,
+        decoder.width(),
+        decoder.height(),
+    );
+
+    loop {
+        let frame_window = read_frame_window(
+            &mut decoder,
+            &mut frame_sampler,
+            setting_option.frame_window,
+            &progress_tracker,
+        )?;
+        if frame_window.is_empty() {
+            break;
+        }
+
+        let gif_frame_list: Vec<Frame<'static>> = frame_window
+            .par_iter()
+            .map(|sampled_frame| {
+                progress_tracker.check_cancelled()?;
+                setting_option
+                    .preprocess
+                    .get_output_size(sampled_frame.img.width(), sampled_frame.img.height())?;
+                let luma_ascii_img = grayscale_to_ascii_img(
+                    &sampled_frame.img,
+                    character_type,
+                    setting_option.clone(),
+                );
+
+                let gif_frame = luma_img_to_gif_frame(luma_ascii_img)?;
+                progress_tracker.add_converted_frames(sampled_frame.delay_list.len());
+                Ok(repeat_gif_frame(gif_frame, &sampled_frame.delay_list))
+            })
+            .collect::<Result<Vec<_>, String>>()?
+            .concat();
+        drop(frame_window);
+
+        encoder.write_frame_window(&gif_frame_list, &progress_tracker)?;
+    }
+
+    return encoder.finish();
+}
+
+// read up to `frame_window` selected frames from the decoder, an empty list means there is no frame left
+fn read_frame_window<R: Read>(
+    decoder: &mut Decoder<R>,
+    frame_sampler: &mut FrameSampler,
+    frame_window: usize,
+    progress_tracker: &ProgressTracker,
+) -> Result<Vec<SampledFrame>, String> {
+    let frame_window = frame_window.max(1);
+    let mut frame_list = Vec::with_capacity(frame_window.min(DEFAULT_FRAME_WINDOW));
+
+    while frame_list.len() < frame_window {
+        if frame_sampler.is_finished() {
+            frame_list.extend(frame_sampler.finish());
+            break;
+        }
+
+        progress_tracker.check_cancelled()?;
+        match decoder
+            .read_next_frame()
+            .map_err(|e| format!("Failed to decode GIF frame: {}", e))?
+        {
+            Some(frame) => {
+                frame_list.extend(frame_sampler.push(frame));
+                progress_tracker.add_decoded_frames(1);
+            }
+            None => {
+                frame_list.extend(frame_sampler.finish());
+                break;
+            }
+        }
+    }
+
+    return Ok(frame_list);
+}
+
+// a converted frame is written once for every delay it was sampled with
+fn repeat_gif_frame(gif_frame: Frame<'static>, delay_list: &[u16]) -> Vec<Frame<'static>> {
+    return delay_list
+        .iter()
+        .map(|delay| {
+            let mut gif_frame = gif_frame.clone();
+            gif_frame.delay = *delay;
+            gif_frame
+        })
+        .collect();
+}
+
+// the encoder of a streamed gif, created on the first window of frames
+// as the size of the ascii art is only known after the first frames were processed
+//
+// every frame is the converted logical screen of the source, so the first frame has the size of every frame
+pub struct GifStreamEncoder<W: Write> {
+    writer: Option<W>,
+    encoder: Option<Encoder<W>>,
+    global_palette: Vec<u8>,
+    width: u16,
+    height: u16,
+}
+
+impl<W: Write> GifStreamEncoder<W> {
+    pub fn new(writer: W, global_palette: &[u8]) -> GifStreamEncoder<W> {
+        return GifStreamEncoder {
+            writer: Some(writer),
+            encoder: None,
+            global_palette: global_palette.to_vec(),
+            width: 0,
+            height: 0,
+        };
+    }
+
+    // write a window of gif frames in order, a frame that doesn't fit the screen is an error instead of being clipped
+    pub fn write_frame_window(
+        &mut self,
+        gif_frame_list: &[Frame<'static>],
+        progress_tracker: &ProgressTracker,
+    ) -> Result<(), String> {
+        if self.encoder.is_none() {
+            gif_frame_list.iter().for_each(|frame| {
+                self.height = self.height.max(frame.height);
+                self.width = self.width.max(frame.width);
+            });
+
+            let writer = self.writer.take().expect("GIF writer was already taken");
+            let mut encoder = Encoder::new(writer, self.width, self.height, &self.global_palette)
+                .map_err(|e| format!("Failed to create GIF encoder: {}", e))?;
+            let _ = encoder.set_repeat(Repeat::Infinite);
+            self.encoder = Some(encoder);
+        }
+
+        let encoder = self.encoder.as_mut().unwrap();
+        for frame in gif_frame_list {
+            progress_tracker.check_cancelled()?;
+            if frame.left as u32 + frame.width as u32 > self.width as u32
+                || frame.top as u32 + frame.height as u32 > self.height as u32
+            {
+                return Err(format!(
+                    "GIF frame of {}x{} does not fit the {}x{} screen of the first frame",
+                    frame.width, frame.height, self.width, self.height
+                ));
+            }
+            encoder
+                .write_frame(frame)
+                .map_err(|e| format!("Failed to encode GIF frame: {}", e))?;
+            progress_tracker.add_encoded_frames(1);
+        }
+
+        return Ok(());
+    }
+
+    // write the gif trailer and give back the writer
+    pub fn finish(self) -> Result<W, String> {
+        match self.encoder {
+            Some(encoder) => {
+                return encoder
+                    .into_inner()
+                    .map_err(|e| format!("Failed to finish GIF: {}", e));
+            }
+            None => return Err("GIF does not contain any frame".to_string()),
+        }
+    }
 }
 
 // ***************************************************************************************
@@ -222,122 +762,664 @@ pub fn encode_images_to_ascii_gray_gif(
 // ***************************************************************************************
 
 // process the frames to list of rgba ascii art
-pub fn process_frames_to_ascii_rgba_img(
-    mut decoder: Decoder<File>,
+//
+// panics if a frame can't be decoded, use `process_frames_to_ascii_rgba_img_with_progress` to get the error instead
+pub fn process_frames_to_ascii_rgba_img<R: Read>(
+    decoder: Decoder<R>,
     character_type: CharacterType,
     setting_option: SettingOption,
 ) -> Vec<ImageBuffer<Rgba<u8>, Vec<u8>>> {
-    let mut all_frames = vec![];
+    return process_frames_to_ascii_rgba_img_with_progress(
+        decoder,
+        character_type,
+        setting_option,
+        ProgressOption::new(),
+    )
+    .expect("Failed to convert GIF");
+}
 
-    while let Some(frame) = decoder.read_next_frame().unwrap() {
-        // get all the frame
-        all_frames.push(frame.clone());
-    }
+// same as `process_frames_to_ascii_rgba_img`, with the progress reported and a cancellation token checked for every frame
+pub fn process_frames_to_ascii_rgba_img_with_progress<R: Read>(
+    mut decoder: Decoder<R>,
+    character_type: CharacterType,
+    setting_option: SettingOption,
+    progress_option: ProgressOption,
+) -> Result<Vec<RgbaImage>, String> {
+    let progress_tracker = ProgressTracker::new(progress_option);
+
+    // get all the selected frame
+    let mut frame_sampler = FrameSampler::new(
+        setting_option.frame_selection,
+        decoder.width(),
+        decoder.height(),
+    );
+    let all_frames = read_frame_window(
+        &mut decoder,
+        &mut frame_sampler,
+        usize::MAX,
+        &progress_tracker,
+    )?;
 
-    // processed rgba image buffer that will later be encoded back to gif
-    let rgba_image_buffer_list: Vec<ImageBuffer<Rgba<u8>, Vec<u8>>> = all_frames
+    // processed image buffer that will later be encoded back to gif
+    let rgba_image_buffer_list: Vec<Vec<ImageBuffer<Rgba<u8>, Vec<u8>>>> = all_frames
         .par_iter()
-        .map(|frame| {
+        .map(|sampled_frame| {
             // Process every frame
+            progress_tracker.check_cancelled()?;
+            setting_option
+                .preprocess
+                .get_output_size(sampled_frame.img.width(), sampled_frame.img.height())?;
+            let rgb_ascii_img =
+                rgb_to_rgb_ascii_img(&sampled_frame.img, character_type, setting_option.clone());
 
-            // Convert Frame to Dynamic Image
-            let height = frame.height as u32;
-            let width = frame.width as u32;
-
-            // read the frame buffer as ImageBuffer of rgba
-            let rgb_img_buffer = RgbaImage::from_raw(width, height, frame.buffer.to_vec())
-                .expect("Failed to create RgbaImage from frame");
-
-            // convert to rgba dynamic image ffrom the buffer
-            let rgb_img = DynamicImage::ImageRgba8(rgb_img_buffer);
-
-            let rgb_ascii_img = rgb_to_rgb_ascii_img(&rgb_img, character_type, setting_option);
-
-            rgb_ascii_img
+            progress_tracker.add_converted_frames(sampled_frame.delay_list.len());
+            Ok(vec![rgb_ascii_img; sampled_frame.delay_list.len()])
         })
-        .collect();
+        .collect::<Result<_, String>>()?;
 
-    return rgba_image_buffer_list;
+    return Ok(rgba_image_buffer_list.concat());
 }
 
 // process the frames to list of gray ascii art
-pub fn process_frames_to_ascii_grayscale_img(
-    mut decoder: Decoder<File>,
+//
+// panics if a frame can't be decoded, use `process_frames_to_ascii_grayscale_img_with_progress` to get the error instead
+pub fn process_frames_to_ascii_grayscale_img<R: Read>(
+    decoder: Decoder<R>,
     character_type: CharacterType,
     setting_option: SettingOption,
 ) -> Vec<ImageBuffer<Luma<u8>, Vec<u8>>> {
-    let mut all_frames = vec![];
+    return process_frames_to_ascii_grayscale_img_with_progress(
+        decoder,
+        character_type,
+        setting_option,
+        ProgressOption::new(),
+    )
+    .expect("Failed to convert GIF");
+}
 
-    while let Some(frame) = decoder.read_next_frame().unwrap() {
-        // get all the frame
-        all_frames.push(frame.clone());
-    }
+// same as `process_frames_to_ascii_grayscale_img`, with the progress reported and a cancellation token checked for every frame
+pub fn process_frames_to_ascii_grayscale_img_with_progress<R: Read>(
+    mut decoder: Decoder<R>,
+    character_type: CharacterType,
+    setting_option: SettingOption,
+    progress_option: ProgressOption,
+) -> Result<Vec<GrayImage>, String> {
+    let progress_tracker = ProgressTracker::new(progress_option);
+
+    // get all the selected frame
+    let mut frame_sampler = FrameSampler::new(
+        setting_option.frame_selection,
+        decoder.width(),
+        decoder.height(),
+    );
+    let all_frames = read_frame_window(
+        &mut decoder,
+        &mut frame_sampler,
+        usize::MAX,
+        &progress_tracker,
+    )?;
 
-    // processed luma8 image buffer that will later be encoded back to gif
-    let luma8_image_buffer_list: Vec<ImageBuffer<Luma<u8>, Vec<u8>>> = all_frames
+    // processed image buffer that will later be encoded back to gif
+    let luma8_image_buffer_list: Vec<Vec<ImageBuffer<Luma<u8>, Vec<u8>>>> = all_frames
         .par_iter()
-        .map(|frame| {
+        .map(|sampled_frame| {
             // Process every frame
+            progress_tracker.check_cancelled()?;
+            setting_option
+                .preprocess
+                .get_output_size(sampled_frame.img.width(), sampled_frame.img.height())?;
+            let luma_ascii_img =
+                grayscale_to_ascii_img(&sampled_frame.img, character_type, setting_option.clone());
 
-            // Convert Frame to Dynamic Image
-            let height = frame.height as u32;
-            let width = frame.width as u32;
+            progress_tracker.add_converted_frames(sampled_frame.delay_list.len());
+            Ok(vec![luma_ascii_img; sampled_frame.delay_list.len()])
+        })
+        .collect::<Result<_, String>>()?;
 
-            // read the frame buffer as ImageBuffer of luma8
-            let luma_img_buffer = GrayImage::from_raw(width, height, frame.buffer.to_vec())
-                .expect("Failed to create GrayImage from frame");
+    return Ok(luma8_image_buffer_list.concat());
+}
 
-            // convert to luma8 dynamic image from the buffer
-            let luma_img = DynamicImage::ImageLuma8(luma_img_buffer);
+// quantize a rgba ascii art image to a gif frame with its own palette
+// gif only support a 1-bit transparency, so a pixel is either fully transparent or opaque
+pub fn rgba_img_to_gif_frame(
+    img: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    palette: Option<&Palette>,
+) -> Result<Frame<'static>, String> {
+    check_gif_frame_size(img.width(), img.height())?;
+    let width = img.width() as u16;
+    let height = img.height() as u16;
+    let has_transparency = img
+        .pixels()
+        .any(|pixel| pixel[3] < GIF_TRANSPARENT_ALPHA_THRESHOLD);
 
-            let luma_ascii_img = grayscale_to_ascii_img(&luma_img, character_type, setting_option);
+    // the palette is used as the palette of the frame, a spare slot of the palette is needed for the transparent color
+    if let Some(palette) = palette {
+        let mut palette_matcher = PaletteMatcher::new(palette);
+        let mut color_map = palette.get_color_map();
+        let palette_size = palette.get_color_list().len();
 
-            luma_ascii_img
-        })
-        .collect();
-    return luma8_image_buffer_list;
+        if !has_transparency || palette_size < MAX_PALETTE_SIZE {
+            let transparent = if has_transparency {
+                color_map.extend_from_slice(&[0, 0, 0]);
+                Some(palette_size as u8)
+            } else {
+                None
+            };
+            let pixel_list: Vec<u8> = img
+                .pixels()
+                .map(|pixel| match transparent {
+                    Some(index) if pixel[3] < GIF_TRANSPARENT_ALPHA_THRESHOLD => index,
+                    _ => palette_matcher.get_index([pixel[0], pixel[1], pixel[2]]),
+                })
+                .collect();
+
+            let mut frame =
+                Frame::from_palette_pixels(width, height, pixel_list, color_map, transparent);
+            if has_transparency {
+                frame.dispose = DisposalMethod::Background;
+            }
+            return Ok(frame);
+        }
+    }
+
+    if !has_transparency {
+        let mut flat_rgb_list: Vec<u8> =
+            Vec::with_capacity((img.width() * img.height() * 3) as usize);
+        for pixel in img.pixels() {
+            flat_rgb_list.extend_from_slice(&[pixel[0], pixel[1], pixel[2]]); // Ignore alpha
+        }
+        return Ok(Frame::from_rgb_speed(width, height, &flat_rgb_list, 10));
+    }
+
+    let mut flat_rgba_list: Vec<u8> = Vec::with_capacity((img.width() * img.height() * 4) as usize);
+    for pixel in img.pixels() {
+        let alpha = if pixel[3] < GIF_TRANSPARENT_ALPHA_THRESHOLD {
+            0
+        } else {
+            255
+        };
+        flat_rgba_list.extend_from_slice(&[pixel[0], pixel[1], pixel[2], alpha]);
+    }
+    let mut frame = Frame::from_rgba_speed(width, height, &mut flat_rgba_list, 10);
+    // clear the frame before the next one is drawn, else the transparent part will show the previous frame
+    frame.dispose = DisposalMethod::Background;
+
+    return Ok(frame);
+}
+
+// the luma value of a gray ascii art image is already the index into the grayscale color map
+pub fn luma_img_to_gif_frame(
+    img: ImageBuffer<Luma<u8>, Vec<u8>>,
+) -> Result<Frame<'static>, String> {
+    check_gif_frame_size(img.width(), img.height())?;
+    let width = img.width() as u16;
+    let height = img.height() as u16;
+
+    return Ok(Frame {
+        width,
+        height,
+        buffer: Cow::Owned(img.into_raw()),
+        ..Frame::default()
+    });
 }
 
-// a function to convert the flatten rgb to gif frame
+// the width and height of a GIF are 16 bits, a larger ascii art would be cut off
+pub fn check_gif_frame_size(width: u32, height: u32) -> Result<(), String> {
+    if width > u16::MAX as u32 || height > u16::MAX as u32 {
+        return Err(format!(
+            "The ascii art of {}x{} is larger than the {}x{} of a GIF, use fewer columns",
+            width,
+            height,
+            u16::MAX,
+            u16::MAX
+        ));
+    }
+    return Ok(());
+}
+
+// a function to convert the rgba ascii art images to gif frame
 // utilizing rayon parallel processing to faster the process
 fn get_rgb_gif_frame(
-    dynamic_image_list: &Vec<ImageBuffer<Rgba<u8>, Vec<u8>>>,
-    flatten_rgb: &Vec<Vec<u8>>,
-) -> Vec<Frame<'static>> {
-    let frame_array: Vec<Frame<'static>> = dynamic_image_list
+    dynamic_image_list: &[ImageBuffer<Rgba<u8>, Vec<u8>>],
+    palette: Option<&Palette>,
+) -> Result<Vec<Frame<'static>>, String> {
+    return dynamic_image_list
         .par_iter()
-        .enumerate()
-        .map(|(index, img)| {
-            let pixel = flatten_rgb[index].clone(); // Cloning to own the data
-            let width = img.width() as u16;
-            let height = img.height() as u16;
-            let frame = Frame::from_rgb_speed(width, height, &pixel, 10);
-            frame
-        })
+        .map(|img| rgba_img_to_gif_frame(img, palette))
         .collect();
-
-    return frame_array;
 }
 
 // a function to convert the flatten gray to gif frame
 // utilizing rayon parallel processing to faster the process
 fn get_grayscale_gif_frame(
-    dynamic_image_list: &Vec<ImageBuffer<Luma<u8>, Vec<u8>>>,
-    flatten_gray: &Vec<Vec<u8>>,
+    dynamic_image_list: &[ImageBuffer<Luma<u8>, Vec<u8>>],
+    flatten_gray: &[Vec<u8>],
 ) -> Vec<Frame<'static>> {
     let frame_array: Vec<Frame<'static>> = dynamic_image_list
         .par_iter()
         .enumerate()
         .map(|(index, img)| {
-            let mut pixel = flatten_gray[index].clone(); // Cloning to own the data
+            let pixel = &flatten_gray[index];
             let width = img.width() as u16;
             let height = img.height() as u16;
             // it will be quite fast for rgb that was originally grayscale
             // so we will not use Frame::from_rgb_speed like the rgb one does
-            let frame = Frame::from_rgb(width, height, &mut pixel);
-            frame
+            Frame::from_rgb(width, height, pixel)
         })
         .collect();
 
     return frame_array;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FrameSelection;
+
+    // build a small gif in memory, every frame is a solid color with its own delay
+    fn build_gif(num_frames: u16) -> Vec<u8> {
+        let color_map = [0, 0, 0, 255, 255, 255, 255, 0, 0, 0, 0, 255];
+        let mut buffer = Vec::new();
+        {
+            let mut encoder = Encoder::new(&mut buffer, 24, 16, &color_map).unwrap();
+            for index in 0..num_frames {
+                let mut frame = Frame {
+                    width: 24,
+                    height: 16,
+                    buffer: Cow::Owned(vec![(index % 4) as u8; 24 * 16]),
+                    ..Frame::default()
+                };
+                frame.delay = index + 1;
+                encoder.write_frame(&frame).unwrap();
+            }
+        }
+        return buffer;
+    }
+
+    fn get_frame_delays(gif_buffer: &[u8]) -> Vec<u16> {
+        let mut decoder = DecodeOptions::new().read_info(gif_buffer).unwrap();
+        let mut delays = vec![];
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delays.push(frame.delay);
+        }
+        return delays;
+    }
+
+    #[test]
+    fn test_stream_rgb_keeps_frame_order_with_small_window() {
+        let gif = build_gif(5);
+        let decoder = decode_gif(gif.as_slice(), DecodeOptions::new()).unwrap();
+        let output = stream_frames_to_ascii_rgb_gif(
+            decoder,
+            Vec::new(),
+            CharacterType::Simple,
+            SettingOption::rgb(8).with_frame_window(2),
+        )
+        .unwrap();
+
+        assert_eq!(get_frame_delays(&output), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_stream_gray_keeps_frame_order_with_small_window() {
+        let gif = build_gif(5);
+        let decoder = decode_gif(gif.as_slice(), DecodeOptions::new()).unwrap();
+        let output = stream_frames_to_ascii_gray_gif(
+            decoder,
+            Vec::new(),
+            CharacterType::Simple,
+            SettingOption::grayscale(8).with_frame_window(3),
+        )
+        .unwrap();
+
+        assert_eq!(get_frame_delays(&output), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_window_larger_than_gif() {
+        let gif = build_gif(2);
+        let output = rgb_gif_to_ascii_grayscale_gif(
+            gif.as_slice(),
+            DecodeOptions::new(),
+            CharacterType::Simple,
+            SettingOption::grayscale(8).with_frame_window(64),
+        );
+
+        assert_eq!(get_frame_delays(output.get_ref()), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_stream_selected_frames() {
+        let gif = build_gif(5);
+        let decoder = decode_gif(gif.as_slice(), DecodeOptions::new()).unwrap();
+        let output = stream_frames_to_ascii_gray_gif(
+            decoder,
+            Vec::new(),
+            CharacterType::Simple,
+            SettingOption::grayscale(8)
+                .with_frame_selection(FrameSelection::every_nth(2))
+                .with_frame_window(2),
+        )
+        .unwrap();
+
+        // the delay of a skipped frame goes to the kept frame before it
+        assert_eq!(get_frame_delays(&output), vec![3, 7, 5]);
+    }
+
+    #[test]
+    fn test_partial_frame_is_converted_as_the_full_screen() {
+        let color_map = [0, 0, 0, 255, 255, 255];
+        let mut gif = Vec::new();
+        {
+            let mut encoder = Encoder::new(&mut gif, 24, 16, &color_map).unwrap();
+            encoder
+                .write_frame(&Frame {
+                    width: 24,
+                    height: 16,
+                    buffer: Cow::Owned(vec![1; 24 * 16]),
+                    ..Frame::default()
+                })
+                .unwrap();
+            // an optimized gif only update part of the screen
+            encoder
+                .write_frame(&Frame {
+                    left: 4,
+                    top: 4,
+                    width: 4,
+                    height: 4,
+                    buffer: Cow::Owned(vec![0; 4 * 4]),
+                    ..Frame::default()
+                })
+                .unwrap();
+        }
+
+        let decoder = decode_gif(gif.as_slice(), DecodeOptions::new()).unwrap();
+        let output = stream_frames_to_ascii_rgb_gif(
+            decoder,
+            Vec::new(),
+            CharacterType::Simple,
+            SettingOption::rgb(8),
+        )
+        .unwrap();
+
+        let mut decoder = DecodeOptions::new().read_info(output.as_slice()).unwrap();
+        let mut frame_size_list = vec![];
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            frame_size_list.push((frame.width, frame.height));
+        }
+        assert_eq!(frame_size_list.len(), 2);
+        assert_eq!(frame_size_list[0], frame_size_list[1]);
+    }
+
+    #[test]
+    fn test_stream_encoder_rejects_frame_larger_than_screen() {
+        let frame = |width: u16| Frame {
+            width,
+            height: 1,
+            buffer: Cow::Owned(vec![0; width as usize]),
+            ..Frame::default()
+        };
+        let progress_tracker = ProgressTracker::new(ProgressOption::new());
+        let mut encoder = GifStreamEncoder::new(Vec::new(), &[0, 0, 0]);
+        assert!(encoder
+            .write_frame_window(&[frame(2)], &progress_tracker)
+            .is_ok());
+        assert!(encoder
+            .write_frame_window(&[frame(3)], &progress_tracker)
+            .is_err());
+    }
+
+    #[test]
+    fn test_transparent_ascii_art_frame() {
+        let img = ImageBuffer::from_fn(2, 1, |x, _| {
+            if x == 0 {
+                Rgba([255, 0, 0, 255])
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        });
+        let frame = rgba_img_to_gif_frame(&img, None).unwrap();
+        assert!(frame.transparent.is_some());
+        assert_eq!(frame.buffer[1], frame.transparent.unwrap());
+        assert_eq!(frame.dispose, gif::DisposalMethod::Background);
+
+        // an opaque frame doesn't need a transparent color
+        let img = ImageBuffer::from_pixel(2, 1, Rgba([255, 0, 0, 255]));
+        assert!(rgba_img_to_gif_frame(&img, None)
+            .unwrap()
+            .transparent
+            .is_none());
+    }
+
+    #[test]
+    fn test_gray_gif_with_color_theme() {
+        let gif = build_gif(2);
+        let color_theme = ColorTheme::amber();
+        let decoder = decode_gif(gif.as_slice(), DecodeOptions::new()).unwrap();
+        let output = stream_frames_to_ascii_gray_gif(
+            decoder,
+            Vec::new(),
+            CharacterType::Simple,
+            SettingOption::grayscale(8).with_color_theme(color_theme),
+        )
+        .unwrap();
+
+        let decoder = DecodeOptions::new().read_info(output.as_slice()).unwrap();
+        assert_eq!(
+            decoder.global_palette().unwrap(),
+            color_theme.get_color_map().as_slice()
+        );
+
+        // the same palette when encoding the gray ascii art images
+        let luma_img = ImageBuffer::from_pixel(4, 4, Luma([255]));
+        let output =
+            encode_images_to_ascii_gray_gif_with_color_theme(&[luma_img], Some(color_theme));
+        let decoder = DecodeOptions::new()
+            .read_info(output.get_ref().as_slice())
+            .unwrap();
+        assert_eq!(
+            decoder.global_palette().unwrap(),
+            color_theme.get_color_map().as_slice()
+        );
+    }
+
+    #[test]
+    fn test_rgb_gif_with_palette() {
+        let palette = Palette::from_preset(crate::PalettePreset::GameBoy);
+        let gif = build_gif(2);
+        let decoder = decode_gif(gif.as_slice(), DecodeOptions::new()).unwrap();
+        let output = stream_frames_to_ascii_rgb_gif(
+            decoder,
+            Vec::new(),
+            CharacterType::Simple,
+            SettingOption::rgb(8).with_palette(palette),
+        )
+        .unwrap();
+
+        // every frame use the palette as its color map
+        let mut decoder = DecodeOptions::new().read_info(output.as_slice()).unwrap();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!(frame.palette.as_ref().unwrap(), &palette.get_color_map());
+        }
+
+        let img = ImageBuffer::from_pixel(4, 4, Rgba([200, 30, 20, 255]));
+        let output = encode_images_to_ascii_rgb_gif_with_palette(&[img], Some(&palette));
+        let mut decoder = DecodeOptions::new()
+            .read_info(output.get_ref().as_slice())
+            .unwrap();
+        let frame = decoder.read_next_frame().unwrap().unwrap();
+        assert_eq!(frame.palette.as_ref().unwrap(), &palette.get_color_map());
+    }
+
+    #[test]
+    fn test_invalid_setting_option_is_an_error() {
+        let gif = build_gif(2);
+        let decoder = decode_gif(gif.as_slice(), DecodeOptions::new()).unwrap();
+        let mut setting_option = SettingOption::rgb(8);
+        setting_option.frame_window = 0;
+
+        assert!(stream_frames_to_ascii_rgb_gif(
+            decoder,
+            Vec::new(),
+            CharacterType::Simple,
+            setting_option
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_frame_window_is_at_least_one() {
+        assert_eq!(SettingOption::rgb(8).with_frame_window(0).frame_window, 1);
+    }
+
+    #[test]
+    fn test_invalid_gif_is_an_error() {
+        assert!(decode_gif(&b"not a gif"[..], DecodeOptions::new()).is_err());
+    }
+
+    #[test]
+    fn test_invalid_gif_leaves_no_output_file() {
+        let directory = std::env::temp_dir().join(format!("rasciify_gif_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let input = directory.join("invalid.gif");
+        std::fs::write(&input, b"not a gif").unwrap();
+        let output_directory = directory.to_str().unwrap();
+
+        let result = gif_to_gif(
+            input.to_str().unwrap(),
+            CharacterType::Simple,
+            Some(output_directory),
+            Some("output"),
+            SettingOption::rgb(8),
+        );
+
+        assert!(result.is_err());
+        assert!(!directory.join("output.gif").exists());
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn test_check_gif_frame_size() {
+        assert!(check_gif_frame_size(u16::MAX as u32, u16::MAX as u32).is_ok());
+        assert!(check_gif_frame_size(u16::MAX as u32 + 1, 10).is_err());
+        assert!(check_gif_frame_size(10, u16::MAX as u32 + 1).is_err());
+    }
+
+    // write the test gif to its own directory, so the tests can run together
+    fn write_test_gif(name: &str, num_frames: u16) -> std::path::PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("rasciify_gif_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("input.gif"), build_gif(num_frames)).unwrap();
+        return directory;
+    }
+
+    #[test]
+    fn test_count_gif_frames() {
+        let directory = write_test_gif("count", 5);
+        let input = directory.join("input.gif");
+        let input = input.to_str().unwrap();
+
+        assert_eq!(count_gif_frames(input).unwrap(), 5);
+        assert_eq!(
+            count_selected_gif_frames(input, FrameSelection::every_nth(2)).unwrap(),
+            3
+        );
+        assert_eq!(
+            count_selected_gif_frames(input, FrameSelection::range(1, 3)).unwrap(),
+            2
+        );
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn test_progress_is_reported_in_order() {
+        let directory = write_test_gif("progress", 5);
+        let progress_list = std::sync::Mutex::new(Vec::<crate::GifProgress>::new());
+        let observer = |progress| progress_list.lock().unwrap().push(progress);
+
+        gif_to_gif_with_progress(
+            directory.join("input.gif").to_str().unwrap(),
+            CharacterType::Simple,
+            directory.to_str(),
+            Some("output"),
+            SettingOption::rgb(8).with_frame_window(2),
+            ProgressOption::new().with_observer(&observer),
+        )
+        .unwrap();
+
+        let progress_list = progress_list.into_inner().unwrap();
+        for (previous, progress) in progress_list.iter().zip(progress_list.iter().skip(1)) {
+            assert!(progress.decoded_frames >= previous.decoded_frames);
+            assert!(progress.converted_frames >= previous.converted_frames);
+            assert!(progress.encoded_frames >= previous.encoded_frames);
+        }
+        let last = progress_list.last().unwrap();
+        assert_eq!(last.total_frames, Some(5));
+        assert_eq!(last.decoded_frames, 5);
+        assert_eq!(last.converted_frames, 5);
+        assert_eq!(last.encoded_frames, 5);
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn test_cancelled_conversion_leaves_no_output_file() {
+        let directory = write_test_gif("cancel", 5);
+        let cancellation_token = crate::CancellationToken::new();
+        cancellation_token.cancel();
+
+        let result = gif_to_gif_with_progress(
+            directory.join("input.gif").to_str().unwrap(),
+            CharacterType::Simple,
+            directory.to_str(),
+            Some("output"),
+            SettingOption::grayscale(8),
+            ProgressOption::new().with_cancellation_token(&cancellation_token),
+        );
+
+        assert_eq!(result, Err(crate::CANCELLED_ERROR.to_string()));
+        assert!(!directory.join("output.gif").exists());
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn test_encode_image_larger_than_gif_is_an_error() {
+        let img = GrayImage::new(u16::MAX as u32 + 1, 1);
+        assert!(
+            encode_images_to_ascii_gray_gif_with_progress(&[img], None, ProgressOption::new())
+                .is_err()
+        );
+        let img = RgbaImage::new(1, u16::MAX as u32 + 1);
+        assert!(
+            encode_images_to_ascii_rgb_gif_with_progress(&[img], None, ProgressOption::new())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_encode_with_frame_rate() {
+        let img_list = vec![GrayImage::new(4, 4); 2];
+        let gif_buffer = encode_images_to_ascii_gray_gif_with_frame_rate(
+            &img_list,
+            None,
+            10.0,
+            ProgressOption::new(),
+        )
+        .unwrap();
+        let mut decoder = DecodeOptions::new()
+            .read_info(gif_buffer.get_ref().as_slice())
+            .unwrap();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!(frame.delay, 10);
+        }
+
+        let img = RgbaImage::new(u16::MAX as u32 + 1, 1);
+        let result = encode_images_to_ascii_rgb_gif_with_frame_rate(
+            &[img],
+            None,
+            10.0,
+            ProgressOption::new(),
+        );
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,473 @@
+use std::{
+    cmp::Ordering,
+    fs::{remove_file, write},
+    path::{Path, PathBuf},
+};
+
+use globset::GlobBuilder;
+use image::{
+    imageops::{overlay, FilterType},
+    DynamicImage, GenericImageView, GrayImage, RgbaImage,
+};
+
+use crate::{
+    batch::collect_file_list,
+    dynamic_image_to_image, encode_gray_gif, encode_rgb_gif, grayscale_to_ascii_img,
+    preprocess::{get_image_dimensions, open_image, Preprocess},
+    rgb_to_rgb_ascii_img,
+    utils::{
+        frame_sampler::GifDelayTimer,
+        parallel::*,
+        progress_tracker::ProgressTracker,
+        utils::{check_and_create_directory, get_output_path, get_saved_message},
+    },
+    CharacterType, ProgressOption, SettingOption,
+};
+
+// the least number of digits of the frame number of an ascii image sequence, e.g. `walk_0001.png`
+const MIN_SEQUENCE_DIGITS: usize = 4;
+
+// find the images that match a glob pattern, e.g. "frames/*.png" or "shots/**/walk_*.png"
+//
+// the part of the pattern before the first wildcard is the directory that is searched,
+// the paths are sorted by their numbers so `frame_2.png` come before `frame_10.png`
+//
+// a directory that a symlink lead back to is only searched once
+pub fn collect_image_sequence(pattern: &str) -> Result<Vec<String>, String> {
+    let pattern = pattern.replace('\\', "/");
+    let component_list: Vec<&str> = pattern.split('/').collect();
+    let num_literal_components = component_list
+        .iter()
+        .position(|component| component.contains(['*', '?', '[', '{']))
+        .unwrap_or(component_list.len() - 1);
+    let mut base_directory = component_list[..num_literal_components].join("/");
+    // an absolute pattern like `/*.png` search the root, not the current directory
+    if base_directory.is_empty() && num_literal_components > 0 {
+        base_directory = "/".to_string();
+    }
+    let relative_pattern = component_list[num_literal_components..].join("/");
+
+    let glob_matcher = GlobBuilder::new(&relative_pattern)
+        .literal_separator(true)
+        .build()
+        .map_err(|e| format!("Invalid glob pattern {}: {}", pattern, e))?
+        .compile_matcher();
+    // the sub directories are only walked if the pattern goes into them
+    let is_recursive = relative_pattern.contains('/') || relative_pattern.contains("**");
+    let base_path = if base_directory.is_empty() {
+        Path::new(".")
+    } else {
+        Path::new(&base_directory)
+    };
+
+    let mut relative_path_list: Vec<PathBuf> = vec![];
+    collect_file_list(
+        base_path,
+        Path::new(""),
+        is_recursive,
+        &mut relative_path_list,
+    )?;
+    let mut path_list: Vec<String> = relative_path_list
+        .iter()
+        .filter(|relative_path| glob_matcher.is_match(relative_path))
+        .map(|relative_path| {
+            if base_directory.is_empty() {
+                return relative_path.to_string_lossy().to_string();
+            }
+            return base_path.join(relative_path).to_string_lossy().to_string();
+        })
+        .collect();
+    path_list.sort_by(|a, b| compare_natural(a, b));
+
+    if path_list.is_empty() {
+        return Err(format!("No image matches {}", pattern));
+    }
+    return Ok(path_list);
+}
+
+// Converts an ordered list of images to ASCII art gif, playing `frame_rate` images per second.
+pub fn image_sequence_to_gif(
+    path_list: &[String],
+    character_type: CharacterType,
+    output_directory: Option<&str>,
+    filename: Option<&str>,
+    setting_option: SettingOption,
+    frame_rate: f32,
+) -> Result<String, String> {
+    return image_sequence_to_gif_with_progress(
+        path_list,
+        character_type,
+        output_directory,
+        filename,
+        setting_option,
+        frame_rate,
+        ProgressOption::new(),
+    );
+}
+
+// same as `image_sequence_to_gif`, with the progress reported to the observer and a cancellation token that can stop it
+pub fn image_sequence_to_gif_with_progress(
+    path_list: &[String],
+    character_type: CharacterType,
+    output_directory: Option<&str>,
+    filename: Option<&str>,
+    setting_option: SettingOption,
+    frame_rate: f32,
+    mut progress_option: ProgressOption,
+) -> Result<String, String> {
+    if !frame_rate.is_finite() || frame_rate <= 0.0 {
+        return Err(format!(
+            "The frame rate must be greater than 0, got {}",
+            frame_rate
+        ));
+    }
+    if progress_option.total_frames.is_none() {
+        progress_option.total_frames = Some(path_list.len());
+    }
+    let progress_tracker = ProgressTracker::new(progress_option);
+
+    let gif_buffer = if setting_option.is_color {
+        let rgba_img_list = convert_image_sequence(
+            path_list,
+            setting_option.preprocess,
+            &progress_tracker,
+            |img| rgb_to_rgb_ascii_img(img, character_type, setting_option.clone()),
+        )?;
+        encode_rgb_gif(
+            &rgba_img_list,
+            setting_option.palette.as_deref(),
+            Some(GifDelayTimer::new(frame_rate)),
+            &progress_tracker,
+        )?
+    } else {
+        let luma_img_list = convert_image_sequence(
+            path_list,
+            setting_option.preprocess,
+            &progress_tracker,
+            |img| grayscale_to_ascii_img(img, character_type, setting_option.clone()),
+        )?;
+        encode_gray_gif(
+            &luma_img_list,
+            setting_option.color_theme,
+            Some(GifDelayTimer::new(frame_rate)),
+            &progress_tracker,
+        )?
+    };
+
+    let output_path = get_output_path(output_directory, filename, "gif")?;
+    if let Err(e) = write(&output_path, gif_buffer.into_inner()) {
+        let _ = remove_file(&output_path);
+        return Err(format!("Failed to write {}: {}", output_path, e));
+    }
+    return Ok(get_saved_message("Gif", output_directory, filename, "gif"));
+}
+
+// Converts an ordered list of images to a sequence of ASCII art images, saved as `filename_0000.png`, `filename_0001.png`, ...
+pub fn image_sequence_to_images(
+    path_list: &[String],
+    character_type: CharacterType,
+    output_directory: Option<&str>,
+    filename: Option<&str>,
+    setting_option: SettingOption,
+) -> Result<String, String> {
+    check_and_create_directory(output_directory)?;
+    let filename = match filename {
+        Some(filename) => filename,
+        None => return Err("No filename provided".to_string()),
+    };
+    let is_auto_orient = setting_option.preprocess.is_auto_orient;
+    let (width, height) = get_sequence_size(path_list, is_auto_orient)?;
+    let num_digits = path_list.len().saturating_sub(1).to_string().len();
+    let num_digits = num_digits.max(MIN_SEQUENCE_DIGITS);
+
+    path_list
+        .par_iter()
+        .enumerate()
+        .try_for_each(|(index, path)| -> Result<(), String> {
+            let img = load_sequence_image(path, width, height, is_auto_orient)?;
+            let frame_filename = format!("{}_{:0width$}", filename, index, width = num_digits);
+            dynamic_image_to_image(
+                &img,
+                character_type,
+                output_directory,
+                Some(&frame_filename),
+                setting_option.clone(),
+            )?;
+            Ok(())
+        })?;
+
+    let last_filename = format!(
+        "{}_{:0width$}",
+        filename,
+        path_list.len() - 1,
+        width = num_digits
+    );
+    let first_filename = format!("{}_{}", filename, "0".repeat(num_digits));
+    return Ok(format!(
+        "{} to {}.png",
+        get_saved_message(
+            "Image sequence",
+            output_directory,
+            Some(&first_filename),
+            "png"
+        ),
+        last_filename
+    ));
+}
+
+// convert every image of the sequence to rgba ascii art images, in the order of the list
+//
+// the images are fitted in the size of the first image, so every ascii art image is the same size
+pub fn image_sequence_to_ascii_rgba_img_list(
+    path_list: &[String],
+    character_type: CharacterType,
+    setting_option: SettingOption,
+) -> Result<Vec<RgbaImage>, String> {
+    let progress_tracker = ProgressTracker::new(ProgressOption::new());
+    return convert_image_sequence(
+        path_list,
+        setting_option.preprocess,
+        &progress_tracker,
+        |img| rgb_to_rgb_ascii_img(img, character_type, setting_option.clone()),
+    );
+}
+
+// convert every image of the sequence to grayscale ascii art images, in the order of the list
+//
+// the images are fitted in the size of the first image, so every ascii art image is the same size
+pub fn image_sequence_to_ascii_grayscale_img_list(
+    path_list: &[String],
+    character_type: CharacterType,
+    setting_option: SettingOption,
+) -> Result<Vec<GrayImage>, String> {
+    let progress_tracker = ProgressTracker::new(ProgressOption::new());
+    return convert_image_sequence(
+        path_list,
+        setting_option.preprocess,
+        &progress_tracker,
+        |img| grayscale_to_ascii_img(img, character_type, setting_option.clone()),
+    );
+}
+
+// load and convert the images in parallel, only the ascii art images are held in memory
+fn convert_image_sequence<T, F>(
+    path_list: &[String],
+    preprocess: Preprocess,
+    progress_tracker: &ProgressTracker,
+    convert: F,
+) -> Result<Vec<T>, String>
+where
+    T: Send,
+    F: Fn(&DynamicImage) -> T + Sync,
+{
+    let is_auto_orient = preprocess.is_auto_orient;
+    let (width, height) = get_sequence_size(path_list, is_auto_orient)?;
+    // every image is fitted in the same size, so the preprocess is checked once
+    preprocess.get_output_size(width, height)?;
+
+    return path_list
+        .par_iter()
+        .map(|path| {
+            progress_tracker.check_cancelled()?;
+            let img = load_sequence_image(path, width, height, is_auto_orient)?;
+            progress_tracker.add_decoded_frames(1);
+            let ascii_img = convert(&img);
+            progress_tracker.add_converted_frames(1);
+            Ok(ascii_img)
+        })
+        .collect();
+}
+
+// the size of the first image, which every other image is fitted in
+fn get_sequence_size(path_list: &[String], is_auto_orient: bool) -> Result<(u32, u32), String> {
+    let first_path = path_list.first().ok_or("The image sequence is empty")?;
+    let (width, height) = get_image_dimensions(first_path, is_auto_orient)
+        .map_err(|e| format!("Failed to open image {}: {}", first_path, e))?;
+    return Ok((width, height));
+}
+
+fn load_sequence_image(
+    path: &str,
+    width: u32,
+    height: u32,
+    is_auto_orient: bool,
+) -> Result<DynamicImage, String> {
+    let img = open_image(path, is_auto_orient)
+        .map_err(|e| format!("Failed to open image {}: {}", path, e))?;
+    if img.dimensions() == (width, height) {
+        return Ok(img);
+    }
+
+    // an image of another size keep its aspect ratio, it is fitted and centered on a transparent frame
+    let resized_img = img.resize(width, height, FilterType::Triangle).to_rgba8();
+    let mut letterboxed_img = RgbaImage::new(width, height);
+    overlay(
+        &mut letterboxed_img,
+        &resized_img,
+        ((width - resized_img.width()) / 2) as i64,
+        ((height - resized_img.height()) / 2) as i64,
+    );
+    return Ok(DynamicImage::ImageRgba8(letterboxed_img));
+}
+
+// compare the runs of digits by their value and the rest character by character
+fn compare_natural(a: &str, b: &str) -> Ordering {
+    let mut a_iter = a.chars().peekable();
+    let mut b_iter = b.chars().peekable();
+    loop {
+        match (a_iter.peek(), b_iter.peek()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_char), Some(b_char)) if a_char.is_ascii_digit() && b_char.is_ascii_digit() => {
+                let a_number = take_digit_run(&mut a_iter);
+                let b_number = take_digit_run(&mut b_iter);
+                // the leading zeros are left out, a longer run of digits is a larger number
+                let a_trimmed = a_number.trim_start_matches('0');
+                let b_trimmed = b_number.trim_start_matches('0');
+                let ordering = a_trimmed
+                    .len()
+                    .cmp(&b_trimmed.len())
+                    .then_with(|| a_trimmed.cmp(b_trimmed))
+                    .then_with(|| a_number.len().cmp(&b_number.len()));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(a_char), Some(b_char)) => {
+                let ordering = a_char.cmp(b_char);
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a_iter.next();
+                b_iter.next();
+            }
+        }
+    }
+}
+
+fn take_digit_run<I: Iterator<Item = char>>(iter: &mut std::iter::Peekable<I>) -> String {
+    let mut digit_run = String::new();
+    while let Some(c) = iter.next_if(|c| c.is_ascii_digit()) {
+        digit_run.push(c);
+    }
+    return digit_run;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_numbers_by_value() {
+        let mut path_list = vec!["frame_10.png", "frame_2.png", "frame_02.png", "frame_1.png"];
+        path_list.sort_by(|a, b| compare_natural(a, b));
+        assert_eq!(
+            path_list,
+            vec!["frame_1.png", "frame_2.png", "frame_02.png", "frame_10.png"]
+        );
+    }
+
+    #[test]
+    fn collect_sorted_image_sequence() {
+        let directory = std::env::temp_dir().join(format!(
+            "rasciify_image_sequence_collect_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(directory.join("sub")).unwrap();
+        for name in ["frame_10.png", "frame_2.png", "frame_1.png", "note.txt"] {
+            write(directory.join(name), b"").unwrap();
+        }
+        write(directory.join("sub").join("frame_3.png"), b"").unwrap();
+
+        let pattern = format!("{}/frame_*.png", directory.to_string_lossy());
+        let path_list = collect_image_sequence(&pattern).unwrap();
+        let name_list: Vec<&str> = path_list
+            .iter()
+            .map(|path| path.rsplit('/').next().unwrap())
+            .collect();
+        assert_eq!(
+            name_list,
+            vec!["frame_1.png", "frame_2.png", "frame_10.png"]
+        );
+        let pattern = format!("{}/*.gif", directory.to_string_lossy());
+        assert!(collect_image_sequence(&pattern).is_err());
+
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn image_sequence_to_gif_size_and_frame_rate() {
+        let directory = std::env::temp_dir().join(format!(
+            "rasciify_image_sequence_gif_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path_list: Vec<String> = [(16, 16), (8, 16)]
+            .iter()
+            .enumerate()
+            .map(|(index, (width, height))| {
+                let path = directory.join(format!("frame_{}.png", index));
+                RgbaImage::new(*width, *height).save(&path).unwrap();
+                path.to_string_lossy().to_string()
+            })
+            .collect();
+        let output_directory = directory.to_string_lossy().to_string();
+
+        image_sequence_to_gif(
+            &path_list,
+            CharacterType::Simple,
+            Some(&output_directory),
+            Some("art"),
+            SettingOption::grayscale(4),
+            4.0,
+        )
+        .unwrap();
+        let gif_file = std::fs::File::open(directory.join("art.gif")).unwrap();
+        let mut decoder = gif::DecodeOptions::new().read_info(gif_file).unwrap();
+        let mut delay_list = vec![];
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delay_list.push(frame.delay);
+        }
+        assert_eq!(delay_list, vec![25, 25]);
+
+        // a frame rate that is not positive is an error
+        let result = image_sequence_to_gif(
+            &path_list,
+            CharacterType::Simple,
+            Some(&output_directory),
+            Some("art"),
+            SettingOption::grayscale(4),
+            0.0,
+        );
+        assert!(result.is_err());
+
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn image_sequence_larger_than_gif_is_an_error() {
+        let directory = std::env::temp_dir().join(format!(
+            "rasciify_image_sequence_large_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("frame_0.png");
+        RgbaImage::new(12000, 2).save(&path).unwrap();
+        let output_directory = directory.to_string_lossy().to_string();
+
+        // every pixel is a character, the ascii art is wider than a gif can be
+        let result = image_sequence_to_gif(
+            &[path.to_string_lossy().to_string()],
+            CharacterType::Simple,
+            Some(&output_directory),
+            Some("art"),
+            SettingOption::rgb(12000),
+            25.0,
+        );
+        assert!(result.unwrap_err().contains("GIF"));
+        assert!(!directory.join("art.gif").exists());
+
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+}
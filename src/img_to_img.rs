@@ -1,16 +1,11 @@
-use image::{DynamicImage, GenericImageView, ImageBuffer, Luma, Rgba};
-use imageproc::drawing::draw_text_mut;
+use image::{DynamicImage, ImageBuffer, Luma, Rgb, Rgba};
 
 use crate::{
-    character::{CharacterType, FontData},
-    utils::{
-        font::get_character_dimensions,
-        utils::{
-            check_and_create_directory, get_character_and_rgba_based_on_rgba,
-            get_character_line_list_based_on_luma,
-        },
-    },
-    SettingOption,
+    ascii_grid::{sample_ascii_grid, IMAGE_FALLBACK_CELL_WIDTH},
+    character::CharacterType,
+    preprocess::open_image,
+    utils::utils::{apply_color_theme, check_and_create_directory},
+    ColorTheme, PaletteMatcher, SettingOption,
 };
 
 // Converts an image to ASCII art.
@@ -21,12 +16,34 @@ pub fn image_to_image(
     filename: Option<&str>,
     setting_option: SettingOption,
 ) -> Result<String, String> {
-    let img = image::open(path).expect("Failed to open image");
+    let img = open_image(path, setting_option.preprocess.is_auto_orient)
+        .map_err(|e| format!("Failed to open image: {}", e))?;
+
+    return dynamic_image_to_image(
+        &img,
+        character_type,
+        output_directory,
+        filename,
+        setting_option,
+    );
+}
+
+// Converts an already loaded image to ASCII art and save it.
+pub fn dynamic_image_to_image(
+    img: &DynamicImage,
+    character_type: CharacterType,
+    output_directory: Option<&str>,
+    filename: Option<&str>,
+    setting_option: SettingOption,
+) -> Result<String, String> {
+    setting_option
+        .preprocess
+        .get_output_size(img.width(), img.height())?;
 
     // process to generate ascii rgb image or ascii grayscale image
     if setting_option.is_color {
         // todo: process image to rgb ascii image
-        let rgba_ascii_img = rgb_to_rgb_ascii_img(&img, character_type, setting_option);
+        let rgba_ascii_img = rgb_to_rgb_ascii_img(img, character_type, setting_option);
         match check_and_create_directory(output_directory) {
             Ok(_) => {
                 if let Some(filename) = filename {
@@ -35,12 +52,13 @@ pub fn image_to_image(
                     } else {
                         format!("{}.png", filename)
                     };
-                    let _ = rgba_ascii_img.save(path);
-                    if output_directory.is_some() {
+                    rgba_ascii_img
+                        .save(&path)
+                        .map_err(|e| format!("Failed to save {}: {}", path, e))?;
+                    if let Some(output_dir) = output_directory {
                         return Ok(format!(
                             "Image saved to path: {} as {}.png",
-                            output_directory.unwrap(),
-                            filename
+                            output_dir, filename
                         ));
                     } else {
                         return Ok(format!(
@@ -54,7 +72,14 @@ pub fn image_to_image(
             Err(e) => Err(e),
         }
     } else {
-        let gray_ascii_img = grayscale_to_ascii_img(&img, character_type, setting_option);
+        let gray_ascii_img = grayscale_to_ascii_img(img, character_type, setting_option.clone());
+        // the gray levels are only colored when saving if there is a color theme
+        let gray_ascii_img = match setting_option.color_theme {
+            Some(color_theme) => {
+                DynamicImage::ImageRgb8(apply_color_theme(&gray_ascii_img, color_theme))
+            }
+            None => DynamicImage::ImageLuma8(gray_ascii_img),
+        };
         match check_and_create_directory(output_directory) {
             Ok(_) => {
                 if let Some(filename) = filename {
@@ -63,12 +88,13 @@ pub fn image_to_image(
                     } else {
                         format!("{}.jpg", filename)
                     };
-                    let _ = gray_ascii_img.save(path);
-                    if output_directory.is_some() {
+                    gray_ascii_img
+                        .save(&path)
+                        .map_err(|e| format!("Failed to save {}: {}", path, e))?;
+                    if let Some(output_dir) = output_directory {
                         return Ok(format!(
                             "Image saved to path: {} as {}.jpg",
-                            output_directory.unwrap(),
-                            filename
+                            output_dir, filename
                         ));
                     } else {
                         return Ok(format!(
@@ -84,77 +110,66 @@ pub fn image_to_image(
     }
 }
 
-pub fn grayscale_to_ascii_img(
+// the ascii art image that `image_to_image` save, kept in memory:
+// rgba for a color ascii art, rgb for a grayscale ascii art with a color theme and grayscale otherwise
+pub fn dynamic_image_to_ascii_img(
     img: &DynamicImage,
     character_type: CharacterType,
     setting_option: SettingOption,
-) -> ImageBuffer<Luma<u8>, Vec<u8>> {
-    let img = img.grayscale();
-    let (width, height) = img.dimensions();
-    let character_data: FontData = character_type.get_character_data();
-    // if the user provide a number of columns that is greater than the width of the image,
-    // we default to the 1/4 width of the image to prevent panic or error
-    let mut num_cols = if setting_option.num_cols > width {
-        width / 4
-    } else {
-        setting_option.num_cols
-    };
-    // width per cell
-    let mut cell_width = width / num_cols;
-    // height per cell
-    let mut cell_height = 2 * cell_width;
-    let mut num_rows = height / cell_height;
-    let background_code = if setting_option.is_white_bg { 255 } else { 0 };
-
-    if num_cols > width || num_rows > height {
-        // Too many columns or rows. Use default setting
-        cell_width = 6;
-        cell_height = 12;
-        num_cols = width / cell_width;
-        num_rows = height / cell_height;
+) -> DynamicImage {
+    if setting_option.is_color {
+        return DynamicImage::ImageRgba8(rgb_to_rgb_ascii_img(img, character_type, setting_option));
+    }
+    if setting_option.color_theme.is_some() {
+        return DynamicImage::ImageRgb8(grayscale_to_color_theme_ascii_img(
+            img,
+            character_type,
+            setting_option,
+        ));
     }
+    return DynamicImage::ImageLuma8(grayscale_to_ascii_img(img, character_type, setting_option));
+}
 
-    // Calculate the size of the output image based on the number of columns and rows need and the size of the font
-    let (char_width, char_height) = get_character_dimensions(
-        character_data.scale,
-        character_data.character,
-        character_data.font_data,
-    );
-    let output_image_width = char_width * num_cols;
-    let output_image_height = char_height * num_rows;
-
-    // create a blank image to draw the ASCII art on
-    let mut out_image: ImageBuffer<Luma<u8>, Vec<u8>> = ImageBuffer::from_pixel(
-        output_image_width,
-        output_image_height,
-        Luma([background_code]),
+// the same as grayscale_to_ascii_img but with the gray levels mapped to the color theme,
+// black and white is used if there is no color theme
+pub fn grayscale_to_color_theme_ascii_img(
+    img: &DynamicImage,
+    character_type: CharacterType,
+    setting_option: SettingOption,
+) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let gray_ascii_img = grayscale_to_ascii_img(img, character_type, setting_option.clone());
+
+    return apply_color_theme(
+        &gray_ascii_img,
+        setting_option
+            .color_theme
+            .unwrap_or(ColorTheme::black_and_white()),
     );
+}
 
-    // Get the character based on the grayscale value and form a row of characters and draw it on the image row by row
-    for i in 0..num_rows {
-        let character_line_list = get_character_line_list_based_on_luma(
-            character_data.character_list.clone(),
-            &img,
-            num_cols,
-            cell_width,
-            width,
-            cell_height,
-            height,
-            i,
-        );
-        let text_line: String = character_line_list.into_iter().collect();
-
-        draw_text_mut(
-            &mut out_image,
-            Luma([255 - background_code]),
-            0,
-            (i * char_height) as i32,
-            character_data.scale,
-            &character_data.font,
-            &text_line,
-        );
-    }
-    return out_image;
+// when there is a color theme, the background is always drawn as 0 and the characters as 255,
+// which are the two ends of the color theme gradient
+pub fn grayscale_to_ascii_img(
+    img: &DynamicImage,
+    character_type: CharacterType,
+    setting_option: SettingOption,
+) -> ImageBuffer<Luma<u8>, Vec<u8>> {
+    let setting_option = SettingOption {
+        is_color: false,
+        is_white_bg: setting_option.is_white_bg && setting_option.color_theme.is_none(),
+        color_theme: None,
+        ..setting_option
+    };
+
+    let character_data = character_type.get_character_data();
+    return sample_ascii_grid(
+        img,
+        character_type,
+        &character_data,
+        &setting_option,
+        IMAGE_FALLBACK_CELL_WIDTH,
+    )
+    .to_luma_img_with_character_data(&character_data);
 }
 
 pub fn rgb_to_rgb_ascii_img(
@@ -162,70 +177,122 @@ pub fn rgb_to_rgb_ascii_img(
     character_type: CharacterType,
     setting_option: SettingOption,
 ) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
-    let (width, height) = img.dimensions();
-    let character_data: FontData = character_type.get_character_data();
-    // if the user provide a number of columns that is greater than the width of the image,
-    // we default to the 1/4 width of the image to prevent panic or error
-    let mut num_cols = if setting_option.num_cols > width {
-        width / 4
-    } else {
-        setting_option.num_cols
+    let setting_option = SettingOption {
+        is_color: true,
+        ..setting_option
     };
-    // width per cell
-    let mut cell_width = width / num_cols;
-    // height per cell
-    let mut cell_height = 2 * cell_width;
-    let mut num_rows = height / cell_height;
-    let background_code = if setting_option.is_white_bg { 255 } else { 0 };
-
-    if num_cols > width || num_rows > height {
-        // Too many columns or rows. Use default setting
-        cell_width = 6;
-        cell_height = 12;
-        num_cols = width / cell_width;
-        num_rows = height / cell_height;
+    let character_data = character_type.get_character_data();
+    let mut out_image = sample_ascii_grid(
+        img,
+        character_type,
+        &character_data,
+        &setting_option,
+        IMAGE_FALLBACK_CELL_WIDTH,
+    )
+    .to_rgba_img_with_character_data(&character_data);
+
+    // the edge of the characters are blended with the background, so map them to the palette as well
+    if let Some(palette) = setting_option.palette.as_deref() {
+        PaletteMatcher::new(palette).apply_to_img(&mut out_image);
     }
+    return out_image;
+}
 
-    // Calculate the size of the output image based on the number of columns and rows need and the size of the font
-    let (char_width, char_height) = get_character_dimensions(
-        character_data.scale,
-        character_data.character,
-        character_data.font_data,
-    );
-    let output_image_width = char_width * num_cols;
-    let output_image_height = char_height * num_rows;
-
-    // create a blank image to draw the ASCII art on
-    let mut out_image: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::from_pixel(
-        output_image_width,
-        output_image_height,
-        Rgba([background_code, background_code, background_code, 255]),
-    );
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AlphaMode, CellBackground, GlyphShade};
+
+    // a half opaque red, half fully transparent image
+    fn half_transparent_img() -> DynamicImage {
+        let img = ImageBuffer::from_fn(64, 32, |x, _| {
+            if x < 32 {
+                Rgba([255, 0, 0, 255])
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        });
+        return DynamicImage::ImageRgba8(img);
+    }
+
+    #[test]
+    fn test_opaque_background_by_default() {
+        let ascii_img = rgb_to_rgb_ascii_img(
+            &half_transparent_img(),
+            CharacterType::Simple,
+            SettingOption::rgb(8),
+        );
+        // the background of a cell is filled even where the image is transparent
+        let (width, height) = ascii_img.dimensions();
+        assert_eq!(ascii_img.get_pixel(width - 1, height - 1)[3], 255);
+    }
+
+    #[test]
+    fn test_transparent_background() {
+        let ascii_img = rgb_to_rgb_ascii_img(
+            &half_transparent_img(),
+            CharacterType::Simple,
+            SettingOption::rgb(8)
+                .with_transparent_bg(true)
+                .with_alpha_threshold(128, AlphaMode::Skip),
+        );
+        let (width, height) = ascii_img.dimensions();
+        // the transparent half of the image is left empty
+        for y in 0..height {
+            for x in width / 2..width {
+                assert_eq!(ascii_img.get_pixel(x, y)[3], 0);
+            }
+        }
+        assert!(ascii_img.pixels().any(|pixel| pixel[3] == 255));
+    }
 
-    // Get the character based on the mean rgb value and \draw it on the image pixel by pixel
-    for i in 0..num_rows {
-        for j in 0..num_cols {
-            let (character, rgba_value) = get_character_and_rgba_based_on_rgba(
-                character_data.character_list.clone(),
-                img,
-                cell_width,
-                width,
-                cell_height,
-                height,
-                i,
-                j,
-            );
-
-            draw_text_mut(
-                &mut out_image,
-                rgba_value,
-                (j * char_width) as i32,
-                (i * char_height) as i32,
-                character_data.scale,
-                &character_data.font,
-                &character.to_string(),
-            );
+    #[test]
+    fn test_color_theme_ascii_img() {
+        let img = DynamicImage::ImageRgba8(ImageBuffer::from_fn(64, 32, |x, _| {
+            let level = (x * 4) as u8;
+            Rgba([level, level, level, 255])
+        }));
+        let color_theme = ColorTheme::green_phosphor();
+        let ascii_img = grayscale_to_color_theme_ascii_img(
+            &img,
+            CharacterType::Simple,
+            SettingOption::grayscale(8).with_color_theme(color_theme),
+        );
+
+        // every pixel is a color of the theme gradient
+        let color_map = color_theme.get_color_map();
+        for pixel in ascii_img.pixels() {
+            assert!(color_map.chunks_exact(3).any(|color| color == pixel.0));
         }
+        // the background is the background of the theme, even with `is_white_bg`
+        let ascii_img = grayscale_to_color_theme_ascii_img(
+            &img,
+            CharacterType::Simple,
+            SettingOption::grayscale_white_bg(8).with_color_theme(color_theme),
+        );
+        let (width, height) = ascii_img.dimensions();
+        assert_eq!(
+            ascii_img.get_pixel(width - 1, height - 1).0,
+            color_theme.background
+        );
+    }
+
+    #[test]
+    fn test_cell_background() {
+        let img =
+            DynamicImage::ImageRgba8(ImageBuffer::from_pixel(64, 32, Rgba([200, 100, 50, 255])));
+        let ascii_img = rgb_to_rgb_ascii_img(
+            &img,
+            CharacterType::Simple,
+            SettingOption::rgb(8)
+                .with_cell_background(CellBackground::new(0.5, GlyphShade::Sampled)),
+        );
+
+        // the background of every cell is the darkened color of the cell instead of black
+        let (width, height) = ascii_img.dimensions();
+        assert_eq!(
+            ascii_img.get_pixel(width - 1, height - 1).0,
+            [100, 50, 25, 255]
+        );
     }
-    return out_image;
 }
@@ -1,10 +1,13 @@
 use std::fs::write;
 
 use crate::{
+    ascii_grid::{sample_ascii_grid, TEXT_FALLBACK_CELL_WIDTH},
     character::CharacterType,
-    utils::utils::{check_and_create_directory, get_character_line_list_based_on_luma},
+    preprocess::{apply_preprocess, open_image, Preprocess},
+    utils::utils::check_and_create_directory,
+    SettingOption,
 };
-use image::{DynamicImage, GenericImageView};
+use image::DynamicImage;
 
 // Converts an image to ASCII art.
 pub fn image_to_text(
@@ -14,16 +17,49 @@ pub fn image_to_text(
     output_directory: Option<&str>,
     filename: Option<&str>,
 ) -> Result<String, String> {
-    let img = image::open(path).expect("Failed to open image");
+    return image_to_text_with_preprocess(
+        path,
+        num_cols,
+        complex,
+        Preprocess::new(),
+        output_directory,
+        filename,
+    );
+}
 
-    let ascii = grayscale_to_ascii(&img, num_cols, complex);
+// same as `image_to_text`, with the image oriented, cropped, rotated, flipped, scaled and padded before the conversion
+pub fn image_to_text_with_preprocess(
+    path: &str,
+    num_cols: u32,
+    complex: bool,
+    preprocess: Preprocess,
+    output_directory: Option<&str>,
+    filename: Option<&str>,
+) -> Result<String, String> {
+    let img = open_image(path, preprocess.is_auto_orient)
+        .map_err(|e| format!("Failed to open image: {}", e))?;
+    preprocess.get_output_size(img.width(), img.height())?;
+    let img = apply_preprocess(&img, preprocess);
+
+    return dynamic_image_to_text(&img, num_cols, complex, output_directory, filename);
+}
+
+// Converts an already loaded image to ASCII art and save it.
+pub fn dynamic_image_to_text(
+    img: &DynamicImage,
+    num_cols: u32,
+    complex: bool,
+    output_directory: Option<&str>,
+    filename: Option<&str>,
+) -> Result<String, String> {
+    let ascii = grayscale_to_ascii(img, num_cols, complex);
 
     match check_and_create_directory(output_directory) {
         Ok(_) => {
             if let Some(filename) = filename {
                 let mut path: String = format!("{}.txt", filename);
-                if output_directory.is_some() {
-                    path = format!("{}{}", output_directory.unwrap(), filename);
+                if let Some(output_dir) = output_directory {
+                    path = format!("{}{}", output_dir, filename);
                 }
                 let _ = write(path, ascii.clone());
             }
@@ -34,43 +70,50 @@ pub fn image_to_text(
 }
 
 pub fn grayscale_to_ascii(img: &DynamicImage, num_cols: u32, complex: bool) -> String {
-    let img = img.grayscale();
-    let (width, height) = img.dimensions();
-    let mut ascii = String::new();
-    let mut num_cols = if num_cols > width { width } else { num_cols };
-    let mut cell_width = width / num_cols;
-    let mut cell_height = 2 * cell_width;
-    let mut num_rows = height / cell_height;
-    let character_array: Vec<char> = if complex {
-        CharacterType::Complex.get_character_data().character_list
+    let character_type = if complex {
+        CharacterType::Complex
     } else {
-        CharacterType::Simple.get_character_data().character_list
+        CharacterType::Simple
     };
 
-    if num_cols > width || num_rows > height {
-        // Too many columns or rows. Use default setting
-        cell_width = 6;
-        cell_height = 12;
-        num_cols = width / cell_width;
-        num_rows = height / cell_height;
-    }
+    // more columns than the width of the image get a column for every pixel
+    return sample_ascii_grid(
+        img,
+        character_type,
+        &character_type.get_character_data(),
+        &SettingOption::grayscale(num_cols),
+        TEXT_FALLBACK_CELL_WIDTH,
+    )
+    .to_text();
+}
 
-    for x in 0..num_rows {
-        let character_line_list = get_character_line_list_based_on_luma(
-            character_array.clone(),
-            &img,
-            num_cols,
-            cell_width,
-            width,
-            cell_height,
-            height,
-            x,
-        );
-        let text_line: String = character_line_list.into_iter().collect();
+#[cfg(test)]
+mod tests {
+    use std::fs::remove_file;
 
-        ascii.push_str(&text_line);
-        ascii.push_str("\n"); // Newline after each row
-    }
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+    use crate::Rotation;
+
+    #[test]
+    fn image_to_text_with_rotation() {
+        let path = std::env::temp_dir().join(format!(
+            "rasciify_img_to_text_rotation_{}.png",
+            std::process::id()
+        ));
+        RgbImage::from_pixel(40, 20, Rgb([200, 100, 50]))
+            .save(&path)
+            .unwrap();
+        let path = path.to_str().unwrap();
 
-    return ascii;
+        let ascii = image_to_text(path, 10, false, None, None).unwrap();
+        assert_eq!(ascii.lines().count(), 2);
+        // the rotated image is taller than it is wide, so it get more rows
+        let preprocess = Preprocess::new().with_rotation(Rotation::Rotate90);
+        let ascii = image_to_text_with_preprocess(path, 10, false, preprocess, None, None).unwrap();
+        assert_eq!(ascii.lines().count(), 10);
+
+        remove_file(path).unwrap();
+    }
 }
@@ -1,14 +1,31 @@
 pub mod character;
 pub use character::*;
 
+pub mod ascii_grid;
+pub use ascii_grid::*;
+
 pub mod img_to_img;
 pub use img_to_img::*;
 
 pub mod img_to_text;
 pub use img_to_text::*;
 
+pub mod text_to_img;
+pub use text_to_img::*;
+
+pub mod ansi;
+pub use ansi::*;
+pub mod ansi_art;
+pub use ansi_art::*;
+pub mod banner;
+pub use banner::*;
+pub mod figlet;
+pub use figlet::*;
+
 pub mod gif_to_gif;
 pub use gif_to_gif::*;
+pub mod image_sequence;
+pub use image_sequence::*;
 
 pub mod utils;
 pub use utils::*;
@@ -16,4 +33,51 @@ pub use utils::*;
 pub mod types;
 pub use types::*;
 
+pub mod palette;
+pub use palette::*;
+pub mod preprocess;
+pub use preprocess::*;
+
+pub mod config;
+pub use config::*;
+
+pub mod batch;
+pub use batch::*;
+
+pub mod progress;
+pub use progress::*;
+pub mod terminal;
+pub use terminal::*;
+pub mod video_stream;
+pub use video_stream::*;
+#[cfg(feature = "ratatui")]
+pub mod ratatui_widget;
+#[cfg(feature = "ratatui")]
+pub use ratatui_widget::*;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "capi")]
+pub use capi::*;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "python")]
+pub use python::*;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "wasm")]
+pub use wasm::*;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "server")]
+pub use server::*;
+#[cfg(feature = "watch")]
+pub mod watch;
+#[cfg(feature = "watch")]
+pub use watch::*;
+
+#[cfg(feature = "serde")]
+pub mod preset;
+#[cfg(feature = "serde")]
+pub use preset::*;
+
 fn main() {}
@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+
+use image::{ImageBuffer, Rgba};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// the most colors a palette can have, which is also the most colors a gif frame can have
+pub const MAX_PALETTE_SIZE: usize = 256;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum PalettePreset {
+    Cga,       // CGA 4 colors (mode 4, palette 1, high intensity)
+    Ega,       // EGA 16 colors
+    GameBoy,   // Game Boy 4 shades of green
+    Xterm256,  // xterm 256 colors
+    Solarized, // Solarized 16 colors
+    C64,       // Commodore 64 16 colors
+}
+
+impl PalettePreset {
+    // get a preset by name, e.g. "gameboy"
+    pub fn from_name(name: &str) -> Option<PalettePreset> {
+        match name.to_lowercase().replace(['-', '_', ' '], "").as_str() {
+            "cga" => return Some(PalettePreset::Cga),
+            "ega" | "ega16" => return Some(PalettePreset::Ega),
+            "gameboy" | "gb" => return Some(PalettePreset::GameBoy),
+            "xterm" | "xterm256" => return Some(PalettePreset::Xterm256),
+            "solarized" => return Some(PalettePreset::Solarized),
+            "c64" | "commodore64" => return Some(PalettePreset::C64),
+            _ => return None,
+        }
+    }
+
+    fn get_color_list(&self) -> Vec<[u8; 3]> {
+        match self {
+            PalettePreset::Cga => {
+                return hex_to_color_list(&[0x000000, 0x55FFFF, 0xFF55FF, 0xFFFFFF]);
+            }
+            PalettePreset::Ega => {
+                return hex_to_color_list(&[
+                    0x000000, 0x0000AA, 0x00AA00, 0x00AAAA, 0xAA0000, 0xAA00AA, 0xAA5500, 0xAAAAAA,
+                    0x555555, 0x5555FF, 0x55FF55, 0x55FFFF, 0xFF5555, 0xFF55FF, 0xFFFF55, 0xFFFFFF,
+                ]);
+            }
+            PalettePreset::GameBoy => {
+                return hex_to_color_list(&[0x0F380F, 0x306230, 0x8BAC0F, 0x9BBC0F]);
+            }
+            PalettePreset::Xterm256 => {
+                // the 16 system colors
+                let mut color_list = hex_to_color_list(&[
+                    0x000000, 0xCD0000, 0x00CD00, 0xCDCD00, 0x0000EE, 0xCD00CD, 0x00CDCD, 0xE5E5E5,
+                    0x7F7F7F, 0xFF0000, 0x00FF00, 0xFFFF00, 0x5C5CFF, 0xFF00FF, 0x00FFFF, 0xFFFFFF,
+                ]);
+                // the 6x6x6 color cube
+                let level_list: [u8; 6] = [0, 95, 135, 175, 215, 255];
+                for r in level_list {
+                    for g in level_list {
+                        for b in level_list {
+                            color_list.push([r, g, b]);
+                        }
+                    }
+                }
+                // the 24 gray ramp
+                for i in 0..24 {
+                    let gray = 8 + 10 * i;
+                    color_list.push([gray, gray, gray]);
+                }
+                return color_list;
+            }
+            PalettePreset::Solarized => {
+                return hex_to_color_list(&[
+                    0x002B36, 0x073642, 0x586E75, 0x657B83, 0x839496, 0x93A1A1, 0xEEE8D5, 0xFDF6E3,
+                    0xB58900, 0xCB4B16, 0xDC322F, 0xD33682, 0x6C71C4, 0x268BD2, 0x2AA198, 0x859900,
+                ]);
+            }
+            PalettePreset::C64 => {
+                return hex_to_color_list(&[
+                    0x000000, 0xFFFFFF, 0x68372B, 0x70A4B2, 0x6F3D86, 0x588D43, 0x352879, 0xB8C76F,
+                    0x6F4F25, 0x433900, 0x9A6759, 0x444444, 0x6C6C6C, 0x9AD284, 0x6C5EB5, 0x959595,
+                ]);
+            }
+        }
+    }
+}
+
+// a restricted set of colors that the rgb ascii art is allowed to use
+//
+// in a config file it is a list of colors, or the name of a preset, e.g. "gameboy"
+#[derive(PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "PaletteValue", into = "PaletteValue")
+)]
+pub struct Palette {
+    color_list: [[u8; 3]; MAX_PALETTE_SIZE],
+    len: usize,
+}
+
+impl Palette {
+    // create a palette from a list of colors, the list need to have 1 to 256 colors
+    pub fn new(color_list: &[[u8; 3]]) -> Result<Palette, String> {
+        if color_list.is_empty() {
+            return Err("Palette need to have at least 1 color".to_string());
+        }
+        if color_list.len() > MAX_PALETTE_SIZE {
+            return Err(format!(
+                "Palette can have at most {} colors, got {}",
+                MAX_PALETTE_SIZE,
+                color_list.len()
+            ));
+        }
+
+        let mut palette = Palette {
+            color_list: [[0, 0, 0]; MAX_PALETTE_SIZE],
+            len: color_list.len(),
+        };
+        palette.color_list[..color_list.len()].copy_from_slice(color_list);
+
+        return Ok(palette);
+    }
+
+    pub fn from_preset(palette_preset: PalettePreset) -> Palette {
+        return Palette::new(&palette_preset.get_color_list()).unwrap();
+    }
+
+    pub fn get_color_list(&self) -> &[[u8; 3]] {
+        return &self.color_list[..self.len];
+    }
+
+    // the palette as a flat list of rgb, which is the format of a gif color map
+    pub fn get_color_map(&self) -> Vec<u8> {
+        return self.get_color_list().concat();
+    }
+}
+
+// a palette in a config file, either the name of a preset or its colors
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum PaletteValue {
+    Preset(String),
+    ColorList(Vec<[u8; 3]>),
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<PaletteValue> for Palette {
+    type Error = String;
+
+    fn try_from(value: PaletteValue) -> Result<Palette, String> {
+        match value {
+            PaletteValue::Preset(name) => {
+                let palette_preset = PalettePreset::from_name(&name)
+                    .ok_or_else(|| format!("Unknown palette preset: {}", name))?;
+                return Ok(Palette::from_preset(palette_preset));
+            }
+            PaletteValue::ColorList(color_list) => return Palette::new(&color_list),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Palette> for PaletteValue {
+    fn from(palette: Palette) -> PaletteValue {
+        return PaletteValue::ColorList(palette.get_color_list().to_vec());
+    }
+}
+
+// find the nearest color of a palette by the CIE76 color difference (ΔE) in CIELAB,
+// which is closer to how different the colors look than the distance in rgb
+//
+// the matched colors are cached, as an ascii art usually only have a few distinct colors
+pub struct PaletteMatcher {
+    palette: Palette,
+    lab_list: Vec<[f32; 3]>,
+    cache: HashMap<[u8; 3], u8>,
+}
+
+impl PaletteMatcher {
+    pub fn new(palette: &Palette) -> PaletteMatcher {
+        return PaletteMatcher {
+            palette: *palette,
+            lab_list: palette.get_color_list().iter().map(rgb_to_lab).collect(),
+            cache: HashMap::new(),
+        };
+    }
+
+    pub fn get_palette(&self) -> &Palette {
+        return &self.palette;
+    }
+
+    // get the index of the nearest color in the palette
+    pub fn get_index(&mut self, rgb: [u8; 3]) -> u8 {
+        if let Some(index) = self.cache.get(&rgb) {
+            return *index;
+        }
+
+        let lab = rgb_to_lab(&rgb);
+        let mut nearest_index = 0;
+        let mut nearest_distance = f32::MAX;
+        for (index, palette_lab) in self.lab_list.iter().enumerate() {
+            let distance = (lab[0] - palette_lab[0]).powi(2)
+                + (lab[1] - palette_lab[1]).powi(2)
+                + (lab[2] - palette_lab[2]).powi(2);
+            if distance < nearest_distance {
+                nearest_distance = distance;
+                nearest_index = index;
+            }
+        }
+
+        self.cache.insert(rgb, nearest_index as u8);
+        return nearest_index as u8;
+    }
+
+    // get the nearest color in the palette
+    pub fn get_color(&mut self, rgb: [u8; 3]) -> [u8; 3] {
+        let index = self.get_index(rgb);
+        return self.palette.color_list[index as usize];
+    }
+
+    // map every pixel of the image to the nearest color in the palette, the alpha is kept
+    pub fn apply_to_img(&mut self, img: &mut ImageBuffer<Rgba<u8>, Vec<u8>>) {
+        for pixel in img.pixels_mut() {
+            let color = self.get_color([pixel[0], pixel[1], pixel[2]]);
+            *pixel = Rgba([color[0], color[1], color[2], pixel[3]]);
+        }
+    }
+}
+
+fn hex_to_color_list(hex_list: &[u32]) -> Vec<[u8; 3]> {
+    return hex_list
+        .iter()
+        .map(|hex| [(hex >> 16) as u8, (hex >> 8) as u8, *hex as u8])
+        .collect();
+}
+
+// convert sRGB to CIELAB with the D65 white point
+pub fn rgb_to_lab(rgb: &[u8; 3]) -> [f32; 3] {
+    let linear = rgb.map(|channel| {
+        let channel = channel as f32 / 255.0;
+        if channel <= 0.04045 {
+            channel / 12.92
+        } else {
+            ((channel + 0.055) / 1.055).powf(2.4)
+        }
+    });
+
+    let x = (0.4124 * linear[0] + 0.3576 * linear[1] + 0.1805 * linear[2]) / 0.95047;
+    let y = 0.2126 * linear[0] + 0.7152 * linear[1] + 0.0722 * linear[2];
+    let z = (0.0193 * linear[0] + 0.1192 * linear[1] + 0.9505 * linear[2]) / 1.08883;
+
+    let f = |t: f32| {
+        if t > 0.008856 {
+            t.cbrt()
+        } else {
+            7.787 * t + 16.0 / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+
+    return [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn palette_size() {
+        assert!(Palette::new(&[]).is_err());
+        assert!(Palette::new(&[[0, 0, 0]; MAX_PALETTE_SIZE + 1]).is_err());
+        assert_eq!(
+            Palette::new(&[[1, 2, 3], [4, 5, 6]])
+                .unwrap()
+                .get_color_map(),
+            vec![1, 2, 3, 4, 5, 6]
+        );
+    }
+
+    #[test]
+    fn preset_size() {
+        let size_list = [
+            (PalettePreset::Cga, 4),
+            (PalettePreset::Ega, 16),
+            (PalettePreset::GameBoy, 4),
+            (PalettePreset::Xterm256, 256),
+            (PalettePreset::Solarized, 16),
+            (PalettePreset::C64, 16),
+        ];
+        for (palette_preset, size) in size_list {
+            assert_eq!(
+                Palette::from_preset(palette_preset).get_color_list().len(),
+                size
+            );
+        }
+        assert_eq!(
+            PalettePreset::from_name("Game Boy"),
+            Some(PalettePreset::GameBoy)
+        );
+        assert_eq!(PalettePreset::from_name("unknown"), None);
+    }
+
+    #[test]
+    fn match_nearest_color() {
+        let palette = Palette::new(&[[0, 0, 0], [255, 255, 255], [255, 0, 0]]).unwrap();
+        let mut palette_matcher = PaletteMatcher::new(&palette);
+        assert_eq!(palette_matcher.get_index([20, 10, 10]), 0);
+        assert_eq!(palette_matcher.get_index([240, 240, 230]), 1);
+        assert_eq!(palette_matcher.get_color([200, 30, 20]), [255, 0, 0]);
+
+        let mut img = ImageBuffer::from_pixel(2, 2, Rgba([230, 230, 250, 100]));
+        palette_matcher.apply_to_img(&mut img);
+        assert!(img.pixels().all(|pixel| pixel.0 == [255, 255, 255, 100]));
+    }
+}
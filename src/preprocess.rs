@@ -0,0 +1,491 @@
+use std::{
+    borrow::Cow,
+    io::{BufRead, Cursor, Seek},
+};
+
+use image::{
+    imageops::{overlay, FilterType},
+    metadata::Orientation,
+    DynamicImage, GenericImageView, ImageDecoder, ImageReader, ImageResult, Rgba, RgbaImage,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// the largest side a preprocess can scale or pad the image to, an image that is already larger can't grow
+pub const MAX_PREPROCESS_SIZE: u32 = 16384;
+
+// a rotation of the image, clockwise
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Rotation {
+    None,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+
+impl Rotation {
+    // get the rotation from a number of degrees, only a multiple of 90 is supported
+    pub fn from_degrees(degrees: i32) -> Option<Rotation> {
+        match degrees.rem_euclid(360) {
+            0 => return Some(Rotation::None),
+            90 => return Some(Rotation::Rotate90),
+            180 => return Some(Rotation::Rotate180),
+            270 => return Some(Rotation::Rotate270),
+            _ => return None,
+        }
+    }
+}
+
+// the filter used to scale the image, from the fastest to the smoothest
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ResizeFilter {
+    Nearest,
+    Triangle,
+    CatmullRom,
+    Gaussian,
+    Lanczos3,
+}
+
+impl ResizeFilter {
+    pub fn get_filter_type(&self) -> FilterType {
+        match self {
+            ResizeFilter::Nearest => return FilterType::Nearest,
+            ResizeFilter::Triangle => return FilterType::Triangle,
+            ResizeFilter::CatmullRom => return FilterType::CatmullRom,
+            ResizeFilter::Gaussian => return FilterType::Gaussian,
+            ResizeFilter::Lanczos3 => return FilterType::Lanczos3,
+        }
+    }
+}
+
+// the area of the image that is kept, in pixels of the image
+//
+// the rectangle is clamped to the image, so a rectangle larger than the image keep everything to its edge
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CropRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl CropRect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> CropRect {
+        return CropRect {
+            x,
+            y,
+            width,
+            height,
+        };
+    }
+}
+
+// the size the image is scaled to before it is converted
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum PreScale {
+    // multiply both sides, e.g. 0.5 halve the image
+    Factor(f32),
+    // the largest size that fit in the box while keeping the aspect ratio
+    Fit { width: u32, height: u32 },
+    // exactly this size, the image is stretched if the aspect ratio is different
+    Exact { width: u32, height: u32 },
+}
+
+// extend the image on both sides with a color until it has the aspect ratio, e.g. to letterbox a portrait photo to 16:9
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Padding {
+    // width divided by height
+    pub aspect_ratio: f32,
+    // rgba, a transparent padding is left empty like any transparent area of the image
+    pub color: [u8; 4],
+}
+
+impl Padding {
+    pub fn new(aspect_ratio: f32, color: [u8; 4]) -> Padding {
+        return Padding {
+            aspect_ratio,
+            color,
+        };
+    }
+}
+
+// change the image before it is sampled, in this order: crop, rotate, flip, scale and pad
+//
+// the EXIF orientation is applied when the file is decoded, so the crop rectangle is based on the image as it is displayed
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct Preprocess {
+    // rotate and flip a photo based on its EXIF orientation when it is opened
+    pub is_auto_orient: bool,
+    pub crop: Option<CropRect>,
+    pub rotation: Rotation,
+    pub is_flip_horizontal: bool,
+    pub is_flip_vertical: bool,
+    pub scale: Option<PreScale>,
+    pub resize_filter: ResizeFilter,
+    pub padding: Option<Padding>,
+}
+
+impl Preprocess {
+    pub fn new() -> Preprocess {
+        return Preprocess {
+            is_auto_orient: true,
+            crop: None,
+            rotation: Rotation::None,
+            is_flip_horizontal: false,
+            is_flip_vertical: false,
+            scale: None,
+            resize_filter: ResizeFilter::Triangle,
+            padding: None,
+        };
+    }
+
+    pub fn with_auto_orient(mut self, is_auto_orient: bool) -> Preprocess {
+        self.is_auto_orient = is_auto_orient;
+        return self;
+    }
+
+    pub fn with_crop(mut self, crop: CropRect) -> Preprocess {
+        self.crop = Some(crop);
+        return self;
+    }
+
+    pub fn with_rotation(mut self, rotation: Rotation) -> Preprocess {
+        self.rotation = rotation;
+        return self;
+    }
+
+    pub fn with_flip(mut self, is_flip_horizontal: bool, is_flip_vertical: bool) -> Preprocess {
+        self.is_flip_horizontal = is_flip_horizontal;
+        self.is_flip_vertical = is_flip_vertical;
+        return self;
+    }
+
+    pub fn with_scale(mut self, scale: PreScale, resize_filter: ResizeFilter) -> Preprocess {
+        self.scale = Some(scale);
+        self.resize_filter = resize_filter;
+        return self;
+    }
+
+    pub fn with_padding(mut self, padding: Padding) -> Preprocess {
+        self.padding = Some(padding);
+        return self;
+    }
+
+    // true if the pixels are left as they are, the EXIF orientation is not part of it as it is applied when decoding
+    pub fn is_identity(&self) -> bool {
+        return self.crop.is_none()
+            && self.rotation == Rotation::None
+            && !self.is_flip_horizontal
+            && !self.is_flip_vertical
+            && self.scale.is_none()
+            && self.padding.is_none();
+    }
+
+    // the size of an image of `width` x `height` after the preprocess,
+    // or an error if the scale or padding would make a side larger than `MAX_PREPROCESS_SIZE`
+    pub fn get_output_size(&self, width: u32, height: u32) -> Result<(u32, u32), String> {
+        let max_size = get_max_output_size(width, height);
+        let (mut width, mut height) = (width, height);
+        if let Some(crop) = self.crop {
+            let x = crop.x.min(width.saturating_sub(1));
+            let y = crop.y.min(height.saturating_sub(1));
+            width = crop.width.min(width - x).max(1);
+            height = crop.height.min(height - y).max(1);
+        }
+        if matches!(self.rotation, Rotation::Rotate90 | Rotation::Rotate270) {
+            (width, height) = (height, width);
+        }
+        if let Some(scale) = self.scale {
+            (width, height) = get_scaled_size(width, height, scale);
+        }
+        if let Some(padding) = self.padding {
+            (width, height) = get_padded_size(width, height, padding.aspect_ratio);
+        }
+
+        if width > max_size || height > max_size {
+            return Err(format!(
+                "the preprocessed image would be {}x{}, the scale and padding can't make a side larger than {}",
+                width, height, max_size
+            ));
+        }
+        return Ok((width, height));
+    }
+
+    // check if the preprocess can be used for conversion
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(crop) = self.crop {
+            if crop.width == 0 || crop.height == 0 {
+                return Err(format!(
+                    "crop width and height must be greater than 0, got {}x{}",
+                    crop.width, crop.height
+                ));
+            }
+        }
+        match self.scale {
+            Some(PreScale::Factor(factor)) if !factor.is_finite() || factor <= 0.0 => {
+                return Err(format!(
+                    "scale factor must be greater than 0, got {}",
+                    factor
+                ));
+            }
+            Some(PreScale::Fit { width, height }) | Some(PreScale::Exact { width, height })
+                if width == 0 || height == 0 =>
+            {
+                return Err(format!(
+                    "scale width and height must be greater than 0, got {}x{}",
+                    width, height
+                ));
+            }
+            _ => {}
+        }
+        if let Some(padding) = self.padding {
+            if !padding.aspect_ratio.is_finite() || padding.aspect_ratio <= 0.0 {
+                return Err(format!(
+                    "padding aspect ratio must be greater than 0, got {}",
+                    padding.aspect_ratio
+                ));
+            }
+        }
+        return Ok(());
+    }
+}
+
+impl Default for Preprocess {
+    fn default() -> Preprocess {
+        return Preprocess::new();
+    }
+}
+
+// crop, rotate, flip, scale and pad the image, the image is only copied if the preprocess change anything
+//
+// a side is clamped to `MAX_PREPROCESS_SIZE` here, a conversion that return a result check
+// `Preprocess::get_output_size()` first so a too large scale or padding is an error instead
+pub fn apply_preprocess(img: &DynamicImage, preprocess: Preprocess) -> Cow<'_, DynamicImage> {
+    if preprocess.is_identity() {
+        return Cow::Borrowed(img);
+    }
+
+    let max_size = get_max_output_size(img.width(), img.height());
+    let mut img = Cow::Borrowed(img);
+    if let Some(crop) = preprocess.crop {
+        let (width, height) = img.dimensions();
+        // at least one pixel is kept, so a rectangle outside of the image doesn't leave an empty image
+        let x = crop.x.min(width.saturating_sub(1));
+        let y = crop.y.min(height.saturating_sub(1));
+        let crop_width = crop.width.min(width - x).max(1);
+        let crop_height = crop.height.min(height - y).max(1);
+        img = Cow::Owned(img.crop_imm(x, y, crop_width, crop_height));
+    }
+
+    match preprocess.rotation {
+        Rotation::None => {}
+        Rotation::Rotate90 => img = Cow::Owned(img.rotate90()),
+        Rotation::Rotate180 => img = Cow::Owned(img.rotate180()),
+        Rotation::Rotate270 => img = Cow::Owned(img.rotate270()),
+    }
+    if preprocess.is_flip_horizontal {
+        img = Cow::Owned(img.fliph());
+    }
+    if preprocess.is_flip_vertical {
+        img = Cow::Owned(img.flipv());
+    }
+
+    if let Some(scale) = preprocess.scale {
+        let filter_type = preprocess.resize_filter.get_filter_type();
+        let (width, height) = img.dimensions();
+        img = Cow::Owned(match scale {
+            PreScale::Fit { width, height } => {
+                img.resize(width.min(max_size), height.min(max_size), filter_type)
+            }
+            _ => {
+                let (scaled_width, scaled_height) = get_scaled_size(width, height, scale);
+                img.resize_exact(
+                    scaled_width.min(max_size),
+                    scaled_height.min(max_size),
+                    filter_type,
+                )
+            }
+        });
+    }
+
+    if let Some(padding) = preprocess.padding {
+        img = Cow::Owned(pad_to_aspect_ratio(&img, padding, max_size));
+    }
+    return img;
+}
+
+// the largest side the preprocess can make for an image of `width` x `height`
+fn get_max_output_size(width: u32, height: u32) -> u32 {
+    return MAX_PREPROCESS_SIZE.max(width).max(height);
+}
+
+// the size the image is scaled to, a side is at least 1 pixel
+fn get_scaled_size(width: u32, height: u32, scale: PreScale) -> (u32, u32) {
+    match scale {
+        PreScale::Factor(factor) => {
+            // the cast saturate, so a huge factor is still larger than the maximum
+            let scaled_width = ((width as f32 * factor).round() as u32).max(1);
+            let scaled_height = ((height as f32 * factor).round() as u32).max(1);
+            return (scaled_width, scaled_height);
+        }
+        PreScale::Fit {
+            width: fit_width,
+            height: fit_height,
+        } => {
+            // the same size as `DynamicImage::resize()`
+            let ratio = (fit_width as f64 / width as f64).min(fit_height as f64 / height as f64);
+            let scaled_width = ((width as f64 * ratio).round() as u32).max(1);
+            let scaled_height = ((height as f64 * ratio).round() as u32).max(1);
+            return (scaled_width, scaled_height);
+        }
+        PreScale::Exact { width, height } => return (width, height),
+    }
+}
+
+// the size of the image extended to the aspect ratio, it is never smaller than the image
+fn get_padded_size(width: u32, height: u32, aspect_ratio: f32) -> (u32, u32) {
+    let (padded_width, padded_height) = if (width as f32 / height as f32) < aspect_ratio {
+        ((height as f32 * aspect_ratio).round() as u32, height)
+    } else {
+        (width, (width as f32 / aspect_ratio).round() as u32)
+    };
+    return (padded_width.max(width), padded_height.max(height));
+}
+
+// center the image on a background of the padding color with the aspect ratio of the padding
+fn pad_to_aspect_ratio(img: &DynamicImage, padding: Padding, max_size: u32) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    let (padded_width, padded_height) = get_padded_size(width, height, padding.aspect_ratio);
+    let padded_width = padded_width.min(max_size).max(width);
+    let padded_height = padded_height.min(max_size).max(height);
+    if (padded_width, padded_height) == (width, height) {
+        return img.clone();
+    }
+
+    let mut padded_img = RgbaImage::from_pixel(padded_width, padded_height, Rgba(padding.color));
+    overlay(
+        &mut padded_img,
+        &img.to_rgba8(),
+        ((padded_width - width) / 2) as i64,
+        ((padded_height - height) / 2) as i64,
+    );
+    return DynamicImage::ImageRgba8(padded_img);
+}
+
+// Opens an image file, rotated and flipped based on its EXIF orientation if `is_auto_orient` is true.
+pub fn open_image(path: &str, is_auto_orient: bool) -> ImageResult<DynamicImage> {
+    return decode_image_reader(ImageReader::open(path)?, is_auto_orient);
+}
+
+// Decodes an encoded image (PNG, JPEG, ...), rotated and flipped based on its EXIF orientation if `is_auto_orient` is true.
+pub fn load_image_from_memory(data: &[u8], is_auto_orient: bool) -> ImageResult<DynamicImage> {
+    let image_reader = ImageReader::new(Cursor::new(data)).with_guessed_format()?;
+    return decode_image_reader(image_reader, is_auto_orient);
+}
+
+// decode the image of a reader, its format and limits must already be set
+pub fn decode_image_reader<R: BufRead + Seek>(
+    image_reader: ImageReader<R>,
+    is_auto_orient: bool,
+) -> ImageResult<DynamicImage> {
+    let mut decoder = image_reader.into_decoder()?;
+    // an image without EXIF data or with a broken one is left as it is
+    let orientation = if is_auto_orient {
+        decoder.orientation().unwrap_or(Orientation::NoTransforms)
+    } else {
+        Orientation::NoTransforms
+    };
+    let mut img = DynamicImage::from_decoder(decoder)?;
+    img.apply_orientation(orientation);
+    return Ok(img);
+}
+
+// the size of an image file without decoding its pixels, swapped if its EXIF orientation turn it sideways
+pub fn get_image_dimensions(path: &str, is_auto_orient: bool) -> ImageResult<(u32, u32)> {
+    let mut decoder = ImageReader::open(path)?.into_decoder()?;
+    let (width, height) = decoder.dimensions();
+    if !is_auto_orient {
+        return Ok((width, height));
+    }
+    match decoder.orientation().unwrap_or(Orientation::NoTransforms) {
+        Orientation::Rotate90
+        | Orientation::Rotate270
+        | Orientation::Rotate90FlipH
+        | Orientation::Rotate270FlipH => return Ok((height, width)),
+        _ => return Ok((width, height)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_output_size_of_each_step() {
+        let preprocess = Preprocess::new()
+            .with_crop(CropRect::new(100, 0, 400, 300))
+            .with_rotation(Rotation::Rotate90);
+        assert_eq!(preprocess.get_output_size(1000, 500), Ok((300, 400)));
+
+        let preprocess = Preprocess::new().with_scale(
+            PreScale::Fit {
+                width: 200,
+                height: 200,
+            },
+            ResizeFilter::Triangle,
+        );
+        assert_eq!(preprocess.get_output_size(1000, 500), Ok((200, 100)));
+
+        let preprocess = Preprocess::new().with_padding(Padding::new(2.0, [0, 0, 0, 255]));
+        assert_eq!(preprocess.get_output_size(100, 100), Ok((200, 100)));
+    }
+
+    #[test]
+    fn reject_too_large_output_size() {
+        let preprocess =
+            Preprocess::new().with_scale(PreScale::Factor(1000.0), ResizeFilter::Nearest);
+        assert!(preprocess.get_output_size(100, 100).is_err());
+
+        let preprocess = Preprocess::new().with_scale(
+            PreScale::Fit {
+                width: u32::MAX,
+                height: u32::MAX,
+            },
+            ResizeFilter::Nearest,
+        );
+        assert!(preprocess.get_output_size(100, 100).is_err());
+
+        let preprocess = Preprocess::new().with_padding(Padding::new(1000.0, [0, 0, 0, 255]));
+        assert!(preprocess.get_output_size(100, 100).is_err());
+
+        // an image that is already larger than the maximum is only limited by its own size
+        let preprocess = Preprocess::new().with_scale(PreScale::Factor(0.5), ResizeFilter::Nearest);
+        assert_eq!(preprocess.get_output_size(40000, 100), Ok((20000, 50)));
+    }
+
+    #[test]
+    fn clamp_applied_output_size() {
+        let img = DynamicImage::new_rgba8(4, 4);
+        let preprocess = Preprocess::new().with_padding(Padding::new(100000.0, [0, 0, 0, 0]));
+        let padded_img = apply_preprocess(&img, preprocess);
+        assert_eq!(padded_img.dimensions(), (MAX_PREPROCESS_SIZE, 4));
+    }
+}
@@ -0,0 +1,231 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use crate::config::RenderConfig;
+
+// the format of a preset file
+//
+// a preset file is a list of named RenderConfig, every option not set keep its default, e.g. in TOML
+//
+// [retro]
+// character_type = "bar"
+// filename = "retro_ascii"
+//
+// [retro.setting_option]
+// num_cols = 120
+// is_color = true
+// palette = "gameboy"
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum PresetFormat {
+    Toml,
+    Json,
+}
+
+impl PresetFormat {
+    // get the format based on the extension of the file
+    pub fn from_path(path: &str) -> Result<PresetFormat, String> {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_lowercase());
+
+        match extension.as_deref() {
+            Some("toml") => return Ok(PresetFormat::Toml),
+            Some("json") => return Ok(PresetFormat::Json),
+            _ => {
+                return Err(format!(
+                    "Unsupported preset file: {}, use a .toml or .json file",
+                    path
+                ))
+            }
+        }
+    }
+}
+
+// parse every preset of a preset file content, every preset is validated
+pub fn parse_preset_list(
+    content: &str,
+    preset_format: PresetFormat,
+) -> Result<BTreeMap<String, RenderConfig>, String> {
+    let preset_list: BTreeMap<String, RenderConfig> = match preset_format {
+        PresetFormat::Toml => {
+            toml::from_str(content).map_err(|e| format!("Failed to parse preset: {}", e))?
+        }
+        PresetFormat::Json => {
+            serde_json::from_str(content).map_err(|e| format!("Failed to parse preset: {}", e))?
+        }
+    };
+
+    for (name, render_config) in &preset_list {
+        render_config
+            .validate()
+            .map_err(|e| format!("Invalid preset {}: {}", name, e))?;
+    }
+
+    return Ok(preset_list);
+}
+
+// load every preset of a .toml or .json preset file
+pub fn load_preset_list(path: &str) -> Result<BTreeMap<String, RenderConfig>, String> {
+    let preset_format = PresetFormat::from_path(path)?;
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read preset file: {}", e))?;
+
+    return parse_preset_list(&content, preset_format);
+}
+
+// load a single preset by its name from a .toml or .json preset file
+pub fn load_preset(path: &str, name: &str) -> Result<RenderConfig, String> {
+    let mut preset_list = load_preset_list(path)?;
+
+    return preset_list
+        .remove(name)
+        .ok_or_else(|| format!("Preset {} was not found in {}", name, path));
+}
+
+// write the presets to a .toml or .json preset file, the path of the file is returned
+pub fn save_preset_list(
+    path: &str,
+    preset_list: &BTreeMap<String, RenderConfig>,
+) -> Result<String, String> {
+    let content = match PresetFormat::from_path(path)? {
+        PresetFormat::Toml => toml::to_string(preset_list)
+            .map_err(|e| format!("Failed to serialize preset: {}", e))?,
+        PresetFormat::Json => serde_json::to_string_pretty(preset_list)
+            .map_err(|e| format!("Failed to serialize preset: {}", e))?,
+    };
+    fs::write(path, content).map_err(|e| format!("Failed to write preset file: {}", e))?;
+
+    return Ok(path.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        character::CharacterType,
+        config::DEFAULT_NUM_COLS,
+        palette::{Palette, PalettePreset},
+        types::ColorTheme,
+    };
+
+    const TOML_PRESET: &str = r#"
+[retro]
+character_type = "bar"
+filename = "retro_ascii"
+
+[retro.setting_option]
+num_cols = 120
+is_color = true
+palette = "gameboy"
+cell_background = { darken = 0.3 }
+
+[mono.setting_option]
+color_theme = "amber"
+tone = { gamma = 1.2 }
+"#;
+
+    #[test]
+    fn preset_format_from_path() {
+        assert_eq!(
+            PresetFormat::from_path("preset.toml").unwrap(),
+            PresetFormat::Toml
+        );
+        assert_eq!(
+            PresetFormat::from_path("preset.JSON").unwrap(),
+            PresetFormat::Json
+        );
+        assert!(PresetFormat::from_path("preset.yaml").is_err());
+        assert!(PresetFormat::from_path("preset").is_err());
+    }
+
+    #[test]
+    fn parse_toml_preset() {
+        let preset_list = parse_preset_list(TOML_PRESET, PresetFormat::Toml).unwrap();
+        assert_eq!(preset_list.len(), 2);
+
+        let retro = &preset_list["retro"];
+        assert_eq!(retro.character_type, CharacterType::Bar);
+        assert_eq!(retro.filename.as_deref(), Some("retro_ascii"));
+        assert_eq!(retro.setting_option.num_cols, 120);
+        assert!(retro.setting_option.is_color);
+        assert_eq!(
+            retro.setting_option.palette.as_deref(),
+            Some(&Palette::from_preset(PalettePreset::GameBoy))
+        );
+        assert_eq!(
+            retro.setting_option.cell_background.map(|c| c.darken),
+            Some(0.3)
+        );
+
+        // every option not set keep its default
+        let mono = &preset_list["mono"];
+        assert_eq!(mono.character_type, CharacterType::Simple);
+        assert_eq!(mono.setting_option.num_cols, DEFAULT_NUM_COLS);
+        assert_eq!(
+            mono.setting_option.color_theme,
+            ColorTheme::from_name("amber")
+        );
+        assert_eq!(mono.setting_option.tone.gamma, 1.2);
+        assert_eq!(mono.setting_option.tone.brightness, 0.0);
+    }
+
+    #[test]
+    fn parse_invalid_preset() {
+        // the preset is parsed but is not valid
+        let error = parse_preset_list("[broken.setting_option]\nnum_cols = 0", PresetFormat::Toml)
+            .unwrap_err();
+        assert!(error.starts_with("Invalid preset broken"));
+
+        assert!(parse_preset_list("{\"broken\": 1}", PresetFormat::Json).is_err());
+        assert!(parse_preset_list(
+            "[broken.setting_option]\npalette = \"unknown\"",
+            PresetFormat::Toml
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn save_and_load_preset() {
+        let mut preset_list = BTreeMap::new();
+        preset_list.insert(
+            "custom".to_string(),
+            RenderConfig::builder()
+                .character_type(CharacterType::Complex)
+                .num_cols(80)
+                .color(true)
+                .palette(Palette::new(&[[0, 0, 0], [255, 255, 255]]).unwrap())
+                .contrast(0.5)
+                .filename("custom_ascii")
+                .build()
+                .unwrap(),
+        );
+
+        let directory =
+            std::env::temp_dir().join(format!("rasciify_preset_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        for extension in ["toml", "json"] {
+            let path = directory.join(format!("preset.{}", extension));
+            let path = path.to_str().unwrap();
+            save_preset_list(path, &preset_list).unwrap();
+
+            let render_config = load_preset(path, "custom").unwrap();
+            assert_eq!(render_config.character_type, CharacterType::Complex);
+            assert_eq!(render_config.filename.as_deref(), Some("custom_ascii"));
+            assert_eq!(render_config.setting_option.num_cols, 80);
+            assert!(render_config.setting_option.is_color);
+            assert_eq!(render_config.setting_option.tone.contrast, 0.5);
+            assert_eq!(
+                render_config
+                    .setting_option
+                    .palette
+                    .unwrap()
+                    .get_color_list()
+                    .len(),
+                2
+            );
+
+            assert!(load_preset(path, "missing").is_err());
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,127 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+// the error returned by a conversion that was stopped through its CancellationToken
+pub const CANCELLED_ERROR: &str = "Conversion was cancelled";
+
+// how far a GIF conversion got, every count is the number of frames done so far
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct GifProgress {
+    // number of frames of the output with the frame selection, `None` if it is not known before decoding,
+    // which is where `converted_frames` and `encoded_frames` end
+    pub total_frames: Option<usize>,
+    // frames decoded from the source GIF, including the ones left out by the frame selection
+    pub decoded_frames: usize,
+    // frames converted to ASCII art, a frame kept for several delays is counted once for every delay
+    pub converted_frames: usize,
+    // frames written to the output GIF
+    pub encoded_frames: usize,
+}
+
+// get notified while a GIF is being converted,
+// it is called from the rayon worker threads so it need to be Sync,
+// the calls are made one at a time so the counts never go back
+//
+// any `Fn(GifProgress) + Sync` closure is a ProgressObserver
+pub trait ProgressObserver: Sync {
+    fn on_progress(&self, progress: GifProgress);
+}
+
+impl<F> ProgressObserver for F
+where
+    F: Fn(GifProgress) + Sync,
+{
+    fn on_progress(&self, progress: GifProgress) {
+        self(progress);
+    }
+}
+
+// stop a running conversion from another thread, e.g. the cancel button of a GUI
+//
+// the token can be cloned and every clone cancel the same conversion,
+// the conversion stop at the next frame and return `Err(CANCELLED_ERROR)`
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    is_cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        return CancellationToken::default();
+    }
+
+    pub fn cancel(&self) {
+        self.is_cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        return self.is_cancelled.load(Ordering::SeqCst);
+    }
+}
+
+// the optional observer and cancellation token of a conversion
+#[derive(Clone, Copy, Default)]
+pub struct ProgressOption<'a> {
+    pub observer: Option<&'a dyn ProgressObserver>,
+    pub cancellation_token: Option<&'a CancellationToken>,
+    // number of frames of the output, reported as `GifProgress::total_frames`
+    pub total_frames: Option<usize>,
+}
+
+impl<'a> ProgressOption<'a> {
+    pub fn new() -> ProgressOption<'a> {
+        return ProgressOption::default();
+    }
+
+    pub fn with_observer(mut self, observer: &'a dyn ProgressObserver) -> ProgressOption<'a> {
+        self.observer = Some(observer);
+        return self;
+    }
+
+    pub fn with_cancellation_token(
+        mut self,
+        cancellation_token: &'a CancellationToken,
+    ) -> ProgressOption<'a> {
+        self.cancellation_token = Some(cancellation_token);
+        return self;
+    }
+
+    pub fn with_total_frames(mut self, total_frames: usize) -> ProgressOption<'a> {
+        self.total_frames = Some(total_frames);
+        return self;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancel_from_a_clone() {
+        let cancellation_token = CancellationToken::new();
+        let clone = cancellation_token.clone();
+        assert!(!cancellation_token.is_cancelled());
+
+        clone.cancel();
+        assert!(cancellation_token.is_cancelled());
+    }
+
+    #[test]
+    fn closure_is_an_observer() {
+        let last_progress = std::sync::Mutex::new(GifProgress::default());
+        let observer = |progress| *last_progress.lock().unwrap() = progress;
+        let progress_option = ProgressOption::new()
+            .with_observer(&observer)
+            .with_total_frames(3);
+
+        let progress = GifProgress {
+            total_frames: progress_option.total_frames,
+            encoded_frames: 1,
+            ..GifProgress::default()
+        };
+        progress_option.observer.unwrap().on_progress(progress);
+        assert_eq!(*last_progress.lock().unwrap(), progress);
+    }
+}
@@ -0,0 +1,353 @@
+// the Python bindings, built into a wheel with `maturin build --release` (see pyproject.toml)
+//
+// an image can be given as the bytes of an encoded image (PNG, JPEG, ...) or as a NumPy uint8 array
+// of shape (height, width), (height, width, 3) or (height, width, 4), the result is of the same kind
+use std::{io::Cursor, sync::Arc};
+
+use gif::DecodeOptions;
+use image::{DynamicImage, GrayImage, ImageFormat, RgbImage, RgbaImage};
+use numpy::{ndarray::Array, IntoPyArray, PyReadonlyArrayDyn};
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyBytes, PyModule},
+};
+
+use crate::{
+    config::DEFAULT_NUM_COLS,
+    decode_gif, dynamic_image_to_ascii_img, grayscale_to_ascii,
+    preprocess::{load_image_from_memory, Preprocess},
+    stream_frames_to_ascii_gray_gif, stream_frames_to_ascii_rgb_gif, CharacterType, ColorTheme,
+    Palette, PalettePreset, SettingOption, DEFAULT_FRAME_WINDOW,
+};
+
+// the SettingOption of the Python API, the color theme and palette are set by name
+#[pyclass(name = "SettingOption")]
+#[derive(Clone)]
+pub struct PySettingOption {
+    setting_option: SettingOption,
+}
+
+#[pymethods]
+impl PySettingOption {
+    #[new]
+    #[pyo3(signature = (
+        num_cols = DEFAULT_NUM_COLS,
+        is_color = false,
+        is_white_bg = false,
+        is_transparent_bg = false,
+        color_theme = None,
+        palette = None,
+        frame_window = DEFAULT_FRAME_WINDOW,
+    ))]
+    fn new(
+        num_cols: u32,
+        is_color: bool,
+        is_white_bg: bool,
+        is_transparent_bg: bool,
+        color_theme: Option<&str>,
+        palette: Option<&str>,
+        frame_window: usize,
+    ) -> PyResult<PySettingOption> {
+        let mut setting_option = if is_color {
+            SettingOption::rgb(num_cols)
+        } else {
+            SettingOption::grayscale(num_cols)
+        };
+        setting_option.is_white_bg = is_white_bg;
+        setting_option.is_transparent_bg = is_transparent_bg;
+        setting_option.frame_window = frame_window;
+        if let Some(name) = color_theme {
+            setting_option.color_theme = Some(ColorTheme::from_name(name).ok_or(
+                PyValueError::new_err(format!("Unknown color theme: {}", name)),
+            )?);
+        }
+        if let Some(name) = palette {
+            let palette_preset = PalettePreset::from_name(name)
+                .ok_or(PyValueError::new_err(format!("Unknown palette: {}", name)))?;
+            setting_option.palette = Some(Arc::new(Palette::from_preset(palette_preset)));
+        }
+        return Ok(PySettingOption { setting_option });
+    }
+
+    #[staticmethod]
+    fn rgb(num_cols: u32) -> PySettingOption {
+        return PySettingOption {
+            setting_option: SettingOption::rgb(num_cols),
+        };
+    }
+
+    #[staticmethod]
+    fn grayscale(num_cols: u32) -> PySettingOption {
+        return PySettingOption {
+            setting_option: SettingOption::grayscale(num_cols),
+        };
+    }
+
+    #[getter]
+    fn get_num_cols(&self) -> u32 {
+        return self.setting_option.num_cols;
+    }
+
+    #[setter]
+    fn set_num_cols(&mut self, num_cols: u32) {
+        self.setting_option.num_cols = num_cols;
+    }
+
+    #[getter]
+    fn get_is_color(&self) -> bool {
+        return self.setting_option.is_color;
+    }
+
+    #[setter]
+    fn set_is_color(&mut self, is_color: bool) {
+        self.setting_option.is_color = is_color;
+    }
+
+    #[getter]
+    fn get_is_white_bg(&self) -> bool {
+        return self.setting_option.is_white_bg;
+    }
+
+    #[setter]
+    fn set_is_white_bg(&mut self, is_white_bg: bool) {
+        self.setting_option.is_white_bg = is_white_bg;
+    }
+
+    #[getter]
+    fn get_is_transparent_bg(&self) -> bool {
+        return self.setting_option.is_transparent_bg;
+    }
+
+    #[setter]
+    fn set_is_transparent_bg(&mut self, is_transparent_bg: bool) {
+        self.setting_option.is_transparent_bg = is_transparent_bg;
+    }
+
+    #[getter]
+    fn get_frame_window(&self) -> usize {
+        return self.setting_option.frame_window;
+    }
+
+    #[setter]
+    fn set_frame_window(&mut self, frame_window: usize) {
+        self.setting_option.frame_window = frame_window;
+    }
+
+    fn __repr__(&self) -> String {
+        let setting_option = self.setting_option.clone();
+        return format!(
+            "SettingOption(num_cols={}, is_color={}, is_white_bg={}, is_transparent_bg={}, frame_window={})",
+            setting_option.num_cols,
+            python_bool(setting_option.is_color),
+            python_bool(setting_option.is_white_bg),
+            python_bool(setting_option.is_transparent_bg),
+            setting_option.frame_window
+        );
+    }
+}
+
+// an image given to the Python API, the result is returned in the same kind
+enum PyImageInput {
+    Bytes(DynamicImage),
+    Array(DynamicImage),
+}
+
+// Converts an image to ASCII art text.
+#[pyfunction]
+#[pyo3(name = "image_to_text", signature = (image, num_cols = DEFAULT_NUM_COLS, complex = false))]
+fn py_image_to_text(
+    py: Python,
+    image: &Bound<PyAny>,
+    num_cols: u32,
+    complex: bool,
+) -> PyResult<String> {
+    let img = match extract_image(image, Preprocess::new())? {
+        PyImageInput::Bytes(img) | PyImageInput::Array(img) => img,
+    };
+    return Ok(py.detach(|| grayscale_to_ascii(&img, num_cols, complex)));
+}
+
+// Converts an image to an ASCII art image, as PNG bytes for bytes or as a NumPy array for an array.
+//
+// the array is (height, width, 4) for an rgb ascii art, (height, width, 3) with a color theme
+// and (height, width) for a grayscale ascii art
+#[pyfunction]
+#[pyo3(name = "image_to_image", signature = (image, character_type = CharacterType::Simple, setting_option = None))]
+fn py_image_to_image(
+    py: Python,
+    image: &Bound<PyAny>,
+    character_type: CharacterType,
+    setting_option: Option<PySettingOption>,
+) -> PyResult<Py<PyAny>> {
+    let setting_option = get_setting_option(setting_option);
+    let input = extract_image(image, setting_option.preprocess)?;
+    let img = match &input {
+        PyImageInput::Bytes(img) | PyImageInput::Array(img) => img,
+    };
+
+    let ascii_img = py.detach(|| dynamic_image_to_ascii_img(img, character_type, setting_option));
+
+    match input {
+        PyImageInput::Bytes(_) => {
+            let mut png_buffer = Cursor::new(Vec::new());
+            ascii_img
+                .write_to(&mut png_buffer, ImageFormat::Png)
+                .map_err(|e| PyValueError::new_err(format!("Failed to encode image: {}", e)))?;
+            return Ok(PyBytes::new(py, png_buffer.get_ref()).into_any().unbind());
+        }
+        PyImageInput::Array(_) => return dynamic_image_to_array(py, ascii_img),
+    }
+}
+
+// Converts the bytes of a GIF to the bytes of an ASCII art GIF, the GIL is released while the frames are converted.
+#[pyfunction]
+#[pyo3(name = "gif_to_gif", signature = (data, character_type = CharacterType::Simple, setting_option = None))]
+fn py_gif_to_gif(
+    py: Python,
+    data: &[u8],
+    character_type: CharacterType,
+    setting_option: Option<PySettingOption>,
+) -> PyResult<Py<PyBytes>> {
+    let setting_option = get_setting_option(setting_option);
+    let gif_buffer = py
+        .detach(|| {
+            let decoder = decode_gif(Cursor::new(data), DecodeOptions::new())?;
+            if setting_option.is_color {
+                return stream_frames_to_ascii_rgb_gif(
+                    decoder,
+                    Vec::new(),
+                    character_type,
+                    setting_option,
+                );
+            }
+            return stream_frames_to_ascii_gray_gif(
+                decoder,
+                Vec::new(),
+                character_type,
+                setting_option,
+            );
+        })
+        .map_err(PyValueError::new_err)?;
+    return Ok(PyBytes::new(py, &gif_buffer).unbind());
+}
+
+#[pymodule]
+#[pyo3(name = "rasciify")]
+fn rasciify_python_module(module: &Bound<PyModule>) -> PyResult<()> {
+    module.add_class::<CharacterType>()?;
+    module.add_class::<PySettingOption>()?;
+    module.add_function(wrap_pyfunction!(py_image_to_text, module)?)?;
+    module.add_function(wrap_pyfunction!(py_image_to_image, module)?)?;
+    module.add_function(wrap_pyfunction!(py_gif_to_gif, module)?)?;
+    return Ok(());
+}
+
+fn python_bool(value: bool) -> &'static str {
+    return if value { "True" } else { "False" };
+}
+
+fn get_setting_option(setting_option: Option<PySettingOption>) -> SettingOption {
+    return setting_option.map_or(
+        SettingOption::grayscale(DEFAULT_NUM_COLS),
+        |setting_option| setting_option.setting_option,
+    );
+}
+
+// decode the bytes or read the array, and check that the preprocess doesn't make the image too large
+fn extract_image(image: &Bound<PyAny>, preprocess: Preprocess) -> PyResult<PyImageInput> {
+    let input = read_image_input(image, preprocess.is_auto_orient)?;
+    let img = match &input {
+        PyImageInput::Bytes(img) | PyImageInput::Array(img) => img,
+    };
+    preprocess
+        .get_output_size(img.width(), img.height())
+        .map_err(PyValueError::new_err)?;
+    return Ok(input);
+}
+
+fn read_image_input(image: &Bound<PyAny>, is_auto_orient: bool) -> PyResult<PyImageInput> {
+    if let Ok(bytes) = image.cast::<PyBytes>() {
+        let img = load_image_from_memory(bytes.as_bytes(), is_auto_orient)
+            .map_err(|e| PyValueError::new_err(format!("Failed to decode image: {}", e)))?;
+        return Ok(PyImageInput::Bytes(img));
+    }
+
+    let array: PyReadonlyArrayDyn<u8> = image.extract().map_err(|_| {
+        PyValueError::new_err("The image must be bytes or a NumPy uint8 array".to_string())
+    })?;
+    let array = array.as_array();
+    let shape = array.shape();
+    // the pixels are collected in logical order, so a non contiguous array (e.g. a slice) works too
+    let pixel_list: Vec<u8> = array.iter().copied().collect();
+    let invalid_shape_error =
+        || PyValueError::new_err(format!("Unsupported image array shape: {:?}", shape));
+    let (height, width) = match shape {
+        [height, width] | [height, width, _] => (*height as u32, *width as u32),
+        _ => return Err(invalid_shape_error()),
+    };
+
+    let img = match shape.get(2) {
+        None | Some(1) => {
+            GrayImage::from_raw(width, height, pixel_list).map(DynamicImage::ImageLuma8)
+        }
+        Some(3) => RgbImage::from_raw(width, height, pixel_list).map(DynamicImage::ImageRgb8),
+        Some(4) => RgbaImage::from_raw(width, height, pixel_list).map(DynamicImage::ImageRgba8),
+        _ => None,
+    };
+    return Ok(PyImageInput::Array(img.ok_or_else(invalid_shape_error)?));
+}
+
+fn dynamic_image_to_array(py: Python, img: DynamicImage) -> PyResult<Py<PyAny>> {
+    let height = img.height() as usize;
+    let width = img.width() as usize;
+    let array_error = |e: numpy::ndarray::ShapeError| {
+        PyValueError::new_err(format!("Failed to create the image array: {}", e))
+    };
+    match img {
+        DynamicImage::ImageLuma8(gray_img) => {
+            let array =
+                Array::from_shape_vec((height, width), gray_img.into_raw()).map_err(array_error)?;
+            return Ok(array.into_pyarray(py).into_any().unbind());
+        }
+        DynamicImage::ImageRgb8(rgb_img) => {
+            let array = Array::from_shape_vec((height, width, 3), rgb_img.into_raw())
+                .map_err(array_error)?;
+            return Ok(array.into_pyarray(py).into_any().unbind());
+        }
+        img => {
+            let array = Array::from_shape_vec((height, width, 4), img.into_rgba8().into_raw())
+                .map_err(array_error)?;
+            return Ok(array.into_pyarray(py).into_any().unbind());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn setting_option_by_name() {
+        let py_setting_option =
+            PySettingOption::new(80, true, false, true, None, Some("gameboy"), 4).unwrap();
+        let setting_option = get_setting_option(Some(py_setting_option.clone()));
+        assert_eq!(setting_option.num_cols, 80);
+        assert!(setting_option.is_color);
+        assert!(setting_option.palette.is_some());
+        assert_eq!(
+            py_setting_option.__repr__(),
+            "SettingOption(num_cols=80, is_color=True, is_white_bg=False, is_transparent_bg=True, frame_window=4)"
+        );
+
+        assert!(PySettingOption::new(80, false, false, false, Some("unknown"), None, 4).is_err());
+        assert!(PySettingOption::new(80, false, false, false, None, Some("unknown"), 4).is_err());
+    }
+
+    #[test]
+    fn default_setting_option() {
+        let setting_option = get_setting_option(None);
+        assert_eq!(setting_option.num_cols, DEFAULT_NUM_COLS);
+        assert!(!setting_option.is_color);
+    }
+}
@@ -0,0 +1,249 @@
+use std::cell::RefCell;
+
+use image::{DynamicImage, GenericImageView, Pixel, Rgba};
+use ratatui::{buffer::Buffer, layout::Rect, style::Color, widgets::Widget};
+
+use crate::{
+    ascii_grid::image_to_ascii_grid,
+    character::CharacterType,
+    config::DEFAULT_NUM_COLS,
+    preprocess::{apply_preprocess, Preprocess},
+    terminal::{fit_num_cols, AnsiColorEncoder, ColorSupport},
+    SettingOption,
+};
+
+// show the ascii art of an image in a ratatui app
+//
+// the art is sized to fit the area it is rendered in and centered there, the converted grid is kept
+// and only converted again when the size of the area change or the image is replaced with `set_image`
+//
+// let widget = AsciiArtWidget::new(img, CharacterType::Simple);
+// frame.render_widget(&widget, area);
+pub struct AsciiArtWidget {
+    img: DynamicImage,
+    character_type: CharacterType,
+    // `num_cols` is not used, the number of columns is picked from the size of the area
+    setting_option: SettingOption,
+    color_support: ColorSupport,
+    cache: RefCell<Option<AsciiArtCache>>,
+}
+
+// the cells converted for an area of `width` x `height` cells, with their colors already mapped
+struct AsciiArtCache {
+    width: u16,
+    height: u16,
+    num_cols: u16,
+    num_rows: u16,
+    cell_list: Vec<AsciiArtCell>,
+}
+
+// the character, foreground and background of a cell, a `None` color leave the buffer color as it is
+type AsciiArtCell = (char, Option<Color>, Option<Color>);
+
+impl AsciiArtWidget {
+    // the art is in the colors of the image, in the colors the terminal support
+    pub fn new(img: DynamicImage, character_type: CharacterType) -> AsciiArtWidget {
+        return AsciiArtWidget {
+            img,
+            character_type,
+            setting_option: SettingOption::rgb(DEFAULT_NUM_COLS),
+            color_support: ColorSupport::from_env(),
+            cache: RefCell::new(None),
+        };
+    }
+
+    pub fn with_setting_option(mut self, setting_option: SettingOption) -> AsciiArtWidget {
+        self.setting_option = setting_option;
+        self.cache = RefCell::new(None);
+        return self;
+    }
+
+    pub fn with_color_support(mut self, color_support: ColorSupport) -> AsciiArtWidget {
+        self.color_support = color_support;
+        self.cache = RefCell::new(None);
+        return self;
+    }
+
+    pub fn get_image(&self) -> &DynamicImage {
+        return &self.img;
+    }
+
+    // replace the image, it is converted on the next render
+    pub fn set_image(&mut self, img: DynamicImage) {
+        self.img = img;
+        self.cache = RefCell::new(None);
+    }
+
+    fn render_ascii_grid(&self, area: Rect, buf: &mut Buffer) {
+        let mut cache = self.cache.borrow_mut();
+        let is_outdated = cache
+            .as_ref()
+            .is_none_or(|cache| cache.width != area.width || cache.height != area.height);
+        if is_outdated {
+            *cache = Some(self.convert(area.width, area.height));
+        }
+        let cache = cache.as_ref().unwrap();
+
+        let left = area.x + (area.width - cache.num_cols) / 2;
+        let top = area.y + (area.height - cache.num_rows) / 2;
+        for row in 0..cache.num_rows {
+            for col in 0..cache.num_cols {
+                // the index is computed in usize, a u16 product would overflow on a large area
+                let index = row as usize * cache.num_cols as usize + col as usize;
+                let (character, foreground, background) = cache.cell_list[index];
+                let buffer_cell = match buf.cell_mut((left + col, top + row)) {
+                    Some(buffer_cell) => buffer_cell,
+                    None => continue,
+                };
+                buffer_cell.set_char(character);
+                if let Some(foreground) = foreground {
+                    buffer_cell.set_fg(foreground);
+                }
+                if let Some(background) = background {
+                    buffer_cell.set_bg(background);
+                }
+            }
+        }
+    }
+
+    // convert the image with the largest number of columns that fit the area
+    fn convert(&self, width: u16, height: u16) -> AsciiArtCache {
+        // a crop or padding change the aspect ratio, so the columns are fitted to the preprocessed image
+        let img = apply_preprocess(&self.img, self.setting_option.preprocess);
+        let (img_width, img_height) = img.dimensions();
+        let mut setting_option = self.setting_option.clone();
+        setting_option.preprocess = Preprocess::new();
+        setting_option.num_cols =
+            fit_num_cols(img_width, img_height, width as u32, Some(height as u32));
+        let ascii_grid = image_to_ascii_grid(&img, self.character_type, &setting_option);
+
+        let is_colored = (setting_option.is_color || setting_option.color_theme.is_some())
+            && self.color_support != ColorSupport::None;
+        let mut color_encoder = AnsiColorEncoder::new(self.color_support);
+        let mut get_color = |rgba: Rgba<u8>| -> Option<Color> {
+            if !is_colored {
+                return None;
+            }
+            let rgb = rgba.to_rgb().0;
+            return match color_encoder.get_color_index(rgb) {
+                Some(index) => Some(Color::Indexed(index)),
+                None => Some(Color::Rgb(rgb[0], rgb[1], rgb[2])),
+            };
+        };
+        // a transparent grid background leave the background of the buffer as it is
+        let grid_background = if ascii_grid.background[3] == 0 {
+            None
+        } else {
+            get_color(ascii_grid.background)
+        };
+
+        // the grid is clipped to the area in case the sampling fell back to another cell size
+        let num_cols = ascii_grid.num_cols.min(width as u32);
+        let num_rows = ascii_grid.num_rows.min(height as u32);
+        let mut cell_list = Vec::with_capacity(num_cols as usize * num_rows as usize);
+        for row in 0..num_rows {
+            for ascii_cell in &ascii_grid.get_row(row)[..num_cols as usize] {
+                let cell = match ascii_cell {
+                    Some(ascii_cell) => (
+                        ascii_cell.character,
+                        get_color(ascii_cell.foreground),
+                        ascii_cell
+                            .background
+                            .and_then(&mut get_color)
+                            .or(grid_background),
+                    ),
+                    None => (' ', None, grid_background),
+                };
+                cell_list.push(cell);
+            }
+        }
+
+        return AsciiArtCache {
+            width,
+            height,
+            num_cols: num_cols as u16,
+            num_rows: num_rows as u16,
+            cell_list,
+        };
+    }
+}
+
+impl Widget for &AsciiArtWidget {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.is_empty() {
+            return;
+        }
+        self.render_ascii_grid(area, buf);
+    }
+}
+
+impl Widget for AsciiArtWidget {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        (&self).render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a black image with a white right half
+    fn get_test_image() -> DynamicImage {
+        let mut img = image::RgbImage::new(80, 80);
+        for (x, _, pixel) in img.enumerate_pixels_mut() {
+            if x >= 40 {
+                *pixel = image::Rgb([255, 255, 255]);
+            }
+        }
+        return DynamicImage::ImageRgb8(img);
+    }
+
+    #[test]
+    fn render_centered_in_area() {
+        let widget = AsciiArtWidget::new(get_test_image(), CharacterType::Simple)
+            .with_color_support(ColorSupport::None);
+        let area = Rect::new(0, 0, 30, 10);
+        let mut buf = Buffer::empty(area);
+        (&widget).render(area, &mut buf);
+
+        // 20 columns and 10 rows fit the area, centered with 5 columns on each side
+        let cache = widget.cache.borrow();
+        let cache = cache.as_ref().unwrap();
+        assert_eq!((cache.num_cols, cache.num_rows), (20, 10));
+        assert_eq!(buf[(0, 0)].symbol(), " ");
+        assert_eq!(buf[(5, 0)].symbol(), " ");
+        assert_ne!(buf[(24, 0)].symbol(), " ");
+        assert_eq!(buf[(25, 0)].symbol(), " ");
+        assert_eq!(buf[(5, 0)].fg, Color::Reset);
+    }
+
+    #[test]
+    fn render_large_area() {
+        // more than u16::MAX cells, the cell index must not overflow
+        let widget = AsciiArtWidget::new(DynamicImage::new_rgb8(1000, 1000), CharacterType::Simple)
+            .with_color_support(ColorSupport::TrueColor);
+        let area = Rect::new(0, 0, 600, 300);
+        let mut buf = Buffer::empty(area);
+        (&widget).render(area, &mut buf);
+        let cache = widget.cache.borrow();
+        let cache = cache.as_ref().unwrap();
+        assert!(cache.num_cols as u32 * cache.num_rows as u32 > u16::MAX as u32);
+        assert_eq!(buf[(300, 150)].fg, Color::Rgb(0, 0, 0));
+    }
+
+    #[test]
+    fn convert_only_when_area_change() {
+        let widget = AsciiArtWidget::new(get_test_image(), CharacterType::Simple)
+            .with_color_support(ColorSupport::Ansi256);
+        let area = Rect::new(0, 0, 30, 10);
+        let mut buf = Buffer::empty(area);
+        (&widget).render(area, &mut buf);
+        assert_eq!(widget.cache.borrow().as_ref().unwrap().width, 30);
+
+        let area = Rect::new(0, 0, 20, 10);
+        let mut buf = Buffer::empty(area);
+        (&widget).render(area, &mut buf);
+        assert_eq!(widget.cache.borrow().as_ref().unwrap().width, 20);
+        assert!(matches!(buf[(10, 5)].fg, Color::Indexed(_)));
+    }
+}
@@ -0,0 +1,765 @@
+use std::{
+    io::{Cursor, ErrorKind, Read},
+    num::NonZeroU64,
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        mpsc::{channel, sync_channel, Receiver, RecvTimeoutError, SendError, TrySendError},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use gif::{DecodeOptions, MemoryLimit};
+use image::{DynamicImage, ImageFormat, ImageReader, Limits};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{
+    config::{RenderConfig, RenderConfigBuilder},
+    decode_gif, dynamic_image_to_ascii_img, grayscale_to_ascii,
+    preprocess::{apply_preprocess, decode_image_reader, Preprocess},
+    stream_frames_to_ascii_gray_gif_with_progress, stream_frames_to_ascii_rgb_gif_with_progress,
+    CancellationToken, CharacterType, ColorTheme, Palette, PalettePreset, ProgressOption,
+    CANCELLED_ERROR,
+};
+
+pub const DEFAULT_SERVER_ADDRESS: &str = "127.0.0.1:8080";
+pub const DEFAULT_MAX_BODY_SIZE: usize = 20 * 1024 * 1024;
+pub const DEFAULT_MAX_IMAGE_DIMENSION: u32 = 8192;
+pub const DEFAULT_MAX_NUM_COLS: u32 = 500;
+pub const DEFAULT_CONVERSION_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_BODY_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_MAX_FRAME_WINDOW: usize = 64;
+pub const DEFAULT_MAX_GIF_FRAMES: usize = 1000;
+pub const DEFAULT_MAX_GIF_PIXELS: u64 = 1_000_000_000;
+
+// the size of every read of an upload, the deadline is checked between them
+const BODY_CHUNK_SIZE: usize = 64 * 1024;
+
+// the settings of `run_server`
+#[derive(PartialEq, Debug, Clone)]
+pub struct ServerOption {
+    pub address: String,
+    // number of conversions run at the same time, default as the number of CPUs
+    pub num_workers: usize,
+    // number of requests waiting for a worker, a request that come when the queue is full get a 503
+    pub queue_size: usize,
+    // the largest upload in bytes, a larger one get a 413
+    pub max_body_size: usize,
+    // the largest width and height of an uploaded image or GIF
+    pub max_image_dimension: u32,
+    // the largest `num_cols` a request can ask for, as the size of the ascii art image grow with it
+    pub max_num_cols: u32,
+    // a GIF conversion that take longer is stopped and get a 503, an image is bounded by the size limits instead
+    pub timeout: Duration,
+    // the time a client has to send the whole upload, a slower one get a 408 so it can't hold a worker
+    pub body_timeout: Duration,
+    // the largest `frame_window` a request can ask for, a larger one is lowered to it
+    pub max_frame_window: usize,
+    // the most frames a GIF can have, and the most pixels of all of its frames together
+    pub max_gif_frames: usize,
+    pub max_gif_pixels: u64,
+}
+
+impl ServerOption {
+    pub fn new() -> ServerOption {
+        let num_workers = thread::available_parallelism()
+            .map(|num_workers| num_workers.get())
+            .unwrap_or(1);
+        return ServerOption {
+            address: DEFAULT_SERVER_ADDRESS.to_string(),
+            num_workers,
+            queue_size: num_workers * 4,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            max_image_dimension: DEFAULT_MAX_IMAGE_DIMENSION,
+            max_num_cols: DEFAULT_MAX_NUM_COLS,
+            timeout: DEFAULT_CONVERSION_TIMEOUT,
+            body_timeout: DEFAULT_BODY_TIMEOUT,
+            max_frame_window: DEFAULT_MAX_FRAME_WINDOW,
+            max_gif_frames: DEFAULT_MAX_GIF_FRAMES,
+            max_gif_pixels: DEFAULT_MAX_GIF_PIXELS,
+        };
+    }
+
+    pub fn with_address(mut self, address: &str) -> ServerOption {
+        self.address = address.to_string();
+        return self;
+    }
+
+    pub fn with_num_workers(mut self, num_workers: usize) -> ServerOption {
+        self.num_workers = num_workers;
+        return self;
+    }
+
+    pub fn with_queue_size(mut self, queue_size: usize) -> ServerOption {
+        self.queue_size = queue_size;
+        return self;
+    }
+
+    pub fn with_max_body_size(mut self, max_body_size: usize) -> ServerOption {
+        self.max_body_size = max_body_size;
+        return self;
+    }
+
+    pub fn with_max_image_dimension(mut self, max_image_dimension: u32) -> ServerOption {
+        self.max_image_dimension = max_image_dimension;
+        return self;
+    }
+
+    pub fn with_max_num_cols(mut self, max_num_cols: u32) -> ServerOption {
+        self.max_num_cols = max_num_cols;
+        return self;
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> ServerOption {
+        self.timeout = timeout;
+        return self;
+    }
+
+    pub fn with_body_timeout(mut self, body_timeout: Duration) -> ServerOption {
+        self.body_timeout = body_timeout;
+        return self;
+    }
+
+    pub fn with_max_frame_window(mut self, max_frame_window: usize) -> ServerOption {
+        self.max_frame_window = max_frame_window;
+        return self;
+    }
+
+    pub fn with_max_gif_frames(mut self, max_gif_frames: usize) -> ServerOption {
+        self.max_gif_frames = max_gif_frames;
+        return self;
+    }
+
+    pub fn with_max_gif_pixels(mut self, max_gif_pixels: u64) -> ServerOption {
+        self.max_gif_pixels = max_gif_pixels;
+        return self;
+    }
+}
+
+impl Default for ServerOption {
+    fn default() -> ServerOption {
+        return ServerOption::new();
+    }
+}
+
+// a request that was answered, given to the callback of `run_server_with_callback`
+#[derive(PartialEq, Debug, Clone)]
+pub struct RequestLog {
+    pub method: String,
+    pub path: String,
+    pub status_code: u16,
+    // the time from taking the request from the queue to answering it
+    pub duration: Duration,
+}
+
+// the status code and message of a request that failed
+type HttpError = (u16, String);
+
+// Serves the conversions over HTTP until the process is stopped.
+//
+// POST /convert/text  -> text/plain, only the simple and complex character types
+// POST /convert/image -> image/png
+// POST /convert/gif   -> image/gif
+//
+// the body is the uploaded image or GIF and the query parameters are the settings of the conversion,
+// e.g. `/convert/gif?num_cols=120&color=true&palette=gameboy`
+pub fn run_server(server_option: ServerOption) -> Result<(), String> {
+    return run_server_with_callback(server_option, |_| {});
+}
+
+// same as `run_server`, with `on_request` called from the worker after every request is answered, e.g. to log it
+pub fn run_server_with_callback<F>(server_option: ServerOption, on_request: F) -> Result<(), String>
+where
+    F: Fn(&RequestLog) + Send + Sync + 'static,
+{
+    if server_option.num_workers == 0 {
+        return Err("The number of workers must be greater than 0".to_string());
+    }
+    let server = Server::http(&server_option.address)
+        .map_err(|e| format!("Failed to listen on {}: {}", server_option.address, e))?;
+
+    let (sender, receiver) = sync_channel::<Request>(server_option.queue_size);
+    let receiver = Arc::new(Mutex::new(receiver));
+    let server_option = Arc::new(server_option);
+    let on_request = Arc::new(on_request);
+    for _ in 0..server_option.num_workers {
+        let receiver = Arc::clone(&receiver);
+        let server_option = Arc::clone(&server_option);
+        let on_request = Arc::clone(&on_request);
+        thread::spawn(move || run_worker(&receiver, &server_option, on_request.as_ref()));
+    }
+
+    for request in server.incoming_requests() {
+        match sender.try_send(request) {
+            Ok(()) => {}
+            Err(TrySendError::Full(request)) => {
+                let response = text_response(503, "The server is busy, try again later")
+                    .with_header(make_header("Retry-After", "1"));
+                let _ = request.respond(response);
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                return Err("Every worker of the server stopped".to_string())
+            }
+        }
+    }
+    return Ok(());
+}
+
+fn run_worker<F: Fn(&RequestLog)>(
+    receiver: &Mutex<Receiver<Request>>,
+    server_option: &ServerOption,
+    on_request: &F,
+) {
+    loop {
+        // the lock is only held while waiting, so the other workers can take the next request
+        let request = match receiver.lock() {
+            Ok(receiver) => match receiver.recv() {
+                Ok(request) => request,
+                Err(_) => return,
+            },
+            Err(_) => return,
+        };
+        handle_request(request, server_option, on_request);
+    }
+}
+
+fn handle_request<F: Fn(&RequestLog)>(
+    request: Request,
+    server_option: &ServerOption,
+    on_request: &F,
+) {
+    let start = Instant::now();
+    let method = request.method().clone();
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+
+    // the path and the settings are checked before the upload is read
+    let route = match path {
+        "/convert/text" | "/convert/image" | "/convert/gif" if method != Method::Post => {
+            Err((405, format!("{} only accept POST", path)))
+        }
+        "/convert/text" | "/convert/image" | "/convert/gif" => parse_query(query, server_option),
+        _ => Err((404, format!("Unknown path: {}", path))),
+    };
+    let (request, result) = match route {
+        Ok(render_config) => match read_body_before_deadline(request, server_option) {
+            Some((request, Ok(body))) => {
+                // a panic of a conversion is answered with a 500 and the worker keep running
+                let result = catch_unwind(AssertUnwindSafe(|| match path {
+                    "/convert/text" => convert_text(&body, render_config, server_option),
+                    "/convert/image" => convert_image(&body, render_config, server_option),
+                    _ => convert_gif(&body, render_config, server_option),
+                }))
+                .unwrap_or_else(|_| Err((500, "The conversion failed unexpectedly".to_string())));
+                (request, result)
+            }
+            Some((request, Err(e))) => (request, Err(e)),
+            // the thread that is still reading answer the request
+            None => {
+                on_request(&RequestLog {
+                    method: method.to_string(),
+                    path: path.to_string(),
+                    status_code: 408,
+                    duration: start.elapsed(),
+                });
+                return;
+            }
+        },
+        Err(e) => (request, Err(e)),
+    };
+
+    let response = match result {
+        Ok((content_type, data)) => {
+            Response::from_data(data).with_header(make_header("Content-Type", content_type))
+        }
+        Err((status_code, message)) => text_response(status_code, &message),
+    };
+    let status_code = response.status_code().0;
+    let _ = request.respond(response);
+    on_request(&RequestLog {
+        method: method.to_string(),
+        path: path.to_string(),
+        status_code,
+        duration: start.elapsed(),
+    });
+}
+
+// read the upload on its own thread, so a client that stop sending can only hold the worker until the deadline
+//
+// `None` once the deadline passed, the reading thread then answer the request with a 408 when its read return
+fn read_body_before_deadline(
+    mut request: Request,
+    server_option: &ServerOption,
+) -> Option<(Request, Result<Vec<u8>, HttpError>)> {
+    let deadline = Instant::now() + server_option.body_timeout;
+    let max_body_size = server_option.max_body_size;
+    let body_timeout = server_option.body_timeout;
+    let (sender, receiver) = sync_channel(1);
+    thread::spawn(move || {
+        let body = read_body(&mut request, max_body_size, body_timeout, deadline);
+        if let Err(SendError((request, _))) = sender.send((request, body)) {
+            let _ = request.respond(get_body_timeout_response(body_timeout));
+        }
+    });
+    return receiver.recv_timeout(server_option.body_timeout).ok();
+}
+
+fn read_body(
+    request: &mut Request,
+    max_body_size: usize,
+    body_timeout: Duration,
+    deadline: Instant,
+) -> Result<Vec<u8>, HttpError> {
+    let too_large_error = || {
+        (
+            413,
+            format!("The upload is larger than {} bytes", max_body_size),
+        )
+    };
+    if request
+        .body_length()
+        .is_some_and(|body_length| body_length > max_body_size)
+    {
+        return Err(too_large_error());
+    }
+
+    // one more byte than the limit is read to know if the body is too large without a Content-Length
+    let mut body = Vec::new();
+    let mut reader = request.as_reader().take(max_body_size as u64 + 1);
+    let mut chunk = vec![0; BODY_CHUNK_SIZE];
+    loop {
+        // a client that send a byte at a time is stopped at the deadline too
+        if Instant::now() > deadline {
+            return Err((408, get_body_timeout_message(body_timeout)));
+        }
+        match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(num_bytes) => body.extend_from_slice(&chunk[..num_bytes]),
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err((400, format!("Failed to read the upload: {}", e))),
+        }
+    }
+    if body.len() > max_body_size {
+        return Err(too_large_error());
+    }
+    if body.is_empty() {
+        return Err((400, "The upload is empty".to_string()));
+    }
+    return Ok(body);
+}
+
+fn get_body_timeout_message(body_timeout: Duration) -> String {
+    return format!("The upload took longer than {:?}", body_timeout);
+}
+
+fn get_body_timeout_response(body_timeout: Duration) -> Response<Cursor<Vec<u8>>> {
+    return text_response(408, &get_body_timeout_message(body_timeout));
+}
+
+// map the query parameters to a render config, an unknown parameter is an error so a typo isn't ignored
+fn parse_query(query: &str, server_option: &ServerOption) -> Result<RenderConfig, HttpError> {
+    let mut builder = RenderConfigBuilder::new();
+    for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+        let invalid_value_error = || (400, format!("Invalid value of {}: {}", key, value));
+        builder = match key.as_ref() {
+            "character_type" => builder.character_type(
+                CharacterType::from_name(&value)
+                    .ok_or((400, format!("Unknown character type: {}", value)))?,
+            ),
+            "num_cols" => builder.num_cols(value.parse().map_err(|_| invalid_value_error())?),
+            "color" => builder.color(parse_bool(&value).ok_or_else(invalid_value_error)?),
+            "white_bg" => builder.white_bg(parse_bool(&value).ok_or_else(invalid_value_error)?),
+            "transparent_bg" => {
+                builder.transparent_bg(parse_bool(&value).ok_or_else(invalid_value_error)?)
+            }
+            "color_theme" => builder.color_theme(
+                ColorTheme::from_name(&value)
+                    .ok_or((400, format!("Unknown color theme: {}", value)))?,
+            ),
+            "palette" => builder.palette(Palette::from_preset(
+                PalettePreset::from_name(&value)
+                    .ok_or((400, format!("Unknown palette: {}", value)))?,
+            )),
+            // the frames of a window are held in memory together, so it is kept within the server's limit
+            "frame_window" => builder.frame_window(
+                value
+                    .parse::<usize>()
+                    .map_err(|_| invalid_value_error())?
+                    .min(server_option.max_frame_window),
+            ),
+            "brightness" => builder.brightness(value.parse().map_err(|_| invalid_value_error())?),
+            "contrast" => builder.contrast(value.parse().map_err(|_| invalid_value_error())?),
+            "gamma" => builder.gamma(value.parse().map_err(|_| invalid_value_error())?),
+            "invert" => builder.invert(parse_bool(&value).ok_or_else(invalid_value_error)?),
+            _ => return Err((400, format!("Unknown query parameter: {}", key))),
+        };
+    }
+
+    let render_config = builder.build().map_err(|e| (400, e))?;
+    if render_config.setting_option.num_cols > server_option.max_num_cols {
+        return Err((
+            400,
+            format!(
+                "num_cols must not be greater than {}",
+                server_option.max_num_cols
+            ),
+        ));
+    }
+    return Ok(render_config);
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "true" | "1" => return Some(true),
+        "false" | "0" => return Some(false),
+        _ => return None,
+    }
+}
+
+fn convert_text(
+    body: &[u8],
+    render_config: RenderConfig,
+    server_option: &ServerOption,
+) -> Result<(&'static str, Vec<u8>), HttpError> {
+    let complex = match render_config.character_type {
+        CharacterType::Simple => false,
+        CharacterType::Complex => true,
+        _ => {
+            return Err((
+                400,
+                "Only the simple and complex character types can be converted to text".to_string(),
+            ))
+        }
+    };
+    let preprocess = render_config.setting_option.preprocess;
+    let img = load_image(body, preprocess, server_option)?;
+    let img = apply_preprocess(&img, preprocess);
+    let ascii = grayscale_to_ascii(&img, render_config.setting_option.num_cols, complex);
+    return Ok(("text/plain; charset=utf-8", ascii.into_bytes()));
+}
+
+fn convert_image(
+    body: &[u8],
+    render_config: RenderConfig,
+    server_option: &ServerOption,
+) -> Result<(&'static str, Vec<u8>), HttpError> {
+    let img = load_image(body, render_config.setting_option.preprocess, server_option)?;
+    let ascii_img = dynamic_image_to_ascii_img(
+        &img,
+        render_config.character_type,
+        render_config.setting_option,
+    );
+
+    let mut png_buffer = Cursor::new(Vec::new());
+    ascii_img
+        .write_to(&mut png_buffer, ImageFormat::Png)
+        .map_err(|e| (500, format!("Failed to encode image: {}", e)))?;
+    return Ok(("image/png", png_buffer.into_inner()));
+}
+
+fn convert_gif(
+    body: &[u8],
+    render_config: RenderConfig,
+    server_option: &ServerOption,
+) -> Result<(&'static str, Vec<u8>), HttpError> {
+    let start = Instant::now();
+    let max_image_dimension = server_option.max_image_dimension;
+    let mut decode_options = DecodeOptions::new();
+    // the limit is for every frame, an rgba frame of the largest size allowed
+    let frame_memory_limit = max_image_dimension as u64 * max_image_dimension as u64 * 4;
+    decode_options.set_memory_limit(MemoryLimit::Bytes(
+        NonZeroU64::new(frame_memory_limit).unwrap_or(NonZeroU64::MIN),
+    ));
+
+    let setting_option = render_config.setting_option;
+    let decoder = decode_gif(Cursor::new(body), decode_options).map_err(|e| (400, e))?;
+    if decoder.width() as u32 > max_image_dimension || decoder.height() as u32 > max_image_dimension
+    {
+        return Err((
+            413,
+            format!(
+                "The GIF is larger than {}x{}",
+                max_image_dimension, max_image_dimension
+            ),
+        ));
+    }
+    check_gif_size(body, decoder.width(), decoder.height(), server_option)?;
+    setting_option
+        .preprocess
+        .get_output_size(decoder.width() as u32, decoder.height() as u32)
+        .map_err(|e| (400, e))?;
+
+    // the conversion is stopped at the next frame once the time is up, a watchdog cancel it
+    // so the time limit hold even while no progress is made
+    let cancellation_token = CancellationToken::new();
+    let progress_option = ProgressOption::new().with_cancellation_token(&cancellation_token);
+    let result = thread::scope(|scope| {
+        let (done_sender, done_receiver) = channel::<()>();
+        let remaining_time = server_option.timeout.saturating_sub(start.elapsed());
+        let watchdog_token = cancellation_token.clone();
+        scope.spawn(move || {
+            if done_receiver.recv_timeout(remaining_time) == Err(RecvTimeoutError::Timeout) {
+                watchdog_token.cancel();
+            }
+        });
+
+        let result = if setting_option.is_color {
+            stream_frames_to_ascii_rgb_gif_with_progress(
+                decoder,
+                Vec::new(),
+                render_config.character_type,
+                setting_option,
+                progress_option,
+            )
+        } else {
+            stream_frames_to_ascii_gray_gif_with_progress(
+                decoder,
+                Vec::new(),
+                render_config.character_type,
+                setting_option,
+                progress_option,
+            )
+        };
+        drop(done_sender);
+        result
+    });
+
+    match result {
+        Ok(gif_buffer) => return Ok(("image/gif", gif_buffer)),
+        Err(e) if e == CANCELLED_ERROR => {
+            return Err((
+                503,
+                format!(
+                    "The conversion took longer than {:?}",
+                    server_option.timeout
+                ),
+            ))
+        }
+        Err(e) => return Err((400, e)),
+    }
+}
+
+// count the frames of an uploaded GIF without decoding their pixels, every frame is converted on the whole screen
+// so the pixels are counted as the size of the screen for every frame
+fn check_gif_size(
+    body: &[u8],
+    width: u16,
+    height: u16,
+    server_option: &ServerOption,
+) -> Result<(), HttpError> {
+    let mut decode_options = DecodeOptions::new();
+    decode_options.skip_frame_decoding(true);
+    let mut decoder = decode_options
+        .read_info(Cursor::new(body))
+        .map_err(|e| (400, format!("Failed to decode GIF: {}", e)))?;
+
+    let screen_size = width as u64 * height as u64;
+    let mut num_frames: usize = 0;
+    while decoder
+        .read_next_frame()
+        .map_err(|e| (400, format!("Failed to decode GIF frame: {}", e)))?
+        .is_some()
+    {
+        num_frames += 1;
+        if num_frames > server_option.max_gif_frames {
+            return Err((
+                413,
+                format!(
+                    "The GIF has more than {} frames",
+                    server_option.max_gif_frames
+                ),
+            ));
+        }
+        if num_frames as u64 * screen_size > server_option.max_gif_pixels {
+            return Err((
+                413,
+                format!(
+                    "The frames of the GIF have more than {} pixels together",
+                    server_option.max_gif_pixels
+                ),
+            ));
+        }
+    }
+    return Ok(());
+}
+
+// decode an uploaded image, the size is checked before the pixels are decoded
+// and the size after the preprocess once they are
+fn load_image(
+    body: &[u8],
+    preprocess: Preprocess,
+    server_option: &ServerOption,
+) -> Result<DynamicImage, HttpError> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(server_option.max_image_dimension);
+    limits.max_image_height = Some(server_option.max_image_dimension);
+
+    let mut image_reader = ImageReader::new(Cursor::new(body))
+        .with_guessed_format()
+        .map_err(|e| (400, format!("Failed to read image: {}", e)))?;
+    image_reader.limits(limits);
+    let img =
+        decode_image_reader(image_reader, preprocess.is_auto_orient).map_err(|e| match e {
+            image::ImageError::Limits(_) => (
+                413,
+                format!(
+                    "The image is larger than {}x{}",
+                    server_option.max_image_dimension, server_option.max_image_dimension
+                ),
+            ),
+            e => (400, format!("Failed to decode image: {}", e)),
+        })?;
+    preprocess
+        .get_output_size(img.width(), img.height())
+        .map_err(|e| (400, e))?;
+    return Ok(img);
+}
+
+fn text_response(status_code: u16, message: &str) -> Response<Cursor<Vec<u8>>> {
+    return Response::from_string(message)
+        .with_status_code(status_code)
+        .with_header(make_header("Content-Type", "text/plain; charset=utf-8"));
+}
+
+fn make_header(field: &str, value: &str) -> Header {
+    return Header::from_bytes(field.as_bytes(), value.as_bytes()).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_gif_data(width: u16, height: u16, num_frames: usize) -> Vec<u8> {
+        let mut gif_data = vec![];
+        {
+            let mut encoder = gif::Encoder::new(&mut gif_data, width, height, &[0, 0, 0]).unwrap();
+            let frame = gif::Frame::from_indexed_pixels(
+                width,
+                height,
+                vec![0; width as usize * height as usize],
+                None,
+            );
+            for _ in 0..num_frames {
+                encoder.write_frame(&frame).unwrap();
+            }
+        }
+        return gif_data;
+    }
+
+    #[test]
+    fn parse_query_settings() {
+        let server_option = ServerOption::new();
+        let render_config = parse_query(
+            "character_type=complex&num_cols=120&color=1&palette=gameboy&invert=false",
+            &server_option,
+        )
+        .unwrap();
+        assert_eq!(render_config.character_type, CharacterType::Complex);
+        assert_eq!(render_config.setting_option.num_cols, 120);
+        assert!(render_config.setting_option.is_color);
+        assert!(render_config.setting_option.palette.is_some());
+
+        let render_config = parse_query("", &server_option).unwrap();
+        assert_eq!(render_config.character_type, CharacterType::Simple);
+        assert!(!render_config.setting_option.is_color);
+    }
+
+    #[test]
+    fn parse_query_invalid() {
+        let server_option = ServerOption::new();
+        assert_eq!(
+            parse_query("color=yes", &server_option).err(),
+            Some((400, "Invalid value of color: yes".to_string()))
+        );
+        assert_eq!(
+            parse_query("num_cols=-1", &server_option).err(),
+            Some((400, "Invalid value of num_cols: -1".to_string()))
+        );
+        assert_eq!(
+            parse_query("palette=unknown", &server_option).err(),
+            Some((400, "Unknown palette: unknown".to_string()))
+        );
+        assert_eq!(
+            parse_query("path=/etc", &server_option).err(),
+            Some((400, "Unknown query parameter: path".to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_query_limits() {
+        let server_option = ServerOption::new()
+            .with_max_num_cols(100)
+            .with_max_frame_window(8);
+        assert_eq!(
+            parse_query("num_cols=101", &server_option).err(),
+            Some((400, "num_cols must not be greater than 100".to_string()))
+        );
+        assert!(parse_query("num_cols=100", &server_option).is_ok());
+
+        // a larger frame window is lowered to the limit instead of failing
+        let render_config = parse_query("frame_window=1000", &server_option).unwrap();
+        assert_eq!(render_config.setting_option.frame_window, 8);
+    }
+
+    #[test]
+    fn check_gif_size_limits() {
+        let gif_data = get_gif_data(10, 10, 3);
+        let server_option = ServerOption::new();
+        assert!(check_gif_size(&gif_data, 10, 10, &server_option).is_ok());
+
+        let server_option = ServerOption::new().with_max_gif_frames(2);
+        assert_eq!(
+            check_gif_size(&gif_data, 10, 10, &server_option).err(),
+            Some((413, "The GIF has more than 2 frames".to_string()))
+        );
+
+        let server_option = ServerOption::new().with_max_gif_pixels(250);
+        assert_eq!(
+            check_gif_size(&gif_data, 10, 10, &server_option).err(),
+            Some((
+                413,
+                "The frames of the GIF have more than 250 pixels together".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn load_image_limits() {
+        let mut png_data = Cursor::new(vec![]);
+        DynamicImage::new_rgb8(40, 20)
+            .write_to(&mut png_data, ImageFormat::Png)
+            .unwrap();
+        let png_data = png_data.into_inner();
+
+        let server_option = ServerOption::new().with_max_image_dimension(40);
+        assert!(load_image(&png_data, Preprocess::new(), &server_option).is_ok());
+        let server_option = ServerOption::new().with_max_image_dimension(30);
+        assert_eq!(
+            load_image(&png_data, Preprocess::new(), &server_option).err(),
+            Some((413, "The image is larger than 30x30".to_string()))
+        );
+        assert_eq!(
+            load_image(b"not an image", Preprocess::new(), &server_option)
+                .err()
+                .unwrap()
+                .0,
+            400
+        );
+    }
+
+    #[test]
+    fn convert_gif_limits() {
+        let gif_data = get_gif_data(40, 40, 2);
+        let render_config = parse_query("num_cols=10", &ServerOption::new()).unwrap();
+        let (content_type, _) =
+            convert_gif(&gif_data, render_config.clone(), &ServerOption::new()).unwrap();
+        assert_eq!(content_type, "image/gif");
+
+        let server_option = ServerOption::new().with_max_image_dimension(30);
+        assert_eq!(
+            convert_gif(&gif_data, render_config, &server_option).err(),
+            Some((413, "The GIF is larger than 30x30".to_string()))
+        );
+    }
+}
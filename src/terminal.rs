@@ -0,0 +1,336 @@
+use std::{
+    env,
+    io::{stdout, IsTerminal, Write},
+};
+
+use image::{DynamicImage, GenericImageView};
+
+use crate::{
+    ascii_grid::{get_ascii_grid_size, image_to_ascii_grid, IMAGE_FALLBACK_CELL_WIDTH},
+    character::CharacterType,
+    preprocess::{apply_preprocess, open_image, Preprocess},
+    Palette, PaletteMatcher, PalettePreset, SettingOption,
+};
+
+// the colors a terminal can show
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ColorSupport {
+    TrueColor, // 24-bit colors
+    Ansi256,   // the xterm 256 color palette
+    Ansi16,    // the 8 basic and 8 bright colors
+    None,      // no color, only the characters
+}
+
+impl ColorSupport {
+    // the colors of the terminal from the environment variables
+    //
+    // NO_COLOR turns the colors off, COLORTERM=truecolor/24bit gives 24-bit colors,
+    // a TERM with 256color gives 256 colors, any other TERM except dumb gives 16 colors
+    pub fn from_env() -> ColorSupport {
+        if env::var("NO_COLOR").is_ok_and(|no_color| !no_color.is_empty()) {
+            return ColorSupport::None;
+        }
+        let color_term = env::var("COLORTERM").unwrap_or_default().to_lowercase();
+        if color_term == "truecolor" || color_term == "24bit" {
+            return ColorSupport::TrueColor;
+        }
+        let term = env::var("TERM").unwrap_or_default().to_lowercase();
+        if term.is_empty() || term == "dumb" {
+            return ColorSupport::None;
+        }
+        if term.contains("256color") {
+            return ColorSupport::Ansi256;
+        }
+        return ColorSupport::Ansi16;
+    }
+}
+
+// turn an rgb color into the SGR parameters of the color support
+pub struct AnsiColorEncoder {
+    color_support: ColorSupport,
+    palette_matcher: Option<PaletteMatcher>,
+}
+
+impl AnsiColorEncoder {
+    pub fn new(color_support: ColorSupport) -> AnsiColorEncoder {
+        let palette_matcher = match color_support {
+            ColorSupport::Ansi256 => Some(PaletteMatcher::new(&Palette::from_preset(
+                PalettePreset::Xterm256,
+            ))),
+            ColorSupport::Ansi16 => {
+                let xterm_palette = Palette::from_preset(PalettePreset::Xterm256);
+                Some(PaletteMatcher::new(
+                    &Palette::new(&xterm_palette.get_color_list()[..16]).unwrap(),
+                ))
+            }
+            _ => None,
+        };
+        return AnsiColorEncoder {
+            color_support,
+            palette_matcher,
+        };
+    }
+
+    // the index of the nearest of the 256 or 16 colors, `None` for true color and no color
+    pub fn get_color_index(&mut self, rgb: [u8; 3]) -> Option<u8> {
+        return self
+            .palette_matcher
+            .as_mut()
+            .map(|palette_matcher| palette_matcher.get_index(rgb));
+    }
+
+    // the escape code that set the foreground (or the background) color, empty for `ColorSupport::None`
+    pub fn get_escape_code(&mut self, rgb: [u8; 3], is_background: bool) -> String {
+        let index = self.get_color_index(rgb);
+        let code = if is_background { 48 } else { 38 };
+        match (self.color_support, index) {
+            (ColorSupport::TrueColor, _) => {
+                return format!("\x1b[{};2;{};{};{}m", code, rgb[0], rgb[1], rgb[2]);
+            }
+            (ColorSupport::Ansi256, Some(index)) => return format!("\x1b[{};5;{}m", code, index),
+            // 30-37 and 90-97 for the foreground, 40-47 and 100-107 for the background
+            (ColorSupport::Ansi16, Some(index)) => {
+                let base = if index < 8 { code - 8 } else { code + 52 };
+                return format!("\x1b[{}m", base + index % 8);
+            }
+            _ => return String::new(),
+        }
+    }
+}
+
+// the size and the colors of the terminal the ascii art is printed to
+//
+// when stdout is not a terminal (e.g. piped to a file) there is no size and no color,
+// so the `num_cols` of the setting option is used and only the characters are printed
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Terminal {
+    pub is_tty: bool,
+    pub num_cols: Option<u32>,
+    pub num_rows: Option<u32>,
+    pub color_support: ColorSupport,
+}
+
+impl Terminal {
+    pub fn detect() -> Terminal {
+        let is_tty = stdout().is_terminal();
+        if !is_tty {
+            return Terminal {
+                is_tty,
+                num_cols: None,
+                num_rows: None,
+                color_support: ColorSupport::None,
+            };
+        }
+
+        // COLUMNS and LINES are used if the size can't be queried
+        let (num_cols, num_rows) = match terminal_size::terminal_size() {
+            Some((terminal_size::Width(width), terminal_size::Height(height))) => {
+                (Some(width as u32), Some(height as u32))
+            }
+            None => (get_env_size("COLUMNS"), get_env_size("LINES")),
+        };
+        return Terminal {
+            is_tty,
+            num_cols,
+            num_rows,
+            color_support: ColorSupport::from_env(),
+        };
+    }
+
+    pub fn with_size(mut self, num_cols: u32, num_rows: u32) -> Terminal {
+        self.num_cols = Some(num_cols);
+        self.num_rows = Some(num_rows);
+        return self;
+    }
+
+    pub fn with_color_support(mut self, color_support: ColorSupport) -> Terminal {
+        self.color_support = color_support;
+        return self;
+    }
+
+    // the setting option with the largest `num_cols` that fit an image of this size in the terminal,
+    // a line is left for the prompt
+    pub fn fit_setting_option(
+        &self,
+        width: u32,
+        height: u32,
+        setting_option: SettingOption,
+    ) -> SettingOption {
+        let mut setting_option = setting_option;
+        if let Some(num_cols) = self.num_cols {
+            let max_rows = self.num_rows.map(|num_rows| num_rows.saturating_sub(1));
+            setting_option.num_cols = fit_num_cols(width, height, num_cols, max_rows);
+        }
+        return setting_option;
+    }
+
+    // the ascii art of an image, sized to the terminal and in the colors it support
+    //
+    // the art is colored if `is_color` or a color theme is set, otherwise only the characters are returned
+    pub fn render(
+        &self,
+        img: &DynamicImage,
+        character_type: CharacterType,
+        setting_option: &SettingOption,
+    ) -> String {
+        // fit the columns to the image as it will be sampled, it is not preprocessed again by the grid
+        let img = apply_preprocess(img, setting_option.preprocess);
+        let mut setting_option = setting_option.clone();
+        setting_option.preprocess = Preprocess::new();
+        let (width, height) = img.dimensions();
+        let setting_option = self.fit_setting_option(width, height, setting_option);
+        let ascii_grid = image_to_ascii_grid(&img, character_type, &setting_option);
+        if setting_option.is_color || setting_option.color_theme.is_some() {
+            return ascii_grid.to_ansi_with_color_support(self.color_support);
+        }
+        return ascii_grid.to_text();
+    }
+
+    pub fn print(
+        &self,
+        img: &DynamicImage,
+        character_type: CharacterType,
+        setting_option: &SettingOption,
+    ) -> Result<(), String> {
+        let ascii = self.render(img, character_type, setting_option);
+        let mut stdout = stdout().lock();
+        stdout
+            .write_all(ascii.as_bytes())
+            .and_then(|_| stdout.flush())
+            .map_err(|e| format!("Failed to print: {}", e))?;
+        return Ok(());
+    }
+
+    // clear the screen and hide the cursor before drawing the frames of a live preview
+    pub fn start_live_preview<W: Write>(&self, writer: &mut W) -> Result<(), String> {
+        return self.write_control(writer, "\x1b[?25l\x1b[2J");
+    }
+
+    // draw a frame over the previous one, from the top left corner of the screen
+    pub fn draw_live_frame<W: Write>(&self, writer: &mut W, frame: &str) -> Result<(), String> {
+        self.write_control(writer, "\x1b[H")?;
+        writer
+            .write_all(frame.as_bytes())
+            .map_err(|e| format!("Failed to print: {}", e))?;
+        // clear what is left of a larger previous frame
+        self.write_control(writer, "\x1b[J")?;
+        return writer
+            .flush()
+            .map_err(|e| format!("Failed to print: {}", e));
+    }
+
+    // show the cursor again once the live preview is done
+    pub fn end_live_preview<W: Write>(&self, writer: &mut W) -> Result<(), String> {
+        self.write_control(writer, "\x1b[?25h")?;
+        return writer
+            .flush()
+            .map_err(|e| format!("Failed to print: {}", e));
+    }
+
+    // the cursor control codes are only written to a terminal, a pipe get the frames one after another
+    fn write_control<W: Write>(&self, writer: &mut W, control: &str) -> Result<(), String> {
+        if !self.is_tty {
+            return Ok(());
+        }
+        return writer
+            .write_all(control.as_bytes())
+            .map_err(|e| format!("Failed to print: {}", e));
+    }
+}
+
+// Print the ascii art of an image in the terminal, sized to fit the terminal.
+pub fn image_to_terminal(
+    path: &str,
+    character_type: CharacterType,
+    setting_option: SettingOption,
+) -> Result<(), String> {
+    let img = open_image(path, setting_option.preprocess.is_auto_orient)
+        .map_err(|e| format!("Failed to open image: {}", e))?;
+    setting_option
+        .preprocess
+        .get_output_size(img.width(), img.height())?;
+    return Terminal::detect().print(&img, character_type, &setting_option);
+}
+
+// the largest number of columns, at most `max_cols`, that keep the ascii art of an image within `max_rows`
+//
+// an empty image has no rows to fit, a single column is returned
+pub fn fit_num_cols(width: u32, height: u32, max_cols: u32, max_rows: Option<u32>) -> u32 {
+    if width == 0 || height == 0 {
+        return 1;
+    }
+    let mut num_cols = max_cols.min(width).max(1);
+    let max_rows = match max_rows {
+        Some(max_rows) => max_rows.max(1),
+        None => return num_cols,
+    };
+
+    // a cell is twice as high as it is wide, start from the estimate and go down to the exact fit
+    let estimate = (2 * max_rows as u64 * width as u64 / height as u64 + 1) as u32;
+    num_cols = num_cols.min(estimate.max(1));
+    while num_cols > 1 {
+        let (_, num_rows, _, _) =
+            get_ascii_grid_size(width, height, num_cols, IMAGE_FALLBACK_CELL_WIDTH);
+        if num_rows <= max_rows {
+            break;
+        }
+        num_cols -= 1;
+    }
+    return num_cols;
+}
+
+fn get_env_size(name: &str) -> Option<u32> {
+    return env::var(name)
+        .ok()
+        .and_then(|size| size.parse().ok())
+        .filter(|size| *size > 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_num_cols_to_rows() {
+        assert_eq!(fit_num_cols(400, 200, 80, None), 80);
+        assert_eq!(fit_num_cols(400, 200, 80, Some(10)), 40);
+        // a wide image is capped by the columns, a tall one by the rows
+        assert_eq!(fit_num_cols(1000, 10, 80, Some(10)), 80);
+        assert_eq!(fit_num_cols(10, 1000, 80, Some(10)), 1);
+    }
+
+    #[test]
+    fn fit_num_cols_of_empty_image() {
+        assert_eq!(fit_num_cols(0, 100, 80, Some(10)), 1);
+        assert_eq!(fit_num_cols(100, 0, 80, Some(10)), 1);
+        assert_eq!(fit_num_cols(0, 0, 80, None), 1);
+    }
+
+    #[test]
+    fn escape_code_of_color_support() {
+        let red = [255, 0, 0];
+        let mut encoder = AnsiColorEncoder::new(ColorSupport::TrueColor);
+        assert_eq!(encoder.get_escape_code(red, false), "\x1b[38;2;255;0;0m");
+        assert_eq!(encoder.get_escape_code(red, true), "\x1b[48;2;255;0;0m");
+        let mut encoder = AnsiColorEncoder::new(ColorSupport::Ansi256);
+        assert_eq!(encoder.get_escape_code(red, false), "\x1b[38;5;9m");
+        let mut encoder = AnsiColorEncoder::new(ColorSupport::Ansi16);
+        assert_eq!(encoder.get_escape_code(red, false), "\x1b[91m");
+        assert_eq!(encoder.get_escape_code([0, 0, 0], true), "\x1b[40m");
+        let mut encoder = AnsiColorEncoder::new(ColorSupport::None);
+        assert_eq!(encoder.get_escape_code(red, false), "");
+    }
+
+    #[test]
+    fn render_fit_to_terminal() {
+        let img = DynamicImage::new_rgb8(400, 200);
+        let terminal = Terminal::detect()
+            .with_size(40, 11)
+            .with_color_support(ColorSupport::None);
+        let setting_option = terminal.fit_setting_option(400, 200, SettingOption::grayscale(100));
+        assert_eq!(setting_option.num_cols, 40);
+        let ascii = terminal.render(&img, CharacterType::Simple, &SettingOption::grayscale(100));
+        assert_eq!(ascii.lines().count(), 10);
+        assert!(ascii.lines().all(|line| line.chars().count() == 40));
+    }
+}
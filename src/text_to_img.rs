@@ -0,0 +1,221 @@
+use std::fs::read_to_string;
+
+use ab_glyph::Font;
+use image::{ImageBuffer, Pixel, Rgba};
+
+use crate::{
+    ansi::{parse_ansi_text, AnsiChar, AnsiColorScheme},
+    ascii_grid::{AsciiCell, AsciiGrid},
+    character::{CharacterType, FontData},
+    utils::utils::{get_output_path, get_saved_message},
+    SettingOption,
+};
+
+// the character drawn in place of a character that the font doesn't have
+pub const MISSING_CHARACTER_REPLACEMENT: char = '?';
+
+// Converts a text file (e.g. from `image_to_text`) back to an image and save it as png.
+pub fn text_to_image(
+    path: &str,
+    character_type: CharacterType,
+    output_directory: Option<&str>,
+    filename: Option<&str>,
+    setting_option: SettingOption,
+) -> Result<String, String> {
+    let text = read_to_string(path).map_err(|e| format!("Failed to read text: {}", e))?;
+    let ascii_img = text_to_ascii_img(&text, character_type, setting_option);
+
+    let path = get_output_path(output_directory, filename, "png")?;
+    ascii_img
+        .save(&path)
+        .map_err(|e| format!("Failed to save {}: {}", path, e))?;
+
+    return Ok(get_saved_message(
+        "Image",
+        output_directory,
+        filename,
+        "png",
+    ));
+}
+
+// draw a multi-line text with the font of the character type, in the same layout as `grayscale_to_ascii_img`
+pub fn text_to_ascii_img(
+    text: &str,
+    character_type: CharacterType,
+    setting_option: SettingOption,
+) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    return text_to_ascii_grid(text, character_type, setting_option).to_rgba_img();
+}
+
+// put every character of a text into a cell of an AsciiGrid, a line of the text is a row of the grid
+//
+// the text is drawn in white on black (or black on white for `is_white_bg`), or in the colors of the color theme,
+// and the background is left transparent for `is_transparent_bg`
+// the ANSI color codes of the text are used if `is_color` is set, otherwise they are left out
+// the characters that the font doesn't have are replaced by `MISSING_CHARACTER_REPLACEMENT`
+pub fn text_to_ascii_grid(
+    text: &str,
+    character_type: CharacterType,
+    setting_option: SettingOption,
+) -> AsciiGrid {
+    let line_list = parse_ansi_text(text);
+    let num_cols = line_list.iter().map(|line| line.len()).max().unwrap_or(0) as u32;
+
+    let default_color_pair = match setting_option.color_theme {
+        Some(color_theme) => (color_theme.get_color(255), color_theme.get_color(0)),
+        None if setting_option.is_white_bg => ([0, 0, 0], [255, 255, 255]),
+        None => ([255, 255, 255], [0, 0, 0]),
+    };
+    return ansi_line_list_to_ascii_grid(
+        &line_list,
+        num_cols,
+        character_type,
+        setting_option,
+        &AnsiColorScheme::xterm(),
+        default_color_pair,
+    );
+}
+
+// put lines of styled characters into the cells of an AsciiGrid with `num_cols` columns,
+// the default colors are the (foreground, background) of the characters without a color
+//
+// shared by the text and the ANSI art conversions
+pub fn ansi_line_list_to_ascii_grid(
+    line_list: &[Vec<AnsiChar>],
+    num_cols: u32,
+    character_type: CharacterType,
+    setting_option: SettingOption,
+    color_scheme: &AnsiColorScheme,
+    default_color_pair: ([u8; 3], [u8; 3]),
+) -> AsciiGrid {
+    let num_rows = line_list.len() as u32;
+    let (foreground, background) = default_color_pair;
+    let background_alpha = if setting_option.is_transparent_bg {
+        0
+    } else {
+        255
+    };
+    let mut ascii_grid = AsciiGrid::new(
+        num_rows,
+        num_cols,
+        Rgba([
+            background[0],
+            background[1],
+            background[2],
+            background_alpha,
+        ]),
+        character_type,
+    );
+
+    let character_data: FontData = character_type.get_character_data();
+    for (i, line) in line_list.iter().enumerate() {
+        for (j, ansi_char) in line.iter().take(num_cols as usize).enumerate() {
+            let (cell_foreground, cell_background) = if setting_option.is_color {
+                ansi_char
+                    .style
+                    .get_color_pair(color_scheme, foreground, background)
+            } else {
+                (foreground, None)
+            };
+            let cell_foreground = Rgba([
+                cell_foreground[0],
+                cell_foreground[1],
+                cell_foreground[2],
+                255,
+            ]);
+
+            // glyph id 0 is the `.notdef` glyph, which the font use for a character it doesn't have
+            let character = if ansi_char.character == ' '
+                || character_data.font.glyph_id(ansi_char.character).0 != 0
+            {
+                ansi_char.character
+            } else {
+                MISSING_CHARACTER_REPLACEMENT
+            };
+
+            ascii_grid.set_cell(
+                i as u32,
+                j as u32,
+                Some(AsciiCell {
+                    character,
+                    foreground: cell_foreground,
+                    background: cell_background
+                        .map(|color| Rgba([color[0], color[1], color[2], 255])),
+                    luminance: cell_foreground.to_luma()[0],
+                }),
+            );
+        }
+    }
+
+    return ascii_grid;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ansi::get_ansi_256_color;
+
+    #[test]
+    fn text_to_grid_layout() {
+        let ascii_grid = text_to_ascii_grid("ab\nc", CharacterType::Simple, SettingOption::rgb(1));
+        assert_eq!((ascii_grid.num_rows, ascii_grid.num_cols), (2, 2));
+        assert_eq!(ascii_grid.get_cell(0, 1).unwrap().character, 'b');
+        // the short line is padded with empty cells
+        assert!(ascii_grid.get_cell(1, 1).is_none());
+        assert_eq!(ascii_grid.to_text(), "ab\nc \n");
+    }
+
+    #[test]
+    fn text_to_grid_color() {
+        let text = "\x1b[31ma\x1b[0mb";
+        let ascii_grid = text_to_ascii_grid(text, CharacterType::Simple, SettingOption::rgb(1));
+        let red = get_ansi_256_color(1);
+        assert_eq!(
+            ascii_grid.get_cell(0, 0).unwrap().foreground,
+            Rgba([red[0], red[1], red[2], 255])
+        );
+        assert_eq!(
+            ascii_grid.get_cell(0, 1).unwrap().foreground,
+            Rgba([255, 255, 255, 255])
+        );
+
+        // the ANSI colors are left out of a grayscale output
+        let ascii_grid =
+            text_to_ascii_grid(text, CharacterType::Simple, SettingOption::grayscale(1));
+        assert_eq!(
+            ascii_grid.get_cell(0, 0).unwrap().foreground,
+            Rgba([255, 255, 255, 255])
+        );
+        assert_eq!(ascii_grid.to_text(), "ab\n");
+    }
+
+    #[test]
+    fn text_to_grid_background() {
+        let mut setting_option = SettingOption::grayscale(1).with_transparent_bg(true);
+        setting_option.is_white_bg = true;
+        let ascii_grid = text_to_ascii_grid("a", CharacterType::Simple, setting_option);
+        assert_eq!(ascii_grid.background, Rgba([255, 255, 255, 0]));
+        assert_eq!(
+            ascii_grid.get_cell(0, 0).unwrap().foreground,
+            Rgba([0, 0, 0, 255])
+        );
+    }
+
+    #[test]
+    fn replace_missing_character() {
+        let ascii_grid =
+            text_to_ascii_grid("a\u{10FFFD}", CharacterType::Simple, SettingOption::rgb(1));
+        assert_eq!(
+            ascii_grid.get_cell(0, 1).unwrap().character,
+            MISSING_CHARACTER_REPLACEMENT
+        );
+    }
+
+    #[test]
+    fn text_to_img_size() {
+        let one_line = text_to_ascii_img("ab", CharacterType::Simple, SettingOption::rgb(1));
+        let two_line = text_to_ascii_img("ab\nab", CharacterType::Simple, SettingOption::rgb(1));
+        assert_eq!(two_line.width(), one_line.width());
+        assert_eq!(two_line.height(), 2 * one_line.height());
+    }
+}
@@ -1,8 +1,58 @@
-#[derive(Clone, Copy)]
+use std::sync::Arc;
+
+use crate::{config::DEFAULT_NUM_COLS, palette::Palette, preprocess::Preprocess};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// the default number of GIF frames that will be decoded, converted and encoded together at a time
+pub const DEFAULT_FRAME_WINDOW: usize = 16;
+// by default the alpha handling is off and every cell is drawn with the color it was sampled with,
+// set a threshold with `with_alpha_threshold()` to skip or fade the transparent cells
+pub const DEFAULT_ALPHA_THRESHOLD: u8 = 0;
+
+// how the cells with an alpha below `alpha_threshold` are drawn
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum AlphaMode {
+    Skip, // leave the cell empty
+    Fade, // draw the character with the alpha of the cell
+}
+
+// new options are added over time, so create it with `rgb()`, `grayscale()` or `default()` and the `with_*` methods,
+// every field missing from a config file keep the value of `SettingOption::default()`
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
 pub struct SettingOption {
     pub num_cols: u32,
     pub is_white_bg: bool,
     pub is_color: bool,
+    // number of GIF frames that are held in memory at once while processing a GIF
+    pub frame_window: usize,
+    // which frames of a GIF will be converted
+    pub frame_selection: FrameSelection,
+    // leave the background of rgb ascii art transparent instead of filling it
+    pub is_transparent_bg: bool,
+    // cells of the image with an alpha below this value are handled based on `alpha_mode`
+    pub alpha_threshold: u8,
+    pub alpha_mode: AlphaMode,
+    // colors of grayscale ascii art, `None` means black and white based on `is_white_bg`
+    pub color_theme: Option<ColorTheme>,
+    // fill the background of every cell of rgb ascii art with its color, `None` means only the character is colored
+    pub cell_background: Option<CellBackground>,
+    // restrict the colors of rgb ascii art to a palette, `None` means the exact color of every cell is used
+    //
+    // it is shared, so cloning the setting option for every frame or thread doesn't copy the colors
+    pub palette: Option<Arc<Palette>>,
+    // adjust the brightness, contrast and gamma of the image before it is converted
+    pub tone: Tone,
+    // crop, rotate, flip, scale and pad the image before it is converted
+    pub preprocess: Preprocess,
 }
 
 impl SettingOption {
@@ -11,30 +61,550 @@ impl SettingOption {
             num_cols,
             is_white_bg: false,
             is_color: true,
+            frame_window: DEFAULT_FRAME_WINDOW,
+            frame_selection: FrameSelection::all(),
+            is_transparent_bg: false,
+            alpha_threshold: DEFAULT_ALPHA_THRESHOLD,
+            alpha_mode: AlphaMode::Skip,
+            color_theme: None,
+            cell_background: None,
+            palette: None,
+            tone: Tone::default(),
+            preprocess: Preprocess::default(),
         };
     }
 
     pub fn rgb_white_bg(num_cols: u32) -> SettingOption {
         return SettingOption {
-            num_cols,
             is_white_bg: true,
-            is_color: true,
+            ..SettingOption::rgb(num_cols)
         };
     }
 
     pub fn grayscale(num_cols: u32) -> SettingOption {
         return SettingOption {
-            num_cols,
-            is_white_bg: false,
             is_color: false,
+            ..SettingOption::rgb(num_cols)
         };
     }
 
     pub fn grayscale_white_bg(num_cols: u32) -> SettingOption {
         return SettingOption {
-            num_cols,
             is_white_bg: true,
             is_color: false,
+            ..SettingOption::rgb(num_cols)
+        };
+    }
+
+    // set the number of GIF frames that are decoded, converted and encoded together at a time,
+    // a larger window uses more memory but keeps more threads busy
+    pub fn with_frame_window(mut self, frame_window: usize) -> SettingOption {
+        self.frame_window = frame_window.max(1);
+        return self;
+    }
+
+    // set which frames of a GIF will be converted
+    pub fn with_frame_selection(mut self, frame_selection: FrameSelection) -> SettingOption {
+        self.frame_selection = frame_selection;
+        return self;
+    }
+
+    // leave the background of rgb ascii art transparent, `is_white_bg` still decide the color
+    // that the edge of the characters blend into
+    pub fn with_transparent_bg(mut self, is_transparent_bg: bool) -> SettingOption {
+        self.is_transparent_bg = is_transparent_bg;
+        return self;
+    }
+
+    // set how the cells with an alpha below `alpha_threshold` are drawn,
+    // cells at or above the threshold are always drawn opaque, a threshold of 0 turn the alpha handling off
+    pub fn with_alpha_threshold(
+        mut self,
+        alpha_threshold: u8,
+        alpha_mode: AlphaMode,
+    ) -> SettingOption {
+        self.alpha_threshold = alpha_threshold;
+        self.alpha_mode = alpha_mode;
+        return self;
+    }
+
+    // set the colors of grayscale ascii art, this overrides `is_white_bg`
+    pub fn with_color_theme(mut self, color_theme: ColorTheme) -> SettingOption {
+        self.color_theme = Some(color_theme);
+        return self;
+    }
+
+    // fill the background of every cell of rgb ascii art with the color of the cell
+    pub fn with_cell_background(mut self, cell_background: CellBackground) -> SettingOption {
+        self.cell_background = Some(cell_background);
+        return self;
+    }
+
+    // restrict the colors of rgb ascii art to the palette
+    pub fn with_palette(mut self, palette: Palette) -> SettingOption {
+        self.palette = Some(Arc::new(palette));
+        return self;
+    }
+
+    // adjust the brightness, contrast and gamma of the image before it is converted
+    pub fn with_tone(mut self, tone: Tone) -> SettingOption {
+        self.tone = tone;
+        return self;
+    }
+
+    // crop, rotate, flip, scale and pad the image before it is converted
+    pub fn with_preprocess(mut self, preprocess: Preprocess) -> SettingOption {
+        self.preprocess = preprocess;
+        return self;
+    }
+
+    // check if the setting can be used for conversion
+    pub fn validate(&self) -> Result<(), String> {
+        if self.num_cols == 0 {
+            return Err("num_cols must be greater than 0".to_string());
+        }
+        if self.frame_window == 0 {
+            return Err("frame_window must be greater than 0".to_string());
+        }
+        self.frame_selection.validate()?;
+        if let Some(cell_background) = self.cell_background {
+            if !(0.0..=1.0).contains(&cell_background.darken) {
+                return Err(format!(
+                    "cell_background darken must be between 0.0 and 1.0, got {}",
+                    cell_background.darken
+                ));
+            }
+        }
+        self.tone.validate()?;
+        self.preprocess.validate()?;
+        return Ok(());
+    }
+}
+
+// the same default as RenderConfig::builder()
+impl Default for SettingOption {
+    fn default() -> SettingOption {
+        return SettingOption::grayscale(DEFAULT_NUM_COLS);
+    }
+}
+
+// decide which frames of a GIF will be converted
+//
+// the frames are first trimmed to `start..end`, then every `step`th frame of them is kept,
+// and at last the kept frames are resampled to `target_fps` if it was set
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct FrameSelection {
+    // index of the first frame to convert
+    pub start: usize,
+    // index of the frame to stop at (exclusive), `None` means until the last frame
+    pub end: Option<usize>,
+    // keep every `step`th frame, the skipped frames' delay is added to the kept frame
+    pub step: usize,
+    // resample the frames to this frame rate by dropping or duplicating frames
+    pub target_fps: Option<f32>,
+}
+
+impl FrameSelection {
+    pub fn all() -> FrameSelection {
+        return FrameSelection {
+            start: 0,
+            end: None,
+            step: 1,
+            target_fps: None,
+        };
+    }
+
+    pub fn range(start: usize, end: usize) -> FrameSelection {
+        return FrameSelection {
+            start,
+            end: Some(end),
+            ..FrameSelection::all()
+        };
+    }
+
+    pub fn every_nth(step: usize) -> FrameSelection {
+        return FrameSelection {
+            step: step.max(1),
+            ..FrameSelection::all()
+        };
+    }
+
+    pub fn fps(target_fps: f32) -> FrameSelection {
+        return FrameSelection {
+            target_fps: Some(target_fps),
+            ..FrameSelection::all()
+        };
+    }
+
+    pub fn with_range(mut self, start: usize, end: usize) -> FrameSelection {
+        self.start = start;
+        self.end = Some(end);
+        return self;
+    }
+
+    pub fn with_step(mut self, step: usize) -> FrameSelection {
+        self.step = step.max(1);
+        return self;
+    }
+
+    pub fn with_target_fps(mut self, target_fps: f32) -> FrameSelection {
+        self.target_fps = Some(target_fps);
+        return self;
+    }
+
+    // check if the selection can be used for conversion
+    pub fn validate(&self) -> Result<(), String> {
+        if self.step == 0 {
+            return Err("frame selection step must be greater than 0".to_string());
+        }
+        if let Some(end) = self.end {
+            if end <= self.start {
+                return Err(format!(
+                    "frame selection end ({}) must be greater than start ({})",
+                    end, self.start
+                ));
+            }
+        }
+        if let Some(target_fps) = self.target_fps {
+            if !target_fps.is_finite() || target_fps <= 0.0 {
+                return Err(format!(
+                    "frame selection target fps must be greater than 0, got {}",
+                    target_fps
+                ));
+            }
+        }
+        return Ok(());
+    }
+}
+
+impl Default for FrameSelection {
+    fn default() -> FrameSelection {
+        return FrameSelection::all();
+    }
+}
+
+// the colors used for grayscale ascii art, the gray levels are mapped to a gradient
+// from the background color (level 0) to the foreground color (level 255)
+//
+// in a config file it can also be the name of a theme, e.g. "amber"
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "ColorThemeValue")
+)]
+pub struct ColorTheme {
+    pub background: [u8; 3],
+    pub foreground: [u8; 3],
+}
+
+impl ColorTheme {
+    pub fn new(background: [u8; 3], foreground: [u8; 3]) -> ColorTheme {
+        return ColorTheme {
+            background,
+            foreground,
+        };
+    }
+
+    pub fn black_and_white() -> ColorTheme {
+        return ColorTheme::new([0, 0, 0], [255, 255, 255]);
+    }
+
+    pub fn white_and_black() -> ColorTheme {
+        return ColorTheme::new([255, 255, 255], [0, 0, 0]);
+    }
+
+    // green text of old monochrome monitors
+    pub fn green_phosphor() -> ColorTheme {
+        return ColorTheme::new([5, 20, 5], [51, 255, 102]);
+    }
+
+    // amber text of old monochrome monitors
+    pub fn amber() -> ColorTheme {
+        return ColorTheme::new([20, 10, 0], [255, 176, 0]);
+    }
+
+    pub fn sepia() -> ColorTheme {
+        return ColorTheme::new([44, 28, 16], [240, 222, 186]);
+    }
+
+    // get a named theme, e.g. "green_phosphor"
+    pub fn from_name(name: &str) -> Option<ColorTheme> {
+        match name.to_lowercase().replace(['-', ' '], "_").as_str() {
+            "black_and_white" => return Some(ColorTheme::black_and_white()),
+            "white_and_black" => return Some(ColorTheme::white_and_black()),
+            "green_phosphor" => return Some(ColorTheme::green_phosphor()),
+            "amber" => return Some(ColorTheme::amber()),
+            "sepia" => return Some(ColorTheme::sepia()),
+            _ => return None,
+        }
+    }
+
+    // get the color of a gray level on the gradient from background to foreground
+    pub fn get_color(&self, level: u8) -> [u8; 3] {
+        let ratio = level as f32 / 255.0;
+        let mut color = [0; 3];
+        for (i, channel) in color.iter_mut().enumerate() {
+            *channel = (self.background[i] as f32 * (1.0 - ratio)
+                + self.foreground[i] as f32 * ratio)
+                .round() as u8;
+        }
+        return color;
+    }
+
+    // the color map of the gradient, where the index of every color is its gray level
+    pub fn get_color_map(&self) -> Vec<u8> {
+        let mut color_map: Vec<u8> = Vec::with_capacity(256 * 3);
+        for level in 0..=255 {
+            color_map.extend_from_slice(&self.get_color(level));
+        }
+        return color_map;
+    }
+}
+
+// a color theme in a config file, either the name of a theme or its colors
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColorThemeValue {
+    Name(String),
+    Color {
+        background: [u8; 3],
+        foreground: [u8; 3],
+    },
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<ColorThemeValue> for ColorTheme {
+    type Error = String;
+
+    fn try_from(value: ColorThemeValue) -> Result<ColorTheme, String> {
+        match value {
+            ColorThemeValue::Name(name) => {
+                return ColorTheme::from_name(&name)
+                    .ok_or_else(|| format!("Unknown color theme: {}", name));
+            }
+            ColorThemeValue::Color {
+                background,
+                foreground,
+            } => return Ok(ColorTheme::new(background, foreground)),
+        }
+    }
+}
+
+// the color of the character drawn over a cell background
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum GlyphShade {
+    Sampled,  // the color of the cell
+    Brighter, // the color of the cell mixed with white
+    Contrast, // black or white, whichever stand out more from the cell background
+}
+
+// fill the background of every cell with its color, similar to colored ANSI art
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct CellBackground {
+    // how much darker the cell background is than the color of the cell, 0.0 keeps the color and 1.0 turns it black
+    pub darken: f32,
+    pub glyph_shade: GlyphShade,
+}
+
+impl CellBackground {
+    pub fn new(darken: f32, glyph_shade: GlyphShade) -> CellBackground {
+        return CellBackground {
+            darken: darken.clamp(0.0, 1.0),
+            glyph_shade,
         };
     }
+
+    // get the color of the cell background and the color of the character from the color of the cell
+    pub fn get_background_and_glyph_color(&self, color: [u8; 3]) -> ([u8; 3], [u8; 3]) {
+        let background = color.map(|channel| (channel as f32 * (1.0 - self.darken)).round() as u8);
+        let glyph = match self.glyph_shade {
+            GlyphShade::Sampled => color,
+            GlyphShade::Brighter => color.map(|channel| channel / 2 + 128),
+            GlyphShade::Contrast => {
+                let luma = 0.299 * background[0] as f32
+                    + 0.587 * background[1] as f32
+                    + 0.114 * background[2] as f32;
+                if luma > 127.5 {
+                    [0, 0, 0]
+                } else {
+                    [255, 255, 255]
+                }
+            }
+        };
+        return (background, glyph);
+    }
+}
+
+impl Default for CellBackground {
+    fn default() -> CellBackground {
+        return CellBackground::new(0.5, GlyphShade::Brighter);
+    }
+}
+
+// adjust the tone of the image before it is converted, applied to every rgb channel
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct Tone {
+    // added to every channel, from -1.0 (black) to 1.0 (white)
+    pub brightness: f32,
+    // how far every channel is stretched from the middle gray, 1.0 keeps the image as it is
+    pub contrast: f32,
+    // gamma correction, a value above 1.0 brighten the dark area
+    pub gamma: f32,
+    // turn the image to its negative
+    pub invert: bool,
+}
+
+impl Tone {
+    pub fn new(brightness: f32, contrast: f32, gamma: f32, invert: bool) -> Tone {
+        return Tone {
+            brightness,
+            contrast,
+            gamma,
+            invert,
+        };
+    }
+
+    pub fn is_identity(&self) -> bool {
+        return *self == Tone::default();
+    }
+
+    // check if the tone can be used for conversion
+    pub fn validate(&self) -> Result<(), String> {
+        if !(-1.0..=1.0).contains(&self.brightness) {
+            return Err(format!(
+                "tone brightness must be between -1.0 and 1.0, got {}",
+                self.brightness
+            ));
+        }
+        if !self.contrast.is_finite() || self.contrast < 0.0 {
+            return Err(format!(
+                "tone contrast must not be negative, got {}",
+                self.contrast
+            ));
+        }
+        if !self.gamma.is_finite() || self.gamma <= 0.0 {
+            return Err(format!(
+                "tone gamma must be greater than 0, got {}",
+                self.gamma
+            ));
+        }
+        return Ok(());
+    }
+
+    // the adjusted value of every channel value, so the adjustment is only calculated 256 times
+    pub fn get_lookup_table(&self) -> [u8; 256] {
+        let mut lookup_table = [0; 256];
+        for (value, adjusted_value) in lookup_table.iter_mut().enumerate() {
+            let mut value = value as f32 / 255.0;
+            value = value.powf(1.0 / self.gamma);
+            value = (value - 0.5) * self.contrast + 0.5 + self.brightness;
+            value = value.clamp(0.0, 1.0);
+            if self.invert {
+                value = 1.0 - value;
+            }
+            *adjusted_value = (value * 255.0).round() as u8;
+        }
+        return lookup_table;
+    }
+}
+
+impl Default for Tone {
+    fn default() -> Tone {
+        return Tone::new(0.0, 1.0, 1.0, false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_theme_gradient() {
+        let color_theme = ColorTheme::new([0, 0, 64], [255, 255, 0]);
+        assert_eq!(color_theme.get_color(0), [0, 0, 64]);
+        assert_eq!(color_theme.get_color(255), [255, 255, 0]);
+
+        let color_map = color_theme.get_color_map();
+        assert_eq!(color_map.len(), 256 * 3);
+        assert_eq!(&color_map[255 * 3..], &[255, 255, 0]);
+    }
+
+    #[test]
+    fn color_theme_from_name() {
+        assert_eq!(
+            ColorTheme::from_name("Green-Phosphor"),
+            Some(ColorTheme::green_phosphor())
+        );
+        assert_eq!(ColorTheme::from_name("unknown"), None);
+    }
+
+    #[test]
+    fn cell_background_color() {
+        let cell_background = CellBackground::new(0.5, GlyphShade::Sampled);
+        assert_eq!(
+            cell_background.get_background_and_glyph_color([200, 100, 50]),
+            ([100, 50, 25], [200, 100, 50])
+        );
+
+        let cell_background = CellBackground::new(0.0, GlyphShade::Contrast);
+        assert_eq!(
+            cell_background
+                .get_background_and_glyph_color([250, 250, 250])
+                .1,
+            [0, 0, 0]
+        );
+        assert_eq!(
+            cell_background
+                .get_background_and_glyph_color([10, 10, 10])
+                .1,
+            [255, 255, 255]
+        );
+
+        // the darken is clamped
+        assert_eq!(CellBackground::new(2.0, GlyphShade::Brighter).darken, 1.0);
+    }
+
+    #[test]
+    fn tone_lookup_table() {
+        let lookup_table = Tone::default().get_lookup_table();
+        assert!((0..=255).all(|value| lookup_table[value] == value as u8));
+
+        let lookup_table = Tone::new(0.0, 1.0, 1.0, true).get_lookup_table();
+        assert_eq!(lookup_table[0], 255);
+        assert_eq!(lookup_table[255], 0);
+
+        let lookup_table = Tone::new(0.5, 1.0, 1.0, false).get_lookup_table();
+        assert_eq!(lookup_table[0], 128);
+        assert_eq!(lookup_table[200], 255);
+
+        // a gamma above 1.0 brighten the dark area
+        let lookup_table = Tone::new(0.0, 1.0, 2.0, false).get_lookup_table();
+        assert!(lookup_table[64] > 64);
+    }
+
+    #[test]
+    fn validate_setting_option() {
+        assert!(SettingOption::rgb(100).validate().is_ok());
+        assert!(SettingOption::rgb(0).validate().is_err());
+
+        let mut setting_option = SettingOption::rgb(100);
+        setting_option.frame_window = 0;
+        assert!(setting_option.validate().is_err());
+
+        let setting_option = SettingOption::rgb(100).with_tone(Tone::new(0.0, -1.0, 1.0, false));
+        assert!(setting_option.validate().is_err());
+
+        let setting_option = SettingOption::rgb(100).with_frame_selection(FrameSelection::fps(0.0));
+        assert!(setting_option.validate().is_err());
+    }
 }
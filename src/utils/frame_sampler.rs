@@ -0,0 +1,386 @@
+use gif::{DisposalMethod, Frame};
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use crate::types::FrameSelection;
+
+// the delay (in 1/100 of a second) that most viewers use for a frame with no delay
+pub const DEFAULT_GIF_FRAME_DELAY: u16 = 10;
+
+// a frame that was selected to be converted, as the full logical screen of the GIF
+// the frame is written to the output once for every delay in `delay_list`
+pub struct SampledFrame {
+    pub img: DynamicImage,
+    pub delay_list: Vec<u16>,
+}
+
+// draw the frames of a GIF on its logical screen, as a frame of an optimized GIF often only update part of it
+//
+// the frames must be decoded as RGBA, the screen start fully transparent like `image`'s GifDecoder
+pub struct GifCompositor {
+    canvas: RgbaImage,
+    // the area of the last frame, cleared before the next frame is drawn (`DisposalMethod::Background`)
+    clear_area: Option<(u32, u32, u32, u32)>,
+    // the screen before the last frame was drawn, restored before the next frame is drawn (`DisposalMethod::Previous`)
+    previous_canvas: Option<RgbaImage>,
+}
+
+impl GifCompositor {
+    pub fn new(width: u16, height: u16) -> GifCompositor {
+        return GifCompositor {
+            canvas: RgbaImage::new(width as u32, height as u32),
+            clear_area: None,
+            previous_canvas: None,
+        };
+    }
+
+    // draw the next frame and return the whole screen, the part of the frame outside of the screen is dropped
+    pub fn composite(&mut self, frame: &Frame<'_>) -> &RgbaImage {
+        if let Some(previous_canvas) = self.previous_canvas.take() {
+            self.canvas = previous_canvas;
+        } else if let Some((left, top, width, height)) = self.clear_area.take() {
+            for y in top..(top + height).min(self.canvas.height()) {
+                for x in left..(left + width).min(self.canvas.width()) {
+                    self.canvas.put_pixel(x, y, Rgba([0, 0, 0, 0]));
+                }
+            }
+        }
+
+        let (left, top) = (frame.left as u32, frame.top as u32);
+        let (width, height) = (frame.width as u32, frame.height as u32);
+        match frame.dispose {
+            DisposalMethod::Previous => self.previous_canvas = Some(self.canvas.clone()),
+            DisposalMethod::Background => self.clear_area = Some((left, top, width, height)),
+            _ => {}
+        }
+
+        // a transparent pixel of the frame show the screen under it
+        for (index, pixel) in frame.buffer.chunks_exact(4).enumerate() {
+            if pixel[3] == 0 {
+                continue;
+            }
+            let x = left + index as u32 % width.max(1);
+            let y = top + index as u32 / width.max(1);
+            if x < self.canvas.width() && y < self.canvas.height() {
+                self.canvas
+                    .put_pixel(x, y, Rgba([pixel[0], pixel[1], pixel[2], pixel[3]]));
+            }
+        }
+        return &self.canvas;
+    }
+}
+
+// pick the frames of a GIF based on a FrameSelection while the frames are being decoded
+//
+// every frame is composited on the logical screen, and the selection is left to a FrameSelector
+pub struct FrameSampler {
+    compositor: GifCompositor,
+    frame_selector: FrameSelector<DynamicImage>,
+}
+
+impl FrameSampler {
+    // the frames are composited on a logical screen of `width` x `height`, the size of the GIF
+    pub fn new(frame_selection: FrameSelection, width: u16, height: u16) -> FrameSampler {
+        return FrameSampler {
+            compositor: GifCompositor::new(width, height),
+            frame_selector: FrameSelector::new(frame_selection),
+        };
+    }
+
+    // no more frames are needed once the end of the selected range was reached
+    pub fn is_finished(&self) -> bool {
+        return self.frame_selector.is_finished();
+    }
+
+    // push the next decoded frame, return the previous kept frame once its delay is known
+    //
+    // every frame is drawn on the screen even if it is skipped, as the frames after it are drawn over it
+    pub fn push(&mut self, frame: &Frame<'_>) -> Option<SampledFrame> {
+        let img = self.compositor.composite(frame);
+        return self
+            .frame_selector
+            .push(frame.delay, || DynamicImage::ImageRgba8(img.clone()))
+            .map(|(img, delay_list)| SampledFrame { img, delay_list });
+    }
+
+    // there is no frame left, return the last kept frame
+    pub fn finish(&mut self) -> Option<SampledFrame> {
+        return self
+            .frame_selector
+            .finish()
+            .map(|(img, delay_list)| SampledFrame { img, delay_list });
+    }
+}
+
+// decide which frames are kept and the delay of their output frames, from the delay of every frame
+//
+// a kept frame is held back with its `T` until the next kept frame arrives,
+// as the delay of the skipped frames in between need to be added to it
+pub struct FrameSelector<T> {
+    frame_selection: FrameSelection,
+    // index of the next frame that will be pushed
+    index: usize,
+    // the last kept frame and its delay so far
+    pending: Option<(T, u32)>,
+    // the time (in 1/100 of a second) where the pending frame start, used for resampling
+    elapsed: f64,
+    // the next output frame when resampling
+    next_tick: u64,
+    finished: bool,
+}
+
+impl<T> FrameSelector<T> {
+    pub fn new(frame_selection: FrameSelection) -> FrameSelector<T> {
+        return FrameSelector {
+            frame_selection,
+            index: 0,
+            pending: None,
+            elapsed: 0.0,
+            next_tick: 0,
+            finished: false,
+        };
+    }
+
+    // no more frames are needed once the end of the selected range was reached
+    pub fn is_finished(&self) -> bool {
+        if self.finished {
+            return true;
+        }
+        match self.frame_selection.end {
+            Some(end) => return self.index >= end,
+            None => return false,
+        }
+    }
+
+    // push the delay of the next frame, `get_frame` is only called if the frame is kept,
+    // return the previous kept frame and the delay of every one of its output frames once they are known
+    pub fn push<F: FnOnce() -> T>(&mut self, delay: u16, get_frame: F) -> Option<(T, Vec<u16>)> {
+        let index = self.index;
+        self.index += 1;
+
+        if index < self.frame_selection.start
+            || index >= self.frame_selection.end.unwrap_or(usize::MAX)
+        {
+            return None;
+        }
+
+        let step = self.frame_selection.step.max(1);
+        if !(index - self.frame_selection.start).is_multiple_of(step) {
+            // a skipped frame, its time goes to the kept frame before it
+            if let Some((_, pending_delay)) = self.pending.as_mut() {
+                *pending_delay += delay as u32;
+            }
+            return None;
+        }
+
+        let previous = self.pending.take();
+        self.pending = Some((get_frame(), delay as u32));
+
+        return previous.and_then(|(frame, delay)| self.sample(frame, delay));
+    }
+
+    // there is no frame left, return the last kept frame
+    pub fn finish(&mut self) -> Option<(T, Vec<u16>)> {
+        self.finished = true;
+        let pending = self.pending.take();
+
+        return pending.and_then(|(frame, delay)| self.sample(frame, delay));
+    }
+
+    // get the delay of every output frame of the kept frame, when resampling,
+    // the output frames are spaced evenly in time so a frame might be dropped or duplicated
+    fn sample(&mut self, frame: T, delay: u32) -> Option<(T, Vec<u16>)> {
+        let target_fps = self
+            .frame_selection
+            .target_fps
+            .filter(|target_fps| *target_fps > 0.0);
+
+        let delay_list: Vec<u16> = match target_fps {
+            Some(target_fps) => {
+                let delay = if delay == 0 {
+                    DEFAULT_GIF_FRAME_DELAY as f64
+                } else {
+                    delay as f64
+                };
+                let tick = 100.0 / target_fps as f64;
+                let end = self.elapsed + delay;
+                self.elapsed = end;
+
+                let mut delay_list = vec![];
+                while (self.next_tick as f64) * tick < end {
+                    let tick_start = ((self.next_tick as f64) * tick).round();
+                    let tick_end = ((self.next_tick + 1) as f64 * tick).round();
+                    delay_list.push((tick_end - tick_start).min(u16::MAX as f64) as u16);
+                    self.next_tick += 1;
+                }
+                delay_list
+            }
+            None => vec![delay.min(u16::MAX as u32) as u16],
+        };
+
+        if delay_list.is_empty() {
+            // the frame was dropped by the resampling
+            return None;
+        }
+
+        return Some((frame, delay_list));
+    }
+}
+
+// the delay of every gif frame in hundredths of a second, the rounding is carried over
+// so the animation keep the timing of the frame rate
+pub struct GifDelayTimer {
+    frame_rate: f32,
+    num_frames: u64,
+    elapsed: u64,
+}
+
+impl GifDelayTimer {
+    pub fn new(frame_rate: f32) -> GifDelayTimer {
+        return GifDelayTimer {
+            frame_rate: frame_rate.max(0.01),
+            num_frames: 0,
+            elapsed: 0,
+        };
+    }
+
+    pub fn next_delay(&mut self) -> u16 {
+        self.num_frames += 1;
+        let end = (self.num_frames as f64 * 100.0 / self.frame_rate as f64).round() as u64;
+        let delay = end - self.elapsed;
+        self.elapsed = end;
+        return delay.min(u16::MAX as u64) as u16;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a 1x1 RGBA frame with the index as its red value, as the decoder give them
+    fn index_frame(index: usize, delay: u16) -> Frame<'static> {
+        return Frame {
+            width: 1,
+            height: 1,
+            delay,
+            buffer: vec![index as u8, 0, 0, 255].into(),
+            ..Frame::default()
+        };
+    }
+
+    // push a frame with every delay of `delay_list`,
+    // return the index of the kept frames and the delays of their output frames
+    fn select_frames(
+        frame_selection: FrameSelection,
+        delay_list: &[u16],
+    ) -> Vec<(usize, Vec<u16>)> {
+        let mut frame_sampler = FrameSampler::new(frame_selection, 1, 1);
+        let mut sampled_list = vec![];
+        for (index, delay) in delay_list.iter().enumerate() {
+            if frame_sampler.is_finished() {
+                break;
+            }
+            sampled_list.extend(frame_sampler.push(&index_frame(index, *delay)));
+        }
+        sampled_list.extend(frame_sampler.finish());
+
+        return sampled_list
+            .into_iter()
+            .map(|sampled_frame| {
+                let index = sampled_frame.img.to_rgba8().get_pixel(0, 0)[0] as usize;
+                (index, sampled_frame.delay_list)
+            })
+            .collect();
+    }
+
+    #[test]
+    fn select_every_frame() {
+        let selected_list = select_frames(FrameSelection::all(), &[10, 20, 30]);
+        assert_eq!(
+            selected_list,
+            vec![(0, vec![10]), (1, vec![20]), (2, vec![30])]
+        );
+    }
+
+    #[test]
+    fn select_every_nth_frame() {
+        let selected_list = select_frames(FrameSelection::every_nth(2), &[10, 20, 30, 40, 50]);
+        assert_eq!(
+            selected_list,
+            vec![(0, vec![30]), (2, vec![70]), (4, vec![50])]
+        );
+    }
+
+    #[test]
+    fn select_frame_range() {
+        let selected_list = select_frames(
+            FrameSelection::range(1, 4).with_step(2),
+            &[10, 20, 30, 40, 50],
+        );
+        assert_eq!(selected_list, vec![(1, vec![50]), (3, vec![40])]);
+    }
+
+    #[test]
+    fn resample_to_lower_fps() {
+        // 20 fps to 10 fps drops every other frame
+        let selected_list = select_frames(FrameSelection::fps(10.0), &[5, 5, 5, 5]);
+        assert_eq!(selected_list, vec![(0, vec![10]), (2, vec![10])]);
+    }
+
+    #[test]
+    fn resample_to_higher_fps() {
+        // 10 fps to 30 fps repeat every frame 3 times, the rounding is spread over the output frames
+        let selected_list = select_frames(FrameSelection::fps(30.0), &[10, 0]);
+        assert_eq!(selected_list, vec![(0, vec![3, 4, 3]), (1, vec![3, 4, 3])]);
+    }
+
+    #[test]
+    fn sample_composited_frames() {
+        let mut frame_sampler = FrameSampler::new(FrameSelection::every_nth(2), 2, 1);
+        let mut sampled_list = vec![];
+        for (index, delay) in [10, 20, 30].iter().enumerate() {
+            // a 1x1 frame in the left or right pixel of the screen
+            let frame = Frame {
+                left: index as u16 % 2,
+                ..index_frame(index * 100, *delay)
+            };
+            sampled_list.extend(frame_sampler.push(&frame));
+        }
+        sampled_list.extend(frame_sampler.finish());
+
+        assert_eq!(sampled_list.len(), 2);
+        assert_eq!(sampled_list[0].delay_list, vec![30]);
+        assert_eq!(sampled_list[1].delay_list, vec![30]);
+        // the skipped frame is still drawn on the screen under the last frame
+        let rgba_img = sampled_list[1].img.to_rgba8();
+        assert_eq!(rgba_img.get_pixel(0, 0).0, [200, 0, 0, 255]);
+        assert_eq!(rgba_img.get_pixel(1, 0).0, [100, 0, 0, 255]);
+    }
+
+    #[test]
+    fn restore_previous_screen() {
+        let mut compositor = GifCompositor::new(1, 1);
+        compositor.composite(&index_frame(10, 0));
+        let frame = Frame {
+            dispose: DisposalMethod::Previous,
+            ..index_frame(20, 0)
+        };
+        assert_eq!(compositor.composite(&frame).get_pixel(0, 0)[0], 20);
+
+        // a fully transparent frame show the screen from before the disposed frame
+        let transparent_frame = Frame {
+            buffer: vec![0, 0, 0, 0].into(),
+            ..index_frame(0, 0)
+        };
+        assert_eq!(
+            compositor.composite(&transparent_frame).get_pixel(0, 0)[0],
+            10
+        );
+    }
+
+    #[test]
+    fn carry_gif_delay_rounding() {
+        let mut delay_timer = GifDelayTimer::new(30.0);
+        let delay_list: Vec<u16> = (0..6).map(|_| delay_timer.next_delay()).collect();
+        assert_eq!(delay_list, vec![3, 4, 3, 3, 4, 3]);
+    }
+}
@@ -1,2 +1,5 @@
 pub mod font;
+pub mod frame_sampler;
+pub mod parallel;
+pub mod progress_tracker;
 pub mod utils;
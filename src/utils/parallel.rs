@@ -0,0 +1,32 @@
+// the rayon parallel iterators, or the same `par_iter` on the plain iterators when the `parallel`
+// feature is off (e.g. for wasm32-unknown-unknown which has no threads)
+#[cfg(feature = "parallel")]
+pub use rayon::prelude::*;
+
+#[cfg(not(feature = "parallel"))]
+pub trait IntoParallelRefIterator<'data> {
+    type Iter: Iterator;
+
+    fn par_iter(&'data self) -> Self::Iter;
+}
+
+#[cfg(not(feature = "parallel"))]
+impl<'data, T: 'data> IntoParallelRefIterator<'data> for [T] {
+    type Iter = std::slice::Iter<'data, T>;
+
+    fn par_iter(&'data self) -> Self::Iter {
+        return self.iter();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn par_iter_keep_order() {
+        let values = [1, 2, 3, 4];
+        let doubled: Vec<i32> = values.par_iter().map(|v| v * 2).collect();
+        assert_eq!(doubled, vec![2, 4, 6, 8]);
+    }
+}
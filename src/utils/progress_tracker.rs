@@ -0,0 +1,58 @@
+use std::sync::Mutex;
+
+use crate::progress::{GifProgress, ProgressOption, CANCELLED_ERROR};
+
+// count the frames of a conversion and report them to the observer of the ProgressOption
+//
+// the frames are converted in parallel, so the counts are kept behind a mutex
+pub struct ProgressTracker<'a> {
+    progress_option: ProgressOption<'a>,
+    progress: Mutex<GifProgress>,
+}
+
+impl<'a> ProgressTracker<'a> {
+    pub fn new(progress_option: ProgressOption<'a>) -> ProgressTracker<'a> {
+        return ProgressTracker {
+            progress_option,
+            progress: Mutex::new(GifProgress {
+                total_frames: progress_option.total_frames,
+                ..GifProgress::default()
+            }),
+        };
+    }
+
+    // return `Err(CANCELLED_ERROR)` once the conversion was cancelled
+    pub fn check_cancelled(&self) -> Result<(), String> {
+        if let Some(cancellation_token) = self.progress_option.cancellation_token {
+            if cancellation_token.is_cancelled() {
+                return Err(CANCELLED_ERROR.to_string());
+            }
+        }
+        return Ok(());
+    }
+
+    pub fn add_decoded_frames(&self, num_frames: usize) {
+        self.update(|progress| progress.decoded_frames += num_frames);
+    }
+
+    pub fn add_converted_frames(&self, num_frames: usize) {
+        self.update(|progress| progress.converted_frames += num_frames);
+    }
+
+    pub fn add_encoded_frames(&self, num_frames: usize) {
+        self.update(|progress| progress.encoded_frames += num_frames);
+    }
+
+    fn update<F: FnOnce(&mut GifProgress)>(&self, update: F) {
+        let observer = match self.progress_option.observer {
+            Some(observer) => observer,
+            None => return,
+        };
+
+        // the observer is called while the lock is held, so the snapshots reach it in order,
+        // a slow observer hold back the other threads
+        let mut progress = self.progress.lock().unwrap();
+        update(&mut progress);
+        observer.on_progress(*progress);
+    }
+}
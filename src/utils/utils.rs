@@ -1,20 +1,21 @@
-use std::{collections::HashMap, fs::create_dir_all, path::Path};
+use std::{any::Any, borrow::Cow, collections::HashMap, fs::create_dir_all, path::Path};
 
 use ab_glyph::{FontRef, PxScale};
-use image::{DynamicImage, GenericImageView, ImageBuffer, Luma, Pixel, Rgba};
+use image::{DynamicImage, GenericImageView, ImageBuffer, Luma, Pixel, Rgb, Rgba};
 use imageproc::drawing::draw_text_mut;
 
 use super::font::get_character_dimensions;
+use crate::types::{AlphaMode, ColorTheme, Tone};
 
 pub fn check_and_create_directory(output_directory: Option<&str>) -> Result<(), String> {
-    if output_directory.is_some() {
-        let path = Path::new(output_directory.unwrap());
+    if let Some(output_directory) = output_directory {
+        let path = Path::new(output_directory);
 
         if path.exists() {
             if !path.is_dir() {
                 return Err(format!(
                     "Path exists but is not a directory: {}",
-                    output_directory.unwrap()
+                    output_directory
                 ));
             }
         } else {
@@ -24,6 +25,71 @@ pub fn check_and_create_directory(output_directory: Option<&str>) -> Result<(),
     return Ok(());
 }
 
+// the message of a caught panic, `panic!` raise either a &str or a String
+pub fn get_panic_message(panic: &(dyn Any + Send)) -> String {
+    return panic
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Unknown panic".to_string());
+}
+
+// the path of an output file, the output directory is created if it doesn't exist
+pub fn get_output_path(
+    output_directory: Option<&str>,
+    filename: Option<&str>,
+    extension: &str,
+) -> Result<String, String> {
+    check_and_create_directory(output_directory)?;
+    let filename = match filename {
+        Some(filename) => filename,
+        None => return Err("No filename provided".to_string()),
+    };
+    if let Some(output_dir) = output_directory {
+        return Ok(format!("{}/{}.{}", output_dir, filename, extension));
+    } else {
+        return Ok(format!("{}.{}", filename, extension));
+    }
+}
+
+// the message returned once an output file is saved, e.g. `Image saved to current directory as art.png`
+pub fn get_saved_message(
+    kind: &str,
+    output_directory: Option<&str>,
+    filename: Option<&str>,
+    extension: &str,
+) -> String {
+    let filename = filename.unwrap_or_default();
+    if let Some(output_dir) = output_directory {
+        return format!(
+            "{} saved to path: {} as {}.{}",
+            kind, output_dir, filename, extension
+        );
+    } else {
+        return format!(
+            "{} saved to current directory as {}.{}",
+            kind, filename, extension
+        );
+    }
+}
+
+// adjust the tone of the image, the image is only copied if the tone change anything
+pub fn apply_tone(img: &DynamicImage, tone: Tone) -> Cow<'_, DynamicImage> {
+    if tone.is_identity() {
+        return Cow::Borrowed(img);
+    }
+
+    let lookup_table = tone.get_lookup_table();
+    let mut rgba_img = img.to_rgba8();
+    for pixel in rgba_img.pixels_mut() {
+        for channel in 0..3 {
+            pixel[channel] = lookup_table[pixel[channel] as usize];
+        }
+    }
+
+    return Cow::Owned(DynamicImage::ImageRgba8(rgba_img));
+}
+
 pub fn get_character_line_list_based_on_luma(
     character_list: Vec<char>,
     img: &DynamicImage,
@@ -55,7 +121,7 @@ pub fn get_character_line_list_based_on_luma(
 }
 
 pub fn get_character_and_rgba_based_on_rgba(
-    character_list: Vec<char>,
+    character_list: &[char],
     img: &DynamicImage,
     cell_width: u32,
     width: u32,
@@ -80,6 +146,40 @@ pub fn get_character_and_rgba_based_on_rgba(
     return (character_list[index], pixel_rgba);
 }
 
+// decide the color of a cell based on its alpha, `None` means the cell should be left empty
+// a threshold of 0 keep the sampled color as it is
+pub fn get_alpha_aware_rgba(
+    rgba: Rgba<u8>,
+    background_rgba: Rgba<u8>,
+    alpha_threshold: u8,
+    alpha_mode: AlphaMode,
+) -> Option<Rgba<u8>> {
+    if alpha_threshold == 0 {
+        return Some(rgba);
+    }
+    let alpha = rgba[3];
+    if alpha >= alpha_threshold {
+        return Some(Rgba([rgba[0], rgba[1], rgba[2], 255]));
+    }
+
+    match alpha_mode {
+        AlphaMode::Skip => return None,
+        AlphaMode::Fade => {
+            if background_rgba[3] == 0 {
+                // a transparent background keep the alpha of the cell
+                return Some(rgba);
+            }
+            // an opaque background can't be seen through, so blend the color into it instead
+            let alpha = alpha as f32 / 255.0;
+            let blend = |channel: usize| {
+                (rgba[channel] as f32 * alpha + background_rgba[channel] as f32 * (1.0 - alpha))
+                    .round() as u8
+            };
+            return Some(Rgba([blend(0), blend(1), blend(2), 255]));
+        }
+    }
+}
+
 // this was used to sort the character based on its brightness
 #[derive(Debug, Clone)]
 struct CharacterBrightness {
@@ -177,24 +277,102 @@ pub fn get_img_flatten_rgb_and_color_map(
 
 // get the flatten rgb and also the color map that we need to pass to encoder for mapping color
 pub fn get_img_flatten_gray_and_color_map(
-    img_list: &Vec<ImageBuffer<Luma<u8>, Vec<u8>>>,
+    img_list: &[ImageBuffer<Luma<u8>, Vec<u8>>],
+) -> (Vec<Vec<u8>>, Vec<u8>) {
+    return get_img_flatten_gray_and_color_map_with_color_theme(img_list, None);
+}
+
+// same as `get_img_flatten_gray_and_color_map`, the gray levels are mapped through the color theme if there is one
+pub fn get_img_flatten_gray_and_color_map_with_color_theme(
+    img_list: &[ImageBuffer<Luma<u8>, Vec<u8>>],
+    color_theme: Option<ColorTheme>,
 ) -> (Vec<Vec<u8>>, Vec<u8>) {
     let mut flatten_gray: Vec<Vec<u8>> = Vec::new();
-    let mut color_map: Vec<u8> = Vec::new();
 
-    // Step 1: Collect gray colors from all images
+    // Step 1: generate a color map of grayscale
+    let color_map: Vec<u8> = get_gray_color_map(color_theme);
+
+    // Step 2: Collect gray colors from all images
     for img in img_list {
         let mut flat_gray_list: Vec<u8> = Vec::new();
         for pixel in img.pixels() {
-            flat_gray_list.extend_from_slice(&[pixel[0], pixel[0], pixel[0]]);
+            let index = pixel[0] as usize * 3;
+            flat_gray_list.extend_from_slice(&color_map[index..index + 3]);
         }
         flatten_gray.push(flat_gray_list);
     }
 
-    // Step 2: generate a color map of grayscale
+    return (flatten_gray, color_map);
+}
+
+// the color map of grayscale, where the index of every color is its gray value
+pub fn get_gray_color_map(color_theme: Option<ColorTheme>) -> Vec<u8> {
+    if let Some(color_theme) = color_theme {
+        return color_theme.get_color_map();
+    }
+
+    let mut color_map: Vec<u8> = Vec::with_capacity(256 * 3);
     for i in 0..=255 {
         color_map.extend_from_slice(&[i, i, i]);
     }
 
-    return (flatten_gray, color_map);
+    return color_map;
+}
+
+// map every gray level of a grayscale ascii art image to the color of the theme
+pub fn apply_color_theme(
+    img: &ImageBuffer<Luma<u8>, Vec<u8>>,
+    color_theme: ColorTheme,
+) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let color_map = color_theme.get_color_map();
+
+    return ImageBuffer::from_fn(img.width(), img.height(), |x, y| {
+        let index = img.get_pixel(x, y)[0] as usize * 3;
+        Rgb([color_map[index], color_map[index + 1], color_map[index + 2]])
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+    #[test]
+    fn alpha_handling_off_by_default() {
+        let rgba = Rgba([200, 100, 50, 0]);
+        assert_eq!(
+            get_alpha_aware_rgba(rgba, BLACK, 0, AlphaMode::Skip),
+            Some(rgba)
+        );
+    }
+
+    #[test]
+    fn skip_transparent_cell() {
+        let rgba = Rgba([200, 100, 50, 10]);
+        assert_eq!(
+            get_alpha_aware_rgba(rgba, BLACK, 128, AlphaMode::Skip),
+            None
+        );
+        // a cell at the threshold is drawn opaque
+        assert_eq!(
+            get_alpha_aware_rgba(Rgba([200, 100, 50, 128]), BLACK, 128, AlphaMode::Skip),
+            Some(Rgba([200, 100, 50, 255]))
+        );
+    }
+
+    #[test]
+    fn fade_into_background() {
+        let rgba = Rgba([200, 100, 50, 51]);
+        // an opaque background is blended into
+        assert_eq!(
+            get_alpha_aware_rgba(rgba, BLACK, 128, AlphaMode::Fade),
+            Some(Rgba([40, 20, 10, 255]))
+        );
+        // a transparent background keep the alpha
+        assert_eq!(
+            get_alpha_aware_rgba(rgba, Rgba([0, 0, 0, 0]), 128, AlphaMode::Fade),
+            Some(rgba)
+        );
+    }
 }
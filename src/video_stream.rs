@@ -0,0 +1,674 @@
+use std::{
+    fs::{remove_file, File},
+    io::{stdout, BufReader, BufWriter, Read, Write},
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use gif::Frame;
+use image::{DynamicImage, GrayImage, RgbImage, RgbaImage};
+
+use crate::{
+    gif_to_gif::{luma_img_to_gif_frame, rgba_img_to_gif_frame, GifStreamEncoder},
+    grayscale_to_ascii_img, rgb_to_rgb_ascii_img,
+    terminal::Terminal,
+    utils::{
+        frame_sampler::GifDelayTimer,
+        parallel::*,
+        progress_tracker::ProgressTracker,
+        utils::{get_gray_color_map, get_output_path, get_saved_message},
+    },
+    CharacterType, ProgressOption, SettingOption,
+};
+
+// the frames per second used if the stream doesn't tell
+pub const DEFAULT_VIDEO_FRAME_RATE: f32 = 25.0;
+
+// the largest width and height of a frame, a larger stream is more likely to be corrupt than real
+pub const MAX_VIDEO_FRAME_SIZE: u32 = 16384;
+
+// the lowest frame rate that is played, a lower one is taken as this so a frame is never held for hours
+pub const MIN_VIDEO_FRAME_RATE: f32 = 0.01;
+
+// the longest Y4M header or frame header that is read, a longer one is not a valid stream
+const MAX_Y4M_HEADER_LENGTH: usize = 4096;
+
+// a stream of video frames, e.g. the output of a decoder process piped to stdin
+pub trait VideoFrameReader {
+    // the next frame, `None` once the stream ended
+    fn read_frame(&mut self) -> Result<Option<DynamicImage>, String>;
+
+    // the frames per second of the stream, `None` if it is not known
+    fn get_frame_rate(&self) -> Option<f32>;
+}
+
+// the pixel layout of a raw frame
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum RawPixelFormat {
+    Rgb24, // 3 bytes per pixel
+    Rgba,  // 4 bytes per pixel
+}
+
+impl RawPixelFormat {
+    pub fn get_bytes_per_pixel(&self) -> usize {
+        match self {
+            RawPixelFormat::Rgb24 => return 3,
+            RawPixelFormat::Rgba => return 4,
+        }
+    }
+}
+
+// read raw frames of a fixed size one after another, e.g. from `ffmpeg -f rawvideo -pix_fmt rgb24 -`
+pub struct RawFrameReader<R: Read> {
+    reader: R,
+    width: u32,
+    height: u32,
+    pixel_format: RawPixelFormat,
+    frame_rate: Option<f32>,
+}
+
+impl<R: Read> RawFrameReader<R> {
+    pub fn new(
+        reader: R,
+        width: u32,
+        height: u32,
+        pixel_format: RawPixelFormat,
+    ) -> Result<RawFrameReader<R>, String> {
+        if width == 0 || height == 0 {
+            return Err("The width and height of a raw frame must be greater than 0".to_string());
+        }
+        get_frame_buffer_size(width, height, pixel_format.get_bytes_per_pixel())?;
+        return Ok(RawFrameReader {
+            reader,
+            width,
+            height,
+            pixel_format,
+            frame_rate: None,
+        });
+    }
+
+    // a frame rate that is not greater than 0 is left unknown, the default frame rate is used instead
+    pub fn with_frame_rate(mut self, frame_rate: f32) -> RawFrameReader<R> {
+        self.frame_rate = Some(frame_rate).filter(|frame_rate| *frame_rate > 0.0);
+        return self;
+    }
+}
+
+impl<R: Read> VideoFrameReader for RawFrameReader<R> {
+    fn read_frame(&mut self) -> Result<Option<DynamicImage>, String> {
+        let frame_size = get_frame_buffer_size(
+            self.width,
+            self.height,
+            self.pixel_format.get_bytes_per_pixel(),
+        )?;
+        let mut buffer = vec![0; frame_size];
+        if !read_frame_buffer(&mut self.reader, &mut buffer)? {
+            return Ok(None);
+        }
+
+        let img = match self.pixel_format {
+            RawPixelFormat::Rgb24 => DynamicImage::ImageRgb8(
+                RgbImage::from_raw(self.width, self.height, buffer).unwrap(),
+            ),
+            RawPixelFormat::Rgba => DynamicImage::ImageRgba8(
+                RgbaImage::from_raw(self.width, self.height, buffer).unwrap(),
+            ),
+        };
+        return Ok(Some(img));
+    }
+
+    fn get_frame_rate(&self) -> Option<f32> {
+        return self.frame_rate;
+    }
+}
+
+// the chroma planes of a Y4M stream, as the number of luma pixels per chroma pixel
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Y4mColorSpace {
+    Yuv420,      // 420jpeg, 420paldv, 420mpeg2 and 420
+    Yuv422,      // 422
+    Yuv444,      // 444
+    Yuv444Alpha, // 444alpha
+    Mono,        // mono, luma only
+}
+
+impl Y4mColorSpace {
+    pub fn from_name(name: &str) -> Option<Y4mColorSpace> {
+        match name {
+            "420jpeg" | "420paldv" | "420mpeg2" | "420" => return Some(Y4mColorSpace::Yuv420),
+            "422" => return Some(Y4mColorSpace::Yuv422),
+            "444" => return Some(Y4mColorSpace::Yuv444),
+            "444alpha" => return Some(Y4mColorSpace::Yuv444Alpha),
+            "mono" => return Some(Y4mColorSpace::Mono),
+            _ => return None,
+        }
+    }
+
+    // the horizontal and vertical subsampling of the chroma planes
+    fn get_subsampling(&self) -> Option<(u32, u32)> {
+        match self {
+            Y4mColorSpace::Yuv420 => return Some((2, 2)),
+            Y4mColorSpace::Yuv422 => return Some((2, 1)),
+            Y4mColorSpace::Yuv444 | Y4mColorSpace::Yuv444Alpha => return Some((1, 1)),
+            Y4mColorSpace::Mono => return None,
+        }
+    }
+}
+
+// read the frames of a YUV4MPEG2 (.y4m) stream, e.g. from `ffmpeg -f yuv4mpegpipe -`
+//
+// only the 8-bit color spaces are supported, the colors are converted with BT.601
+pub struct Y4mReader<R: Read> {
+    reader: R,
+    pub width: u32,
+    pub height: u32,
+    pub frame_rate: Option<f32>,
+    pub color_space: Y4mColorSpace,
+    // the luma and chroma use the whole 0-255 range instead of 16-235 (`XCOLORRANGE=FULL`)
+    pub is_full_range: bool,
+}
+
+impl<R: Read> Y4mReader<R> {
+    // read the stream header
+    pub fn new(mut reader: R) -> Result<Y4mReader<R>, String> {
+        let header = read_y4m_line(&mut reader)?.ok_or("The Y4M stream is empty")?;
+        let mut param_iter = header.split(' ');
+        if param_iter.next() != Some("YUV4MPEG2") {
+            return Err("Not a Y4M stream, the header doesn't start with YUV4MPEG2".to_string());
+        }
+
+        let mut width = 0;
+        let mut height = 0;
+        let mut frame_rate = None;
+        let mut color_space = Y4mColorSpace::Yuv420;
+        let mut is_full_range = false;
+        for param in param_iter.filter(|param| !param.is_empty()) {
+            // the tag is a single character, which might not be ASCII in a corrupt header
+            let mut char_iter = param.chars();
+            let tag = char_iter.next();
+            let value = char_iter.as_str();
+            match tag {
+                Some('W') => {
+                    width = value
+                        .parse()
+                        .map_err(|_| format!("Invalid Y4M width: {}", value))?
+                }
+                Some('H') => {
+                    height = value
+                        .parse()
+                        .map_err(|_| format!("Invalid Y4M height: {}", value))?
+                }
+                Some('F') => frame_rate = parse_ratio(value).filter(|frame_rate| *frame_rate > 0.0),
+                Some('C') => {
+                    color_space = Y4mColorSpace::from_name(value)
+                        .ok_or(format!("Unsupported Y4M color space: {}", value))?
+                }
+                Some('X') if value.eq_ignore_ascii_case("COLORRANGE=FULL") => is_full_range = true,
+                // the interlacing, pixel aspect ratio and the other extensions don't change the frames
+                _ => {}
+            }
+        }
+        if width == 0 || height == 0 {
+            return Err("The Y4M header has no width or height".to_string());
+        }
+        // every plane is at most the size of the luma plane, and the alpha plane is the fourth
+        get_frame_buffer_size(width, height, 4)?;
+
+        return Ok(Y4mReader {
+            reader,
+            width,
+            height,
+            frame_rate,
+            color_space,
+            is_full_range,
+        });
+    }
+}
+
+impl<R: Read> VideoFrameReader for Y4mReader<R> {
+    fn read_frame(&mut self) -> Result<Option<DynamicImage>, String> {
+        let frame_header = match read_y4m_line(&mut self.reader)? {
+            Some(frame_header) => frame_header,
+            None => return Ok(None),
+        };
+        if !frame_header.starts_with("FRAME") {
+            return Err(format!("Invalid Y4M frame header: {}", frame_header));
+        }
+
+        let width = self.width as usize;
+        let mut luma_plane = vec![0; get_frame_buffer_size(self.width, self.height, 1)?];
+        read_y4m_plane(&mut self.reader, &mut luma_plane)?;
+
+        let (subsampling_x, subsampling_y) = match self.color_space.get_subsampling() {
+            Some(subsampling) => subsampling,
+            None => {
+                let gray_img = GrayImage::from_fn(self.width, self.height, |x, y| {
+                    let luma = luma_plane[y as usize * width + x as usize];
+                    image::Luma([self.expand_luma(luma)])
+                });
+                return Ok(Some(DynamicImage::ImageLuma8(gray_img)));
+            }
+        };
+        let chroma_width = self.width.div_ceil(subsampling_x) as usize;
+        let chroma_height = self.height.div_ceil(subsampling_y) as usize;
+        let mut cb_plane = vec![0; chroma_width * chroma_height];
+        let mut cr_plane = vec![0; chroma_width * chroma_height];
+        read_y4m_plane(&mut self.reader, &mut cb_plane)?;
+        read_y4m_plane(&mut self.reader, &mut cr_plane)?;
+        let alpha_plane = if self.color_space == Y4mColorSpace::Yuv444Alpha {
+            let mut alpha_plane = vec![0; luma_plane.len()];
+            read_y4m_plane(&mut self.reader, &mut alpha_plane)?;
+            Some(alpha_plane)
+        } else {
+            None
+        };
+
+        let rgba_img = RgbaImage::from_fn(self.width, self.height, |x, y| {
+            let chroma_index =
+                (y / subsampling_y) as usize * chroma_width + (x / subsampling_x) as usize;
+            let index = y as usize * width + x as usize;
+            let rgb = self.yuv_to_rgb(
+                luma_plane[index],
+                cb_plane[chroma_index],
+                cr_plane[chroma_index],
+            );
+            let alpha = alpha_plane
+                .as_ref()
+                .map_or(255, |alpha_plane| alpha_plane[index]);
+            image::Rgba([rgb[0], rgb[1], rgb[2], alpha])
+        });
+        if alpha_plane.is_some() {
+            return Ok(Some(DynamicImage::ImageRgba8(rgba_img)));
+        }
+        return Ok(Some(DynamicImage::ImageRgb8(
+            DynamicImage::ImageRgba8(rgba_img).into_rgb8(),
+        )));
+    }
+
+    fn get_frame_rate(&self) -> Option<f32> {
+        return self.frame_rate;
+    }
+}
+
+impl<R: Read> Y4mReader<R> {
+    // BT.601, in fixed point with 8 fractional bits
+    fn yuv_to_rgb(&self, y: u8, cb: u8, cr: u8) -> [u8; 3] {
+        let d = cb as i32 - 128;
+        let e = cr as i32 - 128;
+        let (c, r, g, b) = if self.is_full_range {
+            (y as i32 * 256, 359 * e, -88 * d - 183 * e, 454 * d)
+        } else {
+            ((y as i32 - 16) * 298, 409 * e, -100 * d - 208 * e, 516 * d)
+        };
+        return [
+            ((c + r + 128) >> 8).clamp(0, 255) as u8,
+            ((c + g + 128) >> 8).clamp(0, 255) as u8,
+            ((c + b + 128) >> 8).clamp(0, 255) as u8,
+        ];
+    }
+
+    fn expand_luma(&self, y: u8) -> u8 {
+        if self.is_full_range {
+            return y;
+        }
+        return (((y as i32 - 16) * 298 + 128) >> 8).clamp(0, 255) as u8;
+    }
+}
+
+// Converts a Y4M video file to ASCII art gif.
+pub fn y4m_to_gif(
+    path: &str,
+    character_type: CharacterType,
+    output_directory: Option<&str>,
+    filename: Option<&str>,
+    setting_option: SettingOption,
+) -> Result<String, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open Y4M: {}", e))?;
+    let frame_reader = Y4mReader::new(BufReader::new(file))?;
+
+    let output_path = get_output_path(output_directory, filename, "gif")?;
+    let output_file = File::create(&output_path)
+        .map_err(|e| format!("Failed to create {}: {}", output_path, e))?;
+    let result = video_to_ascii_gif(
+        frame_reader,
+        BufWriter::new(output_file),
+        character_type,
+        setting_option,
+    )
+    .and_then(|writer| {
+        writer
+            .into_inner()
+            .map_err(|e| format!("Failed to write {}: {}", output_path, e))
+    });
+    // the partly written output file is removed if the conversion failed
+    if let Err(e) = result {
+        let _ = remove_file(&output_path);
+        return Err(e);
+    }
+
+    return Ok(get_saved_message("Gif", output_directory, filename, "gif"));
+}
+
+// convert the frames of a video stream to an ascii art gif written to `writer`,
+// with the same per-frame conversion as `gif_to_gif`
+//
+// the frames are read and converted `frame_window` at a time, so a long stream never needs to be held in memory
+// the delay of every frame is taken from the frame rate of the stream, `frame_selection` is not used
+pub fn video_to_ascii_gif<F: VideoFrameReader, W: Write>(
+    frame_reader: F,
+    writer: W,
+    character_type: CharacterType,
+    setting_option: SettingOption,
+) -> Result<W, String> {
+    return video_to_ascii_gif_with_progress(
+        frame_reader,
+        writer,
+        character_type,
+        setting_option,
+        ProgressOption::new(),
+    );
+}
+
+// same as `video_to_ascii_gif`, with the progress reported and a cancellation token checked for every frame
+pub fn video_to_ascii_gif_with_progress<F: VideoFrameReader, W: Write>(
+    mut frame_reader: F,
+    writer: W,
+    character_type: CharacterType,
+    setting_option: SettingOption,
+    progress_option: ProgressOption,
+) -> Result<W, String> {
+    let progress_tracker = ProgressTracker::new(progress_option);
+    // the rgb frames carry their own palette, the gray frames share the grayscale color map
+    let color_map = if setting_option.is_color {
+        vec![]
+    } else {
+        get_gray_color_map(setting_option.color_theme)
+    };
+    let mut encoder = GifStreamEncoder::new(writer, &color_map);
+    let mut delay_timer = GifDelayTimer::new(get_video_frame_rate(&frame_reader));
+
+    loop {
+        let mut frame_list: Vec<(DynamicImage, u16)> = vec![];
+        while frame_list.len() < setting_option.frame_window.max(1) {
+            progress_tracker.check_cancelled()?;
+            match frame_reader.read_frame()? {
+                Some(frame) => {
+                    frame_list.push((frame, delay_timer.next_delay()));
+                    progress_tracker.add_decoded_frames(1);
+                }
+                None => break,
+            }
+        }
+        if frame_list.is_empty() {
+            break;
+        }
+
+        let gif_frame_list: Vec<Frame<'static>> = frame_list
+            .par_iter()
+            .map(|(frame, delay)| {
+                progress_tracker.check_cancelled()?;
+                setting_option
+                    .preprocess
+                    .get_output_size(frame.width(), frame.height())?;
+                let mut gif_frame = if setting_option.is_color {
+                    let rgb_ascii_img =
+                        rgb_to_rgb_ascii_img(frame, character_type, setting_option.clone());
+                    rgba_img_to_gif_frame(&rgb_ascii_img, setting_option.palette.as_deref())?
+                } else {
+                    luma_img_to_gif_frame(grayscale_to_ascii_img(
+                        frame,
+                        character_type,
+                        setting_option.clone(),
+                    ))?
+                };
+                gif_frame.delay = *delay;
+                progress_tracker.add_converted_frames(1);
+                Ok(gif_frame)
+            })
+            .collect::<Result<_, String>>()?;
+        drop(frame_list);
+
+        encoder.write_frame_window(&gif_frame_list, &progress_tracker)?;
+    }
+
+    return encoder.finish();
+}
+
+// play the frames of a video stream as ascii art in the terminal, at the frame rate of the stream
+//
+// a frame that took longer than the frame rate to convert is shown late instead of being skipped
+pub fn video_to_terminal<F: VideoFrameReader>(
+    mut frame_reader: F,
+    character_type: CharacterType,
+    setting_option: SettingOption,
+    terminal: &Terminal,
+) -> Result<(), String> {
+    let frame_duration = Duration::from_secs_f32(1.0 / get_video_frame_rate(&frame_reader));
+    let mut stdout = stdout().lock();
+
+    terminal.start_live_preview(&mut stdout)?;
+    let start_time = Instant::now();
+    let mut num_frames: u32 = 0;
+    let result = loop {
+        let frame = match frame_reader.read_frame() {
+            Ok(Some(frame)) => frame,
+            Ok(None) => break Ok(()),
+            Err(e) => break Err(e),
+        };
+        if let Err(e) = setting_option
+            .preprocess
+            .get_output_size(frame.width(), frame.height())
+        {
+            break Err(e);
+        }
+        let ascii = terminal.render(&frame, character_type, &setting_option);
+        if let Err(e) = terminal.draw_live_frame(&mut stdout, &ascii) {
+            break Err(e);
+        }
+
+        num_frames += 1;
+        let next_frame_time = start_time + frame_duration * num_frames;
+        let now = Instant::now();
+        if next_frame_time > now {
+            sleep(next_frame_time - now);
+        }
+    };
+    // the cursor is shown again even if the stream failed
+    terminal.end_live_preview(&mut stdout)?;
+
+    return result;
+}
+
+// the frame rate of the stream, a missing, zero, negative or infinite frame rate is replaced by the default
+fn get_video_frame_rate<F: VideoFrameReader>(frame_reader: &F) -> f32 {
+    return frame_reader
+        .get_frame_rate()
+        .filter(|frame_rate| frame_rate.is_finite() && *frame_rate > 0.0)
+        .map_or(DEFAULT_VIDEO_FRAME_RATE, |frame_rate| {
+            frame_rate.max(MIN_VIDEO_FRAME_RATE)
+        });
+}
+
+// the number of bytes of a frame, a frame larger than `MAX_VIDEO_FRAME_SIZE` is rejected
+// before its buffer is allocated
+fn get_frame_buffer_size(width: u32, height: u32, bytes_per_pixel: usize) -> Result<usize, String> {
+    if width > MAX_VIDEO_FRAME_SIZE || height > MAX_VIDEO_FRAME_SIZE {
+        return Err(format!(
+            "The frame is {}x{}, at most {}x{} is supported",
+            width, height, MAX_VIDEO_FRAME_SIZE, MAX_VIDEO_FRAME_SIZE
+        ));
+    }
+    return (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|num_pixels| num_pixels.checked_mul(bytes_per_pixel))
+        .ok_or(format!("The frame of {}x{} is too large", width, height));
+}
+
+// fill the buffer with the next frame, `false` if the stream ended before the frame
+fn read_frame_buffer<R: Read>(reader: &mut R, buffer: &mut [u8]) -> Result<bool, String> {
+    let mut num_bytes = 0;
+    while num_bytes < buffer.len() {
+        match reader.read(&mut buffer[num_bytes..]) {
+            Ok(0) => break,
+            Ok(n) => num_bytes += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(format!("Failed to read frame: {}", e)),
+        }
+    }
+    if num_bytes == 0 {
+        return Ok(false);
+    }
+    if num_bytes < buffer.len() {
+        return Err(format!(
+            "The stream ended in the middle of a frame ({} of {} bytes)",
+            num_bytes,
+            buffer.len()
+        ));
+    }
+    return Ok(true);
+}
+
+fn read_y4m_plane<R: Read>(reader: &mut R, plane: &mut [u8]) -> Result<(), String> {
+    if !read_frame_buffer(reader, plane)? {
+        return Err("The Y4M stream ended in the middle of a frame".to_string());
+    }
+    return Ok(());
+}
+
+// read a header line up to the `\n`, `None` if the stream ended before it
+fn read_y4m_line<R: Read>(reader: &mut R) -> Result<Option<String>, String> {
+    let mut line: Vec<u8> = vec![];
+    let mut byte = [0; 1];
+    loop {
+        match reader.read(&mut byte) {
+            Ok(0) if line.is_empty() => return Ok(None),
+            Ok(0) => return Err("The Y4M stream ended in the middle of a header".to_string()),
+            Ok(_) if byte[0] == b'\n' => break,
+            Ok(_) => line.push(byte[0]),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(format!("Failed to read Y4M: {}", e)),
+        }
+        if line.len() > MAX_Y4M_HEADER_LENGTH {
+            return Err("The Y4M header is too long".to_string());
+        }
+    }
+    return Ok(Some(String::from_utf8_lossy(&line).to_string()));
+}
+
+// a ratio like `30000:1001`
+fn parse_ratio(value: &str) -> Option<f32> {
+    let (numerator, denominator) = value.split_once(':')?;
+    let numerator: f32 = numerator.parse().ok()?;
+    let denominator: f32 = denominator.parse().ok()?;
+    if denominator == 0.0 {
+        return None;
+    }
+    return Some(numerator / denominator);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_y4m_header() {
+        let stream: &[u8] = b"YUV4MPEG2 W4 H2 F30000:1001 Ip A1:1 C422 XCOLORRANGE=FULL\n";
+        let reader = Y4mReader::new(stream).unwrap();
+        assert_eq!((reader.width, reader.height), (4, 2));
+        assert_eq!(reader.color_space, Y4mColorSpace::Yuv422);
+        assert!(reader.is_full_range);
+        assert!((reader.get_frame_rate().unwrap() - 29.97).abs() < 0.01);
+
+        assert!(Y4mReader::new(&b"YUV4MPEG2 W4\n"[..]).is_err());
+        assert!(Y4mReader::new(&b"YUV4MPEG2 W4 H2 C411\n"[..]).is_err());
+        assert!(Y4mReader::new(&b"P6 W4 H2\n"[..]).is_err());
+    }
+
+    #[test]
+    fn read_y4m_420_frame() {
+        // a 2x2 frame with a single chroma pixel, the black and white luma keep their color
+        let mut stream = b"YUV4MPEG2 W2 H2 F25:1 C420jpeg\nFRAME\n".to_vec();
+        stream.extend_from_slice(&[16, 235, 235, 16, 128, 128]);
+        let mut reader = Y4mReader::new(stream.as_slice()).unwrap();
+        assert_eq!(reader.get_frame_rate(), Some(25.0));
+
+        let rgb_img = reader.read_frame().unwrap().unwrap().into_rgb8();
+        assert_eq!(rgb_img.dimensions(), (2, 2));
+        assert_eq!(rgb_img.get_pixel(0, 0).0, [0, 0, 0]);
+        assert_eq!(rgb_img.get_pixel(1, 0).0, [255, 255, 255]);
+        assert_eq!(rgb_img.get_pixel(0, 1).0, [255, 255, 255]);
+        assert_eq!(rgb_img.get_pixel(1, 1).0, [0, 0, 0]);
+        assert_eq!(reader.read_frame().unwrap(), None);
+    }
+
+    #[test]
+    fn read_y4m_mono_frame() {
+        let mut stream = b"YUV4MPEG2 W2 H1 Cmono\nFRAME\n".to_vec();
+        stream.extend_from_slice(&[16, 235]);
+        stream.extend_from_slice(b"FRAME\n");
+        stream.extend_from_slice(&[126, 0]);
+        let mut reader = Y4mReader::new(stream.as_slice()).unwrap();
+
+        let gray_img = reader.read_frame().unwrap().unwrap().into_luma8();
+        assert_eq!(gray_img.as_raw(), &vec![0, 255]);
+        let gray_img = reader.read_frame().unwrap().unwrap().into_luma8();
+        assert_eq!(gray_img.as_raw(), &vec![128, 0]);
+        assert_eq!(reader.read_frame().unwrap(), None);
+    }
+
+    #[test]
+    fn reject_invalid_y4m_header() {
+        // a tag that is not ASCII is skipped instead of splitting a character
+        let stream = "YUV4MPEG2 W2 H2 é Ĥ1\n".as_bytes();
+        assert_eq!(Y4mReader::new(stream).unwrap().height, 2);
+
+        let stream = format!("YUV4MPEG2 W{} H2\n", MAX_VIDEO_FRAME_SIZE + 1);
+        assert!(Y4mReader::new(stream.as_bytes()).is_err());
+        assert!(Y4mReader::new(&b"YUV4MPEG2 W4294967295 H4294967295\n"[..]).is_err());
+        assert!(RawFrameReader::new(&[][..], 1, u32::MAX, RawPixelFormat::Rgba).is_err());
+    }
+
+    #[test]
+    fn replace_invalid_frame_rate() {
+        for frame_rate in [0.0, -25.0, f32::NAN] {
+            let reader = RawFrameReader::new(&[][..], 1, 1, RawPixelFormat::Rgb24)
+                .unwrap()
+                .with_frame_rate(frame_rate);
+            assert_eq!(get_video_frame_rate(&reader), DEFAULT_VIDEO_FRAME_RATE);
+        }
+        let reader = RawFrameReader::new(&[][..], 1, 1, RawPixelFormat::Rgb24)
+            .unwrap()
+            .with_frame_rate(1e-30);
+        assert_eq!(get_video_frame_rate(&reader), MIN_VIDEO_FRAME_RATE);
+    }
+
+    #[test]
+    fn read_truncated_y4m_frame() {
+        let mut stream = b"YUV4MPEG2 W2 H2 Cmono\nFRAME\n".to_vec();
+        stream.extend_from_slice(&[16, 235]);
+        let mut reader = Y4mReader::new(stream.as_slice()).unwrap();
+        assert!(reader.read_frame().is_err());
+    }
+
+    #[test]
+    fn raw_frames_to_ascii_gif() {
+        let stream = vec![128; 8 * 8 * 3 * 3];
+        let frame_reader = RawFrameReader::new(stream.as_slice(), 8, 8, RawPixelFormat::Rgb24)
+            .unwrap()
+            .with_frame_rate(10.0);
+        let gif = video_to_ascii_gif(
+            frame_reader,
+            vec![],
+            CharacterType::Simple,
+            SettingOption::rgb(4),
+        )
+        .unwrap();
+
+        let mut decoder = gif::DecodeOptions::new().read_info(gif.as_slice()).unwrap();
+        let mut delay_list = vec![];
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delay_list.push(frame.delay);
+        }
+        assert_eq!(delay_list, vec![10, 10, 10]);
+    }
+}
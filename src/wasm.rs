@@ -0,0 +1,219 @@
+// the JS bindings of the `wasm` feature, built for wasm32-unknown-unknown as a cdylib
+// and bound with the wasm-bindgen CLI (see docs/usage.md)
+//
+// everything is done in memory, the images and GIFs are given and returned as the bytes of the
+// encoded file (a `Uint8Array` in JS) so the filesystem is never used
+use std::io::Cursor;
+
+use gif::DecodeOptions;
+use image::{DynamicImage, ImageFormat};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    config::{RenderConfig, RenderConfigBuilder},
+    decode_gif, dynamic_image_to_ascii_img, grayscale_to_ascii,
+    preprocess::{apply_preprocess, load_image_from_memory, Preprocess},
+    stream_frames_to_ascii_gray_gif, stream_frames_to_ascii_rgb_gif, CharacterType, ColorTheme,
+    Palette, PalettePreset,
+};
+
+// the settings of a conversion, `new RenderConfig()` start with the defaults of `RenderConfig::builder()`
+//
+// the names are the same as the C API, e.g. "zh_simplified" for the character type and "amber" for the color theme
+#[wasm_bindgen(js_name = RenderConfig)]
+pub struct WasmRenderConfig {
+    builder: RenderConfigBuilder,
+}
+
+#[wasm_bindgen(js_class = RenderConfig)]
+impl WasmRenderConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WasmRenderConfig {
+        return WasmRenderConfig {
+            builder: RenderConfig::builder(),
+        };
+    }
+
+    #[wasm_bindgen(js_name = setCharacterType)]
+    pub fn set_character_type(&mut self, name: &str) -> Result<(), JsError> {
+        let character_type = CharacterType::from_name(name)
+            .ok_or_else(|| JsError::new(&format!("Unknown character type: {}", name)))?;
+        self.update(|builder| builder.character_type(character_type));
+        return Ok(());
+    }
+
+    #[wasm_bindgen(js_name = setNumCols)]
+    pub fn set_num_cols(&mut self, num_cols: u32) {
+        self.update(|builder| builder.num_cols(num_cols));
+    }
+
+    #[wasm_bindgen(js_name = setColor)]
+    pub fn set_color(&mut self, is_color: bool) {
+        self.update(|builder| builder.color(is_color));
+    }
+
+    #[wasm_bindgen(js_name = setWhiteBg)]
+    pub fn set_white_bg(&mut self, is_white_bg: bool) {
+        self.update(|builder| builder.white_bg(is_white_bg));
+    }
+
+    #[wasm_bindgen(js_name = setTransparentBg)]
+    pub fn set_transparent_bg(&mut self, is_transparent_bg: bool) {
+        self.update(|builder| builder.transparent_bg(is_transparent_bg));
+    }
+
+    #[wasm_bindgen(js_name = setColorTheme)]
+    pub fn set_color_theme(&mut self, name: &str) -> Result<(), JsError> {
+        let color_theme = ColorTheme::from_name(name)
+            .ok_or_else(|| JsError::new(&format!("Unknown color theme: {}", name)))?;
+        self.update(|builder| builder.color_theme(color_theme));
+        return Ok(());
+    }
+
+    #[wasm_bindgen(js_name = setPalette)]
+    pub fn set_palette(&mut self, name: &str) -> Result<(), JsError> {
+        let palette_preset = PalettePreset::from_name(name)
+            .ok_or_else(|| JsError::new(&format!("Unknown palette: {}", name)))?;
+        self.update(|builder| builder.palette(Palette::from_preset(palette_preset)));
+        return Ok(());
+    }
+
+    #[wasm_bindgen(js_name = setFrameWindow)]
+    pub fn set_frame_window(&mut self, frame_window: usize) {
+        self.update(|builder| builder.frame_window(frame_window));
+    }
+
+    fn update<F: FnOnce(RenderConfigBuilder) -> RenderConfigBuilder>(&mut self, update: F) {
+        self.builder = update(self.builder.clone());
+    }
+
+    // the options are validated when they are used, so the setters never fail on a bad combination
+    fn build(&self) -> Result<RenderConfig, JsError> {
+        return self.builder.clone().build().map_err(|e| JsError::new(&e));
+    }
+}
+
+impl Default for WasmRenderConfig {
+    fn default() -> WasmRenderConfig {
+        return WasmRenderConfig::new();
+    }
+}
+
+// Converts an encoded image (PNG, JPEG, ...) to ASCII art text, only the "simple" and "complex" character types are supported.
+#[wasm_bindgen(js_name = imageToText)]
+pub fn wasm_image_to_text(config: &WasmRenderConfig, data: &[u8]) -> Result<String, JsError> {
+    let render_config = config.build()?;
+    let preprocess = render_config.setting_option.preprocess;
+    let img = load_image(data, preprocess)?;
+    let img = apply_preprocess(&img, preprocess);
+    let complex = match render_config.character_type {
+        CharacterType::Simple => false,
+        CharacterType::Complex => true,
+        _ => {
+            return Err(JsError::new(
+                "Only the simple and complex character types can be converted to text",
+            ))
+        }
+    };
+    return Ok(grayscale_to_ascii(
+        &img,
+        render_config.setting_option.num_cols,
+        complex,
+    ));
+}
+
+// Converts an encoded image (PNG, JPEG, ...) to the PNG of the ASCII art image.
+#[wasm_bindgen(js_name = imageToImage)]
+pub fn wasm_image_to_image(config: &WasmRenderConfig, data: &[u8]) -> Result<Vec<u8>, JsError> {
+    let render_config = config.build()?;
+    let img = load_image(data, render_config.setting_option.preprocess)?;
+    let ascii_img = dynamic_image_to_ascii_img(
+        &img,
+        render_config.character_type,
+        render_config.setting_option,
+    );
+
+    let mut png_buffer = Cursor::new(Vec::new());
+    ascii_img
+        .write_to(&mut png_buffer, ImageFormat::Png)
+        .map_err(|e| JsError::new(&format!("Failed to encode image: {}", e)))?;
+    return Ok(png_buffer.into_inner());
+}
+
+// Converts an encoded GIF to an ASCII art GIF.
+#[wasm_bindgen(js_name = gifToGif)]
+pub fn wasm_gif_to_gif(config: &WasmRenderConfig, data: &[u8]) -> Result<Vec<u8>, JsError> {
+    let render_config = config.build()?;
+    let character_type = render_config.character_type;
+    let setting_option = render_config.setting_option;
+    let decoder =
+        decode_gif(Cursor::new(data), DecodeOptions::new()).map_err(|e| JsError::new(&e))?;
+    let result = if setting_option.is_color {
+        stream_frames_to_ascii_rgb_gif(decoder, Vec::new(), character_type, setting_option)
+    } else {
+        stream_frames_to_ascii_gray_gif(decoder, Vec::new(), character_type, setting_option)
+    };
+    return result.map_err(|e| JsError::new(&e));
+}
+
+// decode the image and check that the preprocess doesn't make it too large
+fn load_image(data: &[u8], preprocess: Preprocess) -> Result<DynamicImage, JsError> {
+    let img = load_image_from_memory(data, preprocess.is_auto_orient)
+        .map_err(|e| JsError::new(&format!("Failed to decode image: {}", e)))?;
+    preprocess
+        .get_output_size(img.width(), img.height())
+        .map_err(|e| JsError::new(&e))?;
+    return Ok(img);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_png_data() -> Vec<u8> {
+        let mut png_data = Cursor::new(vec![]);
+        DynamicImage::new_rgb8(40, 40)
+            .write_to(&mut png_data, ImageFormat::Png)
+            .unwrap();
+        return png_data.into_inner();
+    }
+
+    fn get_gif_data() -> Vec<u8> {
+        let mut gif_data = vec![];
+        {
+            let mut encoder = gif::Encoder::new(&mut gif_data, 40, 40, &[0, 0, 0]).unwrap();
+            let frame = gif::Frame::from_indexed_pixels(40, 40, vec![0; 40 * 40], None);
+            encoder.write_frame(&frame).unwrap();
+            encoder.write_frame(&frame).unwrap();
+        }
+        return gif_data;
+    }
+
+    #[test]
+    fn convert_image() {
+        let png_data = get_png_data();
+        let mut config = WasmRenderConfig::new();
+        config.set_num_cols(10);
+
+        let text = wasm_image_to_text(&config, &png_data).unwrap();
+        assert_eq!(text.lines().count(), 5);
+
+        config.set_color(true);
+        let png_buffer = wasm_image_to_image(&config, &png_data).unwrap();
+        let ascii_img = image::load_from_memory(&png_buffer).unwrap();
+        assert!(ascii_img.width() > 0 && ascii_img.height() > 0);
+    }
+
+    #[test]
+    fn convert_gif() {
+        let gif_data = get_gif_data();
+        let mut config = WasmRenderConfig::new();
+        config.set_num_cols(10);
+
+        let gif_buffer = wasm_gif_to_gif(&config, &gif_data).unwrap();
+        let decoder = DecodeOptions::new()
+            .read_info(Cursor::new(gif_buffer))
+            .unwrap();
+        assert_eq!(decoder.into_iter().count(), 2);
+    }
+}
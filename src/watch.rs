@@ -0,0 +1,624 @@
+use std::{
+    collections::HashMap,
+    fs::create_dir_all,
+    io::stdout,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, RecvTimeoutError},
+    time::{Duration, Instant},
+};
+
+use notify::{event::EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::{
+    batch::{
+        collect_file_list, convert_batch_file, get_batch_file_kind, get_batch_output_path,
+        BatchFileResult,
+    },
+    config::{RenderConfig, RenderConfigBuilder},
+    preprocess::open_image,
+    terminal::Terminal,
+    BatchOutput, CancellationToken, CharacterType, SettingOption,
+};
+
+// how long a file must stay unchanged before it is converted, an editor often write a file in several steps
+pub const DEFAULT_WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+// how often the cancellation token is checked while no file is changing
+const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// decide how the watched files are converted
+#[derive(Debug, Clone)]
+pub struct WatchOption {
+    // what the still images are converted to, GIFs are always converted to GIF
+    pub output: BatchOutput,
+    // watch the sub directories of a watched directory too, their structure is mirrored in the output directory
+    pub is_recursive: bool,
+    pub debounce: Duration,
+    // redraw the ascii art of the last changed file in the terminal, the files are still saved
+    pub is_terminal_preview: bool,
+    // stop watching from another thread, e.g. on Ctrl-C
+    pub cancellation_token: Option<CancellationToken>,
+}
+
+impl WatchOption {
+    pub fn new() -> WatchOption {
+        return WatchOption {
+            output: BatchOutput::Image,
+            is_recursive: true,
+            debounce: DEFAULT_WATCH_DEBOUNCE,
+            is_terminal_preview: false,
+            cancellation_token: None,
+        };
+    }
+
+    pub fn with_output(mut self, output: BatchOutput) -> WatchOption {
+        self.output = output;
+        return self;
+    }
+
+    pub fn with_recursive(mut self, is_recursive: bool) -> WatchOption {
+        self.is_recursive = is_recursive;
+        return self;
+    }
+
+    pub fn with_debounce(mut self, debounce: Duration) -> WatchOption {
+        self.debounce = debounce;
+        return self;
+    }
+
+    pub fn with_terminal_preview(mut self, is_terminal_preview: bool) -> WatchOption {
+        self.is_terminal_preview = is_terminal_preview;
+        return self;
+    }
+
+    pub fn with_cancellation_token(mut self, cancellation_token: CancellationToken) -> WatchOption {
+        self.cancellation_token = Some(cancellation_token);
+        return self;
+    }
+}
+
+impl Default for WatchOption {
+    fn default() -> WatchOption {
+        return WatchOption::new();
+    }
+}
+
+// a watched file or directory, the paths are canonical so they can be compared with the paths of the events
+struct WatchTarget {
+    directory: PathBuf,
+    // the watched file of the directory, `None` when the whole directory is watched
+    file_name: Option<PathBuf>,
+}
+
+// Converts the files once and again every time they change, until the cancellation token is cancelled.
+//
+// every path is an image, a GIF or a directory of them, see `watch_with_callback` to get the result of every conversion
+pub fn watch(
+    path_list: &[String],
+    output_directory: &str,
+    character_type: CharacterType,
+    setting_option: SettingOption,
+    watch_option: &WatchOption,
+) -> Result<(), String> {
+    return watch_with_callback(
+        path_list,
+        output_directory,
+        character_type,
+        setting_option,
+        watch_option,
+        |_| {},
+    );
+}
+
+// same as `watch`, with `on_render` called after every conversion with its result
+//
+// a watched file is saved in the output directory with the same name, e.g. `cat.png` -> `output/cat.png`,
+// and the files of a watched directory keep their sub directory, the same as `batch_convert`
+//
+// the output directory is left out of a watched directory it is inside of, so the ascii art is not converted again,
+// and it can't be a watched directory itself or the directory of a watched file
+pub fn watch_with_callback<F>(
+    path_list: &[String],
+    output_directory: &str,
+    character_type: CharacterType,
+    setting_option: SettingOption,
+    watch_option: &WatchOption,
+    mut on_render: F,
+) -> Result<(), String>
+where
+    F: FnMut(&BatchFileResult),
+{
+    if path_list.is_empty() {
+        return Err("No path to watch".to_string());
+    }
+    let render_config =
+        RenderConfigBuilder::from_setting_option(character_type, setting_option).build()?;
+    // the output directory is created first so it can be left out when it is inside a watched directory
+    create_dir_all(output_directory)
+        .map_err(|e| format!("Failed to create directory {}: {}", output_directory, e))?;
+    let output_directory = canonicalize(Path::new(output_directory))?;
+    let target_list = get_watch_target_list(path_list)?;
+    for target in &target_list {
+        check_watch_target(
+            target,
+            &output_directory,
+            render_config.setting_option.is_color,
+            watch_option.output,
+        )?;
+    }
+
+    let (sender, receiver) = channel();
+    let mut watcher = RecommendedWatcher::new(sender, notify::Config::default())
+        .map_err(|e| format!("Failed to start watching: {}", e))?;
+    for target in &target_list {
+        // a single file is watched through its directory, as editors often replace the file when saving
+        let recursive_mode = if target.file_name.is_none() && watch_option.is_recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        watcher
+            .watch(&target.directory, recursive_mode)
+            .map_err(|e| format!("Failed to watch {}: {}", target.directory.display(), e))?;
+    }
+
+    let initial_file_list = get_initial_file_list(&target_list, &output_directory, watch_option)?;
+    let terminal = Terminal::detect();
+    let mut renderer = WatchRenderer {
+        output_directory: &output_directory,
+        render_config: &render_config,
+        watch_option,
+        terminal,
+    };
+    if watch_option.is_terminal_preview {
+        terminal.start_live_preview(&mut stdout())?;
+    }
+
+    // every file is converted once before waiting for changes
+    let mut result = Ok(());
+    for (directory, relative_path) in initial_file_list {
+        if let Some(file_result) = renderer.render(&directory, &relative_path) {
+            on_render(&file_result);
+        }
+    }
+
+    // the last time every changed file was changed, it is converted once it has been quiet for `debounce`
+    let mut pending_path_map: HashMap<PathBuf, Instant> = HashMap::new();
+    loop {
+        let is_cancelled = watch_option
+            .cancellation_token
+            .as_ref()
+            .is_some_and(|cancellation_token| cancellation_token.is_cancelled());
+        if is_cancelled {
+            break;
+        }
+
+        match receiver.recv_timeout(WATCH_POLL_INTERVAL) {
+            Ok(Ok(event)) => {
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    for path in event.paths {
+                        pending_path_map.insert(path, Instant::now());
+                    }
+                }
+            }
+            Ok(Err(e)) => {
+                result = Err(format!("Failed to watch: {}", e));
+                break;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        let mut ready_path_list: Vec<PathBuf> = pending_path_map
+            .iter()
+            .filter(|(_, changed_at)| changed_at.elapsed() >= watch_option.debounce)
+            .map(|(path, _)| path.clone())
+            .collect();
+        ready_path_list.sort();
+        for path in ready_path_list {
+            pending_path_map.remove(&path);
+            let (directory, relative_path) =
+                match find_watched_file(&target_list, &output_directory, &path, watch_option) {
+                    Some(watched_file) => watched_file,
+                    None => continue,
+                };
+            if let Some(file_result) = renderer.render(&directory, &relative_path) {
+                on_render(&file_result);
+            }
+        }
+    }
+
+    if watch_option.is_terminal_preview {
+        terminal.end_live_preview(&mut stdout())?;
+    }
+    return result;
+}
+
+// convert a changed file and draw it in the terminal if there is a preview
+struct WatchRenderer<'a> {
+    output_directory: &'a Path,
+    render_config: &'a RenderConfig,
+    watch_option: &'a WatchOption,
+    terminal: Terminal,
+}
+
+impl WatchRenderer<'_> {
+    // `None` if the file is not an image or a GIF, or it was removed before it could be converted
+    fn render(&mut self, directory: &Path, relative_path: &Path) -> Option<BatchFileResult> {
+        let path = directory.join(relative_path);
+        if !path.is_file() {
+            return None;
+        }
+        let file_kind = get_batch_file_kind(&path)?;
+        let file_result = convert_batch_file(
+            directory,
+            self.output_directory,
+            relative_path,
+            file_kind,
+            self.render_config,
+            self.watch_option.output,
+        );
+
+        if self.watch_option.is_terminal_preview {
+            let message = match &file_result.result {
+                Ok(message) => message.clone(),
+                Err(e) => format!("{}: {}", path.display(), e),
+            };
+            // a GIF is previewed with its first frame
+            let is_auto_orient = self.render_config.setting_option.preprocess.is_auto_orient;
+            let frame = match open_image(&path.to_string_lossy(), is_auto_orient) {
+                Ok(img) => format!(
+                    "{}\n{}",
+                    self.terminal.render(
+                        &img,
+                        self.render_config.character_type,
+                        &self.render_config.setting_option,
+                    ),
+                    message
+                ),
+                Err(_) => message,
+            };
+            let _ = self.terminal.draw_live_frame(&mut stdout(), &frame);
+        }
+        return Some(file_result);
+    }
+}
+
+fn get_watch_target_list(path_list: &[String]) -> Result<Vec<WatchTarget>, String> {
+    let mut target_list = vec![];
+    for path in path_list {
+        let path = canonicalize(Path::new(path))?;
+        if path.is_dir() {
+            target_list.push(WatchTarget {
+                directory: path,
+                file_name: None,
+            });
+            continue;
+        }
+        let directory = path
+            .parent()
+            .ok_or(format!("Failed to watch {}", path.display()))?;
+        target_list.push(WatchTarget {
+            directory: directory.to_path_buf(),
+            file_name: path.file_name().map(PathBuf::from),
+        });
+    }
+    return Ok(target_list);
+}
+
+// the output directory can't be a watched directory, or the directory of a watched file
+// so the ascii art is never saved where it is watched, a file would otherwise be saved over itself
+fn check_watch_target(
+    target: &WatchTarget,
+    output_directory: &Path,
+    is_color: bool,
+    output: BatchOutput,
+) -> Result<(), String> {
+    let file_name = match &target.file_name {
+        Some(file_name) => file_name,
+        None if target.directory == output_directory => {
+            return Err(format!(
+                "The output directory can't be the watched directory {}",
+                target.directory.display()
+            ))
+        }
+        None => return Ok(()),
+    };
+    let path = target.directory.join(file_name);
+    if let Some(file_kind) = get_batch_file_kind(&path) {
+        let output_path = output_directory.join(get_batch_output_path(
+            file_name, file_kind, is_color, output,
+        ));
+        if output_path == path {
+            return Err(format!(
+                "The ascii art of {} would be saved over it",
+                path.display()
+            ));
+        }
+    }
+    if target.directory == output_directory {
+        return Err(format!(
+            "The output directory can't be the directory of the watched file {}",
+            path.display()
+        ));
+    }
+    return Ok(());
+}
+
+// every file of the targets, as the directory it is converted from and its path relative to it
+fn get_initial_file_list(
+    target_list: &[WatchTarget],
+    output_directory: &Path,
+    watch_option: &WatchOption,
+) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+    let mut file_list = vec![];
+    for target in target_list {
+        match &target.file_name {
+            Some(file_name) => file_list.push((target.directory.clone(), file_name.clone())),
+            None => {
+                let mut relative_path_list = vec![];
+                collect_file_list(
+                    &target.directory,
+                    Path::new(""),
+                    watch_option.is_recursive,
+                    &mut relative_path_list,
+                )?;
+                for relative_path in relative_path_list {
+                    let path = target.directory.join(&relative_path);
+                    if !is_output_of_target(target, output_directory, &path) {
+                        file_list.push((target.directory.clone(), relative_path));
+                    }
+                }
+            }
+        }
+    }
+    return Ok(file_list);
+}
+
+// the target a changed path belong to, as the directory it is converted from and its path relative to it
+fn find_watched_file(
+    target_list: &[WatchTarget],
+    output_directory: &Path,
+    path: &Path,
+    watch_option: &WatchOption,
+) -> Option<(PathBuf, PathBuf)> {
+    for target in target_list {
+        if is_output_of_target(target, output_directory, path) {
+            continue;
+        }
+        let relative_path = match path.strip_prefix(&target.directory) {
+            Ok(relative_path) => relative_path,
+            Err(_) => continue,
+        };
+        let is_watched = match &target.file_name {
+            Some(file_name) => relative_path == file_name,
+            None => watch_option.is_recursive || relative_path.components().count() == 1,
+        };
+        if is_watched {
+            return Some((target.directory.clone(), relative_path.to_path_buf()));
+        }
+    }
+    return None;
+}
+
+// a file of the output directory when it is inside the watched directory, e.g. `art/ascii/cat.png` of `art`
+//
+// a watched directory that is inside the output directory (`art` of `.`) is still watched,
+// its ascii art is saved next to it instead of in it
+fn is_output_of_target(target: &WatchTarget, output_directory: &Path, path: &Path) -> bool {
+    return target.file_name.is_none()
+        && output_directory.starts_with(&target.directory)
+        && path.starts_with(output_directory);
+}
+
+fn canonicalize(path: &Path) -> Result<PathBuf, String> {
+    return path
+        .canonicalize()
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{remove_dir_all, write};
+
+    use super::*;
+
+    fn get_directory_target(directory: &str) -> WatchTarget {
+        return WatchTarget {
+            directory: PathBuf::from(directory),
+            file_name: None,
+        };
+    }
+
+    fn get_file_target(directory: &str, file_name: &str) -> WatchTarget {
+        return WatchTarget {
+            directory: PathBuf::from(directory),
+            file_name: Some(PathBuf::from(file_name)),
+        };
+    }
+
+    #[test]
+    fn output_of_target() {
+        let target = get_directory_target("/art");
+        let output_directory = Path::new("/art/ascii");
+        assert!(is_output_of_target(
+            &target,
+            output_directory,
+            Path::new("/art/ascii/cat.png")
+        ));
+        assert!(!is_output_of_target(
+            &target,
+            output_directory,
+            Path::new("/art/cat.png")
+        ));
+
+        // a watched directory inside the output directory is still watched
+        let target = get_directory_target("/art/photo");
+        assert!(!is_output_of_target(
+            &target,
+            Path::new("/art"),
+            Path::new("/art/photo/cat.png")
+        ));
+        // a watched file is never the output of its target
+        let target = get_file_target("/art", "cat.png");
+        assert!(!is_output_of_target(
+            &target,
+            output_directory,
+            Path::new("/art/ascii/cat.png")
+        ));
+    }
+
+    #[test]
+    fn watched_file_of_path() {
+        let target_list = vec![
+            get_file_target("/photo", "cat.png"),
+            get_directory_target("/art"),
+        ];
+        let output_directory = Path::new("/art/ascii");
+        let watch_option = WatchOption::new();
+        assert_eq!(
+            find_watched_file(
+                &target_list,
+                output_directory,
+                Path::new("/photo/cat.png"),
+                &watch_option
+            ),
+            Some((PathBuf::from("/photo"), PathBuf::from("cat.png")))
+        );
+        assert_eq!(
+            find_watched_file(
+                &target_list,
+                output_directory,
+                Path::new("/photo/dog.png"),
+                &watch_option
+            ),
+            None
+        );
+        assert_eq!(
+            find_watched_file(
+                &target_list,
+                output_directory,
+                Path::new("/art/sub/dog.png"),
+                &watch_option
+            ),
+            Some((PathBuf::from("/art"), PathBuf::from("sub/dog.png")))
+        );
+        assert_eq!(
+            find_watched_file(
+                &target_list,
+                output_directory,
+                Path::new("/art/ascii/dog.png"),
+                &watch_option
+            ),
+            None
+        );
+
+        // only the files directly in the directory without recursive
+        let watch_option = WatchOption::new().with_recursive(false);
+        assert_eq!(
+            find_watched_file(
+                &target_list,
+                output_directory,
+                Path::new("/art/sub/dog.png"),
+                &watch_option
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn check_target_output_directory() {
+        let output_directory = Path::new("/art/ascii");
+        assert!(check_watch_target(
+            &get_directory_target("/art"),
+            output_directory,
+            true,
+            BatchOutput::Image
+        )
+        .is_ok());
+        assert!(check_watch_target(
+            &get_directory_target("/art/ascii"),
+            output_directory,
+            true,
+            BatchOutput::Image
+        )
+        .is_err());
+        assert!(check_watch_target(
+            &get_file_target("/art", "cat.png"),
+            output_directory,
+            true,
+            BatchOutput::Image
+        )
+        .is_ok());
+
+        // the png of a color image would be saved over the watched file
+        assert_eq!(
+            check_watch_target(
+                &get_file_target("/art", "cat.png"),
+                Path::new("/art"),
+                true,
+                BatchOutput::Image
+            ),
+            Err("The ascii art of /art/cat.png would be saved over it".to_string())
+        );
+        // cat.txt is not the watched file, but the output is still next to it
+        assert_eq!(
+            check_watch_target(
+                &get_file_target("/art", "cat.png"),
+                Path::new("/art"),
+                true,
+                BatchOutput::Text
+            ),
+            Err(
+                "The output directory can't be the directory of the watched file /art/cat.png"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn watch_invalid_target() {
+        let directory =
+            std::env::temp_dir().join(format!("rasciify_watch_invalid_{}", std::process::id()));
+        let _ = remove_dir_all(&directory);
+        create_dir_all(&directory).unwrap();
+        let file_path = directory.join("cat.png");
+        write(&file_path, "not read").unwrap();
+
+        let watch_option = WatchOption::new();
+        assert!(watch(
+            &[],
+            directory.to_str().unwrap(),
+            CharacterType::Simple,
+            SettingOption::rgb(8),
+            &watch_option
+        )
+        .is_err());
+        assert!(watch(
+            &[directory.to_string_lossy().to_string()],
+            directory.to_str().unwrap(),
+            CharacterType::Simple,
+            SettingOption::rgb(8),
+            &watch_option
+        )
+        .is_err());
+        assert!(watch(
+            &[file_path.to_string_lossy().to_string()],
+            directory.to_str().unwrap(),
+            CharacterType::Simple,
+            SettingOption::rgb(8),
+            &watch_option
+        )
+        .is_err());
+        assert!(watch(
+            &[directory.join("missing.png").to_string_lossy().to_string()],
+            directory.join("ascii").to_str().unwrap(),
+            CharacterType::Simple,
+            SettingOption::rgb(8),
+            &watch_option
+        )
+        .is_err());
+
+        remove_dir_all(&directory).unwrap();
+    }
+}